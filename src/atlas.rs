@@ -0,0 +1,298 @@
+use anyhow::{Result, Context};
+use std::path::{Path, PathBuf};
+use std::fs;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::file_operations::collect_files_with_extension;
+use crate::names::NameKind;
+use crate::workspace::LoadFailure;
+
+/// Atlas中的一个页面（对应一张PNG）
+#[derive(Debug, Clone)]
+pub struct AtlasPage {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    /// `pma:` 属性（仅4.x），3.x由查看器设置决定
+    pub pma: Option<bool>,
+}
+
+/// Atlas中的一个区域
+#[derive(Debug, Clone)]
+pub struct AtlasRegion {
+    pub name: String,
+    /// 所在页面的下标
+    pub page: usize,
+    /// 在页面中的位置，Atlas缺少位置属性时为None
+    pub bounds: Option<RegionBounds>,
+}
+
+/// 区域在页面中的位置与（旋转前的）尺寸
+#[derive(Debug, Clone, Copy)]
+pub struct RegionBounds {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// 旋转90°（或270°）打包，在页面中占据 height x width
+    pub rotated: bool,
+}
+
+impl RegionBounds {
+    /// 在页面中实际占据的宽高
+    pub fn packed_size(&self) -> (u32, u32) {
+        if self.rotated {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        }
+    }
+}
+
+/// Atlas文本格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum AtlasFormat {
+    /// Spine 3.x 导出的缩进格式（区域属性为xy、orig、offset）
+    #[serde(rename = "3.x")]
+    Legacy,
+    /// Spine 4.x 导出的紧凑格式（区域属性为bounds、offsets）
+    #[serde(rename = "4.x")]
+    Modern,
+}
+
+impl AtlasFormat {
+    pub fn label(self) -> &'static str {
+        match self {
+            AtlasFormat::Legacy => "3.x",
+            AtlasFormat::Modern => "4.x",
+        }
+    }
+
+    /// 对应的Spine主版本号
+    pub fn major(self) -> u32 {
+        match self {
+            AtlasFormat::Legacy => 3,
+            AtlasFormat::Modern => 4,
+        }
+    }
+}
+
+/// 解析后的Atlas文件
+#[derive(Debug, Clone)]
+pub struct Atlas {
+    pub path: PathBuf,
+    pub format: AtlasFormat,
+    pub pages: Vec<AtlasPage>,
+    pub regions: Vec<AtlasRegion>,
+}
+
+/// Atlas文本中的一个页面块或区域块
+#[derive(Debug, Clone)]
+pub struct AtlasBlock {
+    /// `NameKind::Page`或`NameKind::Region`
+    pub kind: NameKind,
+    pub name: String,
+    /// 块的起始行（名称行，从0开始）
+    pub start: usize,
+    /// 块的结束行（不含）
+    pub end: usize,
+}
+
+/// 并行解析目录下全部Atlas，跳过无法解析的文件
+pub fn parse_atlas_dir(atlas_dir: &Path) -> Vec<Atlas> {
+    load_atlas_dir(atlas_dir).0
+}
+
+/// 并行解析目录下全部Atlas，同时返回无法解析的文件
+pub fn load_atlas_dir(atlas_dir: &Path) -> (Vec<Atlas>, Vec<LoadFailure>) {
+    let results: Vec<Result<Atlas, LoadFailure>> = collect_files_with_extension(atlas_dir, "atlas")
+        .par_iter()
+        .map(|path| parse_atlas_file(path).map_err(|e| LoadFailure { path: path.clone(), error: format!("{:#}", e) }))
+        .collect();
+    let mut atlases = Vec::new();
+    let mut failures = Vec::new();
+    for result in results {
+        match result {
+            Ok(atlas) => atlases.push(atlas),
+            Err(failure) => failures.push(failure),
+        }
+    }
+    (atlases, failures)
+}
+
+/// 读取并解析Atlas文件
+pub fn parse_atlas_file(file_path: &Path) -> Result<Atlas> {
+    let content = fs::read_to_string(file_path)
+        .context("无法读取Atlas文件")?;
+
+    let mut atlas = parse_atlas(&content)?;
+    atlas.path = file_path.to_path_buf();
+    Ok(atlas)
+}
+
+/// 解析Atlas文本，兼容3.x（缩进格式）与4.x（紧凑格式）
+pub fn parse_atlas(content: &str) -> Result<Atlas> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut pages: Vec<AtlasPage> = Vec::new();
+    let mut regions: Vec<AtlasRegion> = Vec::new();
+
+    for block in atlas_blocks(content) {
+        match block.kind {
+            NameKind::Page => {
+                let mut page = AtlasPage {
+                    name: block.name,
+                    width: 0,
+                    height: 0,
+                    pma: None,
+                };
+                for line in &lines[block.start + 1..block.end] {
+                    let Some((key, value)) = line.trim().split_once(':') else { continue };
+                    match key.trim() {
+                        "size" => {
+                            (page.width, page.height) = parse_pair(value)
+                                .with_context(|| format!("无效的页面尺寸: {}", line.trim()))?;
+                        }
+                        "pma" => page.pma = Some(value.trim() == "true"),
+                        _ => {}
+                    }
+                }
+                pages.push(page);
+            }
+            _ => {
+                let properties = lines[block.start + 1..block.end].iter()
+                    .filter_map(|line| line.trim().split_once(':'))
+                    .map(|(key, value)| (key.trim(), value.trim()));
+                regions.push(AtlasRegion {
+                    name: block.name,
+                    page: pages.len().saturating_sub(1),
+                    bounds: parse_bounds(properties),
+                });
+            }
+        }
+    }
+
+    if pages.is_empty() {
+        anyhow::bail!("Atlas中没有任何页面");
+    }
+
+    Ok(Atlas {
+        path: PathBuf::new(),
+        format: detect_format(&lines),
+        pages,
+        regions,
+    })
+}
+
+impl Atlas {
+    /// 页面实际采用的预乘设置：4.x缺少`pma:`即为false，3.x为None（由查看器设置决定）
+    pub fn page_pma(&self, page: &AtlasPage) -> Option<bool> {
+        match (page.pma, self.format) {
+            (Some(pma), _) => Some(pma),
+            (None, AtlasFormat::Modern) => Some(false),
+            (None, AtlasFormat::Legacy) => None,
+        }
+    }
+}
+
+/// 修改4.x Atlas文本中某个页面的`pma:`属性，没有该属性时追加在页面属性末尾
+pub fn set_page_pma(content: &str, page: &str, pma: bool) -> Result<String> {
+    let block = atlas_blocks(content).into_iter()
+        .find(|block| block.kind == NameKind::Page && block.name == page)
+        .ok_or_else(|| anyhow::anyhow!("Atlas中没有页面: {}", page))?;
+
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let value = format!("pma:{}", pma);
+    let existing = (block.start + 1..block.end)
+        .find(|&i| lines[i].trim().split_once(':').map(|(key, _)| key.trim() == "pma").unwrap_or(false));
+    match existing {
+        Some(i) => lines[i] = value,
+        None => lines.insert(block.end, value),
+    }
+
+    let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut updated = lines.join(newline);
+    if content.ends_with('\n') {
+        updated.push_str(newline);
+    }
+    Ok(updated)
+}
+
+/// 将Atlas文本划分为页面块与区域块
+///
+/// 空行之后的第一行是页面名，其后不含冒号的行是区域名，含冒号的行是所属块的属性。
+pub fn atlas_blocks(content: &str) -> Vec<AtlasBlock> {
+    let mut blocks: Vec<AtlasBlock> = Vec::new();
+    let mut expect_page = true;
+
+    for (i, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() {
+            expect_page = true;
+            continue;
+        }
+
+        let kind = if expect_page {
+            expect_page = false;
+            NameKind::Page
+        } else if trimmed.contains(':') {
+            if let Some(block) = blocks.last_mut() {
+                block.end = i + 1;
+            }
+            continue;
+        } else {
+            NameKind::Region
+        };
+
+        blocks.push(AtlasBlock {
+            kind,
+            name: trimmed.to_string(),
+            start: i,
+            end: i + 1,
+        });
+    }
+
+    blocks
+}
+
+/// 按属性名判断格式：出现4.x才有的属性（bounds、offsets、pma、scale）即为4.x，否则为3.x
+fn detect_format(lines: &[&str]) -> AtlasFormat {
+    let modern = lines.iter()
+        .filter_map(|line| line.trim().split_once(':'))
+        .any(|(key, _)| matches!(key.trim(), "bounds" | "offsets" | "pma" | "scale"));
+    if modern {
+        AtlasFormat::Modern
+    } else {
+        AtlasFormat::Legacy
+    }
+}
+
+/// 读取区域位置：4.x为 `bounds: x, y, w, h`，3.x为 `xy` 与 `size`；`rotate`为true或角度
+fn parse_bounds<'a>(properties: impl Iterator<Item = (&'a str, &'a str)>) -> Option<RegionBounds> {
+    let (mut position, mut size, mut rotated) = (None, None, false);
+    for (key, value) in properties {
+        match key {
+            "bounds" => {
+                let values: Vec<u32> = value.split(',').filter_map(|v| v.trim().parse().ok()).collect();
+                if let [x, y, width, height] = values[..] {
+                    position = Some((x, y));
+                    size = Some((width, height));
+                }
+            }
+            "xy" => position = parse_pair(value),
+            "size" => size = parse_pair(value),
+            "rotate" => rotated = value == "true" || matches!(value.parse::<i32>(), Ok(90 | 270 | -90)),
+            _ => {}
+        }
+    }
+    let ((x, y), (width, height)) = (position?, size?);
+    Some(RegionBounds { x, y, width, height, rotated })
+}
+
+fn parse_pair(value: &str) -> Option<(u32, u32)> {
+    let mut parts = value.split(',').map(|s| s.trim());
+    let first = parts.next()?.parse().ok()?;
+    let second = parts.next()?.parse().ok()?;
+    Some((first, second))
+}
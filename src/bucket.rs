@@ -0,0 +1,192 @@
+use anyhow::Result;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::atlas::parse_atlas_dir;
+use crate::file_operations::{collect_files_with_extension, get_png_dimensions, NameReservations, RenamePlan};
+use crate::sidecar::Rename;
+use crate::progress::Reporter;
+use crate::workspace::LoadFailure;
+
+/// 长边小于此值的PNG视为图标
+const ICON_MAX_SIDE: u32 = 256;
+/// 长边不小于此值的PNG视为大图立绘
+const PORTRAIT_MIN_SIDE: u32 = 1024;
+/// 宽高都是此范围内2的幂时视为Atlas页面
+const PAGE_SIDES: std::ops::RangeInclusive<u32> = 256..=8192;
+
+/// PNG的尺寸类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SizeClass {
+    /// 长边小于256
+    Icon,
+    /// 与Atlas页面尺寸相同，或宽高都是2的幂
+    Page,
+    /// 长边不小于1024的其他大图
+    Portrait,
+    /// 其余中等尺寸的界面图片
+    Ui,
+}
+
+impl SizeClass {
+    /// 子文件夹名
+    pub fn folder(self) -> &'static str {
+        match self {
+            SizeClass::Icon => "icons",
+            SizeClass::Page => "pages",
+            SizeClass::Portrait => "portraits",
+            SizeClass::Ui => "ui",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SizeClass::Icon => "图标",
+            SizeClass::Page => "Atlas页面",
+            SizeClass::Portrait => "大图立绘",
+            SizeClass::Ui => "界面图片",
+        }
+    }
+}
+
+/// 按尺寸判断类别；`page_sizes`为已有Atlas的页面尺寸，优先于其他规则
+pub fn classify(width: u32, height: u32, page_sizes: &HashSet<(u32, u32)>) -> SizeClass {
+    let longest = width.max(height);
+    let power_of_two = |side: u32| side.is_power_of_two() && PAGE_SIDES.contains(&side);
+    if page_sizes.contains(&(width, height)) || (power_of_two(width) && power_of_two(height)) {
+        SizeClass::Page
+    } else if longest < ICON_MAX_SIDE {
+        SizeClass::Icon
+    } else if longest >= PORTRAIT_MIN_SIDE {
+        SizeClass::Portrait
+    } else {
+        SizeClass::Ui
+    }
+}
+
+/// 分类整理计划
+#[derive(Debug, Default)]
+pub struct BucketPlan {
+    /// 类别 -> PNG数量（含已在正确位置的）
+    pub classes: BTreeMap<SizeClass, usize>,
+    /// 需要移动的PNG；无法读取尺寸的PNG记在`failures`中，不移动
+    pub moves: RenamePlan,
+}
+
+/// 计划将目录下的PNG按尺寸类别移入子文件夹（如 `pages/`），`exact_size`时再按具体尺寸分一层（如 `pages/2048x2048/`）
+///
+/// 与`atlas_dir`中Atlas页面尺寸相同的PNG一律归入页面类，便于按尺寸浏览候选页面。
+pub fn plan_bucket(dir: &Path, atlas_dir: &Path, exact_size: bool, reporter: &dyn Reporter) -> BucketPlan {
+    let page_sizes: HashSet<(u32, u32)> = parse_atlas_dir(atlas_dir).iter()
+        .flat_map(|atlas| atlas.pages.iter().map(|page| (page.width, page.height)))
+        .collect();
+
+    let files = collect_files_with_extension(dir, "png");
+    reporter.started("读取PNG尺寸", files.len());
+    let mut sized: Vec<(PathBuf, Result<(u32, u32)>)> = files
+        .into_par_iter()
+        .map(|path| {
+            let size = get_png_dimensions(&path);
+            match &size {
+                Ok(_) => reporter.processed(&path, None),
+                Err(e) => reporter.error(&path, &format!("{:#}", e)),
+            }
+            (path, size)
+        })
+        .collect();
+    reporter.finished("读取PNG尺寸", "PNG尺寸读取完成");
+    sized.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut plan = BucketPlan::default();
+    let mut reservations = NameReservations::default();
+    for (path, size) in sized {
+        let (width, height) = match size {
+            Ok(size) => size,
+            Err(e) => {
+                plan.moves.failures.push(LoadFailure { path, error: format!("{:#}", e) });
+                continue;
+            }
+        };
+        let class = classify(width, height, &page_sizes);
+        *plan.classes.entry(class).or_default() += 1;
+
+        let mut target_dir = dir.join(class.folder());
+        if exact_size {
+            target_dir.push(format!("{}x{}", width, height));
+        }
+        if path.parent() == Some(target_dir.as_path()) {
+            continue;
+        }
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("file");
+        let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("png");
+        let to = reservations.reserve(&target_dir, stem, extension);
+        plan.moves.renames.push(Rename { from: path, to });
+    }
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imaging::{encode_png, RgbaImage};
+    use crate::progress::QuietReporter;
+    use std::fs;
+
+    #[test]
+    fn classify_by_size() {
+        let atlas_pages: HashSet<(u32, u32)> = [(300, 200), (100, 50)].into_iter().collect();
+        let cases = [
+            (255, 255, SizeClass::Icon),
+            (255, 100, SizeClass::Icon),
+            (256, 100, SizeClass::Ui),
+            (256, 256, SizeClass::Page),
+            (128, 128, SizeClass::Icon),
+            (512, 8192, SizeClass::Page),
+            (512, 16384, SizeClass::Portrait),
+            (1024, 1000, SizeClass::Portrait),
+            (1023, 1000, SizeClass::Ui),
+            (1000, 1024, SizeClass::Portrait),
+            (2048, 2048, SizeClass::Page),
+            (2048, 1000, SizeClass::Portrait),
+            (300, 200, SizeClass::Page),
+            (100, 50, SizeClass::Page),
+            (200, 300, SizeClass::Ui),
+        ];
+        for (width, height, expected) in cases {
+            assert_eq!(classify(width, height, &atlas_pages), expected, "{}x{}", width, height);
+        }
+    }
+
+    #[test]
+    fn plan_moves_into_class_folders() {
+        let root = tempfile::tempdir().unwrap();
+        let (dir, atlas_dir) = (root.path().join("udp"), root.path().join("atlas"));
+        fs::create_dir_all(dir.join("icons")).unwrap();
+        fs::create_dir_all(&atlas_dir).unwrap();
+        fs::write(atlas_dir.join("hero.atlas"), "\nhero.png\nsize: 300,200\nformat: RGBA8888\nfilter: Linear,Linear\nrepeat: none\n").unwrap();
+        let png = |name: &str, width: u32, height: u32| {
+            let image = RgbaImage { width, height, pixels: vec![0; (width * height * 4) as usize] };
+            encode_png(&image, &dir.join(name)).unwrap();
+        };
+        png("a.png", 300, 200);
+        png("b.png", 16, 16);
+        png("icons/c.png", 8, 8);
+        fs::write(dir.join("broken.png"), "not a png").unwrap();
+
+        let plan = plan_bucket(&dir, &atlas_dir, false, &QuietReporter);
+        let classes: Vec<(SizeClass, usize)> = plan.classes.into_iter().collect();
+        assert_eq!(classes, [(SizeClass::Icon, 2), (SizeClass::Page, 1)]);
+        let moves: Vec<(PathBuf, PathBuf)> = plan.moves.renames.into_iter().map(|r| (r.from, r.to)).collect();
+        assert_eq!(moves, [
+            (dir.join("a.png"), dir.join("pages/a.png")),
+            (dir.join("b.png"), dir.join("icons/b.png")),
+        ]);
+        assert_eq!(plan.moves.failures.len(), 1);
+
+        let plan = plan_bucket(&dir, &atlas_dir, true, &QuietReporter);
+        assert!(plan.moves.renames.iter().any(|r| r.to == dir.join("icons/8x8/c.png")));
+    }
+}
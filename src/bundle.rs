@@ -0,0 +1,186 @@
+use anyhow::{Result, Context};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+
+use crate::atlas::Atlas;
+use crate::compat::{check_compatibility, Compatibility};
+use crate::file_operations::get_png_dimensions;
+use crate::paths::has_reserved_chars;
+use crate::skel::Skeleton;
+use crate::workspace::PngFile;
+
+/// 组装完成的角色文件夹
+#[derive(Debug)]
+pub struct Bundle {
+    pub dir: PathBuf,
+    pub files: Vec<PathBuf>,
+    /// Skel版本与Atlas格式的兼容性，不一致时只给出警告，不阻止组装
+    pub compatibility: Compatibility,
+}
+
+/// 检查角色名能否直接用作文件夹名
+pub fn validate_bundle_name(name: &str) -> Result<()> {
+    let name = name.trim();
+    if name.is_empty() {
+        anyhow::bail!("角色名不能为空");
+    }
+    if name == "." || name == ".." || has_reserved_chars(name) {
+        anyhow::bail!("角色名不能包含路径分隔符或特殊字符: {}", name);
+    }
+    Ok(())
+}
+
+/// 为每个Atlas页面挑选第一张同尺寸的PNG
+pub fn default_page_pngs(atlas: &Atlas, pngs: &[PngFile]) -> Vec<Option<usize>> {
+    atlas.pages.iter()
+        .map(|page| pngs.iter().position(|png| png.width == page.width && png.height == page.height))
+        .collect()
+}
+
+/// 将Atlas、Skel与页面PNG复制到`output_root/name`
+///
+/// Atlas与Skel保留原文件名，PNG按Atlas中的页面名命名（如 skeleton.png），
+/// `page_pngs`需与Atlas页面一一对应。
+pub fn assemble_bundle(
+    output_root: &Path,
+    name: &str,
+    atlas: &Atlas,
+    skeleton: &Skeleton,
+    page_pngs: &[PathBuf],
+) -> Result<Bundle> {
+    validate_bundle_name(name)?;
+
+    if page_pngs.len() != atlas.pages.len() {
+        anyhow::bail!("Atlas有 {} 个页面，但提供了 {} 张PNG", atlas.pages.len(), page_pngs.len());
+    }
+    for (page, png) in atlas.pages.iter().zip(page_pngs) {
+        if !Path::new(&page.name).components().all(|c| matches!(c, Component::Normal(_))) {
+            anyhow::bail!("无效的页面名: {}", page.name);
+        }
+        let (width, height) = get_png_dimensions(png)
+            .with_context(|| format!("无法读取PNG: {:?}", png))?;
+        if (width, height) != (page.width, page.height) {
+            anyhow::bail!("页面 {} 需要 {}x{} 的PNG，但 {:?} 为 {}x{}",
+                page.name, page.width, page.height, png, width, height);
+        }
+    }
+
+    let dir = output_root.join(name.trim());
+    fs::create_dir_all(output_root).context("无法创建输出目录")?;
+    // 直接创建而不是先检查是否存在，避免与其他进程竞争同一个文件夹
+    match fs::create_dir(&dir) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => anyhow::bail!("目标文件夹已存在: {:?}", dir),
+        Err(e) => return Err(e).context("无法创建角色文件夹"),
+    }
+
+    // 复制失败时删除已创建的文件夹，下次可以直接重试
+    let files = match copy_bundle_files(&dir, atlas, skeleton, page_pngs) {
+        Ok(files) => files,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&dir);
+            return Err(e);
+        }
+    };
+
+    Ok(Bundle {
+        dir,
+        files,
+        compatibility: check_compatibility(&skeleton.version, atlas.format),
+    })
+}
+
+fn copy_bundle_files(dir: &Path, atlas: &Atlas, skeleton: &Skeleton, page_pngs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for source in [&atlas.path, &skeleton.path] {
+        let file_name = source.file_name()
+            .ok_or_else(|| anyhow::anyhow!("无效的文件名: {:?}", source))?;
+        let target = dir.join(file_name);
+        fs::copy(source, &target).with_context(|| format!("复制 {:?} 失败", source))?;
+        files.push(target);
+    }
+    for (page, png) in atlas.pages.iter().zip(page_pngs) {
+        let target = dir.join(&page.name);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(png, &target).with_context(|| format!("复制 {:?} 失败", png))?;
+        files.push(target);
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::atlas::{AtlasFormat, AtlasPage};
+    use crate::imaging::{encode_png, RgbaImage};
+
+    fn fixture(dir: &Path, page_name: &str) -> (Atlas, Skeleton, Vec<PathBuf>) {
+        let atlas = Atlas {
+            path: dir.join("hero.atlas"),
+            format: AtlasFormat::Legacy,
+            pages: vec![AtlasPage { name: page_name.to_string(), width: 2, height: 2, pma: None }],
+            regions: Vec::new(),
+        };
+        fs::write(&atlas.path, "hero.png\n").unwrap();
+        let skeleton = Skeleton {
+            path: dir.join("hero.skel"),
+            version: "3.8.99".to_string(),
+            bones: Vec::new(),
+            slots: Vec::new(),
+            constraints: Vec::new(),
+            skins: Vec::new(),
+            attachments: Vec::new(),
+            events: Vec::new(),
+            animations: Vec::new(),
+            region_paths: Vec::new(),
+        };
+        fs::write(&skeleton.path, "skel").unwrap();
+        let png = dir.join("page.png");
+        encode_png(&RgbaImage { width: 2, height: 2, pixels: vec![0; 16] }, &png).unwrap();
+        (atlas, skeleton, vec![png])
+    }
+
+    #[test]
+    fn assembles_and_refuses_existing_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let (atlas, skeleton, pngs) = fixture(dir.path(), "hero.png");
+        let output = dir.path().join("out");
+
+        let bundle = assemble_bundle(&output, "赵云", &atlas, &skeleton, &pngs).unwrap();
+        assert_eq!(bundle.dir, output.join("赵云"));
+        assert!(bundle.dir.join("hero.png").is_file());
+        assert_eq!(bundle.files.len(), 3);
+
+        let error = assemble_bundle(&output, "赵云", &atlas, &skeleton, &pngs).unwrap_err();
+        assert!(error.to_string().contains("目标文件夹已存在"), "{:#}", error);
+    }
+
+    #[test]
+    fn rejects_page_names_outside_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        for page_name in ["../hero.png", "/tmp/hero.png", "./hero.png"] {
+            let (atlas, skeleton, pngs) = fixture(dir.path(), page_name);
+            let error = assemble_bundle(&dir.path().join("out"), "hero", &atlas, &skeleton, &pngs).unwrap_err();
+            assert!(error.to_string().contains("无效的页面名"), "{}: {:#}", page_name, error);
+        }
+        assert!(!dir.path().join("out").exists());
+    }
+
+    #[test]
+    fn failed_copy_removes_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let (atlas, mut skeleton, pngs) = fixture(dir.path(), "hero.png");
+        let output = dir.path().join("out");
+        let skel_path = skeleton.path.clone();
+        skeleton.path = dir.path().join("missing.skel");
+
+        assert!(assemble_bundle(&output, "hero", &atlas, &skeleton, &pngs).is_err());
+        assert!(!output.join("hero").exists());
+
+        skeleton.path = skel_path;
+        assemble_bundle(&output, "hero", &atlas, &skeleton, &pngs).unwrap();
+    }
+}
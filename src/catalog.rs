@@ -0,0 +1,180 @@
+use anyhow::{Result, Context};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+
+use crate::atlas::parse_atlas_file;
+use crate::file_operations::{file_hash, single_file_with_extension};
+use crate::skel::parse_skel_file;
+
+/// 已还原角色目录，保存在工作区中
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Catalog {
+    pub entries: Vec<CatalogEntry>,
+}
+
+/// 一个已还原的角色
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogEntry {
+    /// 目录内唯一编号，删除后不复用
+    pub id: u32,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skin: Option<String>,
+    pub atlas: CatalogFile,
+    pub skel: CatalogFile,
+    pub pages: Vec<CatalogPage>,
+    pub spine_version: String,
+    /// 还原日期（UTC，YYYY-MM-DD）
+    pub restored_at: String,
+    pub output_dir: PathBuf,
+}
+
+/// 角色使用的Atlas或Skel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogFile {
+    /// 文件名（通常是游戏中的hash文件名）
+    pub file: String,
+    /// 内容MD5
+    pub hash: String,
+}
+
+/// Atlas页面对应的PNG
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogPage {
+    pub page: String,
+    pub hash: String,
+}
+
+impl CatalogEntry {
+    /// 角色名与皮肤，例如 `SP孙策 / 经典`
+    pub fn title(&self) -> String {
+        match &self.skin {
+            Some(skin) => format!("{} / {}", self.name, skin),
+            None => self.name.clone(),
+        }
+    }
+}
+
+impl Catalog {
+    /// 读取目录，文件不存在时返回空目录
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path).context("无法读取角色目录")?;
+        serde_json::from_str(&content).with_context(|| format!("角色目录格式错误: {:?}", path))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut content = serde_json::to_string_pretty(self)?;
+        content.push('\n');
+        fs::write(path, content).context("无法写入角色目录")
+    }
+
+    /// 加入新角色并分配编号；同一对Atlas与Skel已在目录中时报错
+    pub fn add(&mut self, mut entry: CatalogEntry) -> Result<&CatalogEntry> {
+        if let Some(existing) = self.entries.iter()
+            .find(|e| e.atlas.hash == entry.atlas.hash && e.skel.hash == entry.skel.hash)
+        {
+            anyhow::bail!("该角色已在目录中: #{} {}", existing.id, existing.title());
+        }
+        entry.id = self.entries.iter().map(|e| e.id).max().unwrap_or(0) + 1;
+        self.entries.push(entry);
+        Ok(self.entries.last().expect("刚加入的角色"))
+    }
+
+    pub fn get(&self, id: u32) -> Option<&CatalogEntry> {
+        self.entries.iter().find(|e| e.id == id)
+    }
+
+    pub fn remove(&mut self, id: u32) -> Option<CatalogEntry> {
+        let index = self.entries.iter().position(|e| e.id == id)?;
+        Some(self.entries.remove(index))
+    }
+
+    /// 按角色名、皮肤、文件名或hash搜索（不区分大小写）
+    pub fn search(&self, text: &str) -> Vec<&CatalogEntry> {
+        let text = text.to_lowercase();
+        self.entries.iter()
+            .filter(|e| {
+                let mut fields = vec![&e.name, &e.atlas.file, &e.atlas.hash, &e.skel.file, &e.skel.hash];
+                fields.extend(&e.skin);
+                fields.extend(e.pages.iter().map(|p| &p.hash));
+                fields.iter().any(|field| field.to_lowercase().contains(&text))
+            })
+            .collect()
+    }
+
+    /// 查找使用了该内容hash（Atlas、Skel或页面PNG）的角色
+    pub fn find_by_hash(&self, hash: &str) -> Option<&CatalogEntry> {
+        self.entries.iter().find(|e| {
+            e.atlas.hash == hash || e.skel.hash == hash || e.pages.iter().any(|p| p.hash == hash)
+        })
+    }
+}
+
+/// 从角色文件夹生成目录条目：文件夹中须恰好有一个.atlas与一个.skel，且每个页面都有对应的PNG
+pub fn entry_from_dir(dir: &Path, name: &str, skin: Option<&str>) -> Result<CatalogEntry> {
+    let atlas_path = single_file_with_extension(dir, "atlas")?;
+    let skel_path = single_file_with_extension(dir, "skel")?;
+
+    let atlas = parse_atlas_file(&atlas_path)
+        .with_context(|| format!("无法解析Atlas: {:?}", atlas_path))?;
+    let skeleton = parse_skel_file(&skel_path)
+        .with_context(|| format!("无法解析Skel: {:?}", skel_path))?;
+
+    let pages = atlas.pages.iter()
+        .map(|page| {
+            let png = dir.join(&page.name);
+            if !png.is_file() {
+                anyhow::bail!("找不到页面 {} 对应的PNG: {:?}", page.name, png);
+            }
+            Ok(CatalogPage { page: page.name.clone(), hash: file_hash(&png)? })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(CatalogEntry {
+        id: 0,
+        name: name.to_string(),
+        skin: skin.map(str::to_string),
+        atlas: catalog_file(&atlas_path)?,
+        skel: catalog_file(&skel_path)?,
+        pages,
+        spine_version: skeleton.version,
+        restored_at: today(),
+        output_dir: dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf()),
+    })
+}
+
+fn catalog_file(path: &Path) -> Result<CatalogFile> {
+    Ok(CatalogFile {
+        file: path.file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default()
+            .to_string(),
+        hash: file_hash(path)?,
+    })
+}
+
+/// 当前UTC日期（YYYY-MM-DD）
+pub fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or(0) as i64;
+
+    // 由1970-01-01起的天数换算公历日期
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
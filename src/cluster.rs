@@ -0,0 +1,341 @@
+use anyhow::{Result, Context};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use clap::ValueEnum;
+use rayon::prelude::*;
+
+use crate::file_operations::collect_files_with_extension;
+use crate::gallery::{escape_html, html_head, relative_url};
+use crate::imaging::{decode_rgba, downscale, encode_png, resample, RgbaImage};
+use crate::progress::Reporter;
+
+/// 颜色直方图每个通道的分档数
+const HISTOGRAM_LEVELS: usize = 4;
+const HISTOGRAM_BINS: usize = HISTOGRAM_LEVELS * HISTOGRAM_LEVELS * HISTOGRAM_LEVELS;
+/// 计算特征前先缩小到的长边，减少大图的计算量
+const FEATURE_SIDE: u32 = 64;
+
+/// 聚类结果的输出方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ClusterLayout {
+    /// 带缩略图的离线HTML页面
+    Html,
+    /// 每个聚类一个文件夹（复制原图）
+    Folders,
+}
+
+/// 相似判断的阈值
+#[derive(Debug, Clone, Copy)]
+pub struct ClusterOptions {
+    /// aHash与dHash的汉明距离之和不超过此值（0~128）
+    pub max_distance: u32,
+    /// 颜色直方图交集不低于此值（0~1）
+    pub min_color: f32,
+}
+
+/// 一张图片的感知特征
+#[derive(Debug, Clone)]
+pub struct ImageFeatures {
+    /// 8x8平均哈希
+    pub ahash: u64,
+    /// 9x8差值哈希
+    pub dhash: u64,
+    /// 按Alpha加权、归一化的RGB直方图
+    pub histogram: [f32; HISTOGRAM_BINS],
+}
+
+impl ImageFeatures {
+    pub fn compute(image: &RgbaImage) -> Self {
+        let small = downscale(image, FEATURE_SIDE);
+
+        // 合成到黑色背景上再取亮度，透明部分的形状也参与比较
+        let gray = |image: &RgbaImage| -> Vec<u32> {
+            image.pixels.chunks_exact(4)
+                .map(|p| (p[0] as u32 * 299 + p[1] as u32 * 587 + p[2] as u32 * 114) * p[3] as u32 / 255_000)
+                .collect()
+        };
+
+        let average = gray(&resample(&small, 8, 8));
+        let mean = average.iter().sum::<u32>() / 64;
+        let ahash = average.iter()
+            .enumerate()
+            .fold(0u64, |hash, (i, &value)| if value > mean { hash | 1 << i } else { hash });
+
+        let difference = gray(&resample(&small, 9, 8));
+        let mut dhash = 0u64;
+        for y in 0..8 {
+            for x in 0..8 {
+                if difference[y * 9 + x] > difference[y * 9 + x + 1] {
+                    dhash |= 1 << (y * 8 + x);
+                }
+            }
+        }
+
+        let mut histogram = [0f32; HISTOGRAM_BINS];
+        let shift = 8 - HISTOGRAM_LEVELS.trailing_zeros();
+        for p in small.pixels.chunks_exact(4) {
+            let bin = ((p[0] >> shift) as usize * HISTOGRAM_LEVELS + (p[1] >> shift) as usize) * HISTOGRAM_LEVELS
+                + (p[2] >> shift) as usize;
+            histogram[bin] += p[3] as f32;
+        }
+        let total: f32 = histogram.iter().sum();
+        if total > 0.0 {
+            histogram.iter_mut().for_each(|value| *value /= total);
+        }
+
+        ImageFeatures { ahash, dhash, histogram }
+    }
+
+    /// 两个哈希的汉明距离之和
+    pub fn distance(&self, other: &Self) -> u32 {
+        (self.ahash ^ other.ahash).count_ones() + (self.dhash ^ other.dhash).count_ones()
+    }
+
+    /// 直方图交集，1表示颜色分布相同
+    pub fn color_similarity(&self, other: &Self) -> f32 {
+        self.histogram.iter().zip(&other.histogram).map(|(a, b)| a.min(*b)).sum()
+    }
+}
+
+/// 聚类结果：多于一张图片的聚类按大小降序排列
+pub struct Clustering {
+    pub clusters: Vec<Vec<PathBuf>>,
+    /// 没有相似图片的PNG数量
+    pub singletons: usize,
+    /// 无法解码的PNG
+    pub failures: Vec<PathBuf>,
+}
+
+/// 计算目录下全部PNG的感知特征，将相似的图片连成聚类（只在CPU上计算）
+pub fn cluster_pngs(dir: &Path, options: ClusterOptions, reporter: &dyn Reporter) -> Clustering {
+    let files = collect_files_with_extension(dir, "png");
+
+    reporter.started("计算感知哈希", files.len());
+
+    let features: Vec<Option<ImageFeatures>> = files.par_iter()
+        .map(|path| {
+            match decode_rgba(path) {
+                Ok(image) => {
+                    reporter.processed(path, None);
+                    Some(ImageFeatures::compute(&image))
+                }
+                Err(e) => {
+                    reporter.error(path, &format!("{:#}", e));
+                    None
+                }
+            }
+        })
+        .collect();
+
+    reporter.finished("计算感知哈希", "感知哈希计算完成");
+
+    let failures: Vec<PathBuf> = files.iter()
+        .zip(&features)
+        .filter(|(_, features)| features.is_none())
+        .map(|(path, _)| path.clone())
+        .collect();
+    let (paths, features): (Vec<&PathBuf>, Vec<ImageFeatures>) = files.iter()
+        .zip(features)
+        .filter_map(|(path, features)| features.map(|features| (path, features)))
+        .unzip();
+
+    // 两两比较，先用哈希距离快速排除
+    let edges: Vec<(usize, usize)> = (0..features.len()).into_par_iter()
+        .flat_map_iter(|i| {
+            let features = &features;
+            (i + 1..features.len())
+                .filter(move |&j| {
+                    features[i].distance(&features[j]) <= options.max_distance
+                        && features[i].color_similarity(&features[j]) >= options.min_color
+                })
+                .map(move |j| (i, j))
+        })
+        .collect();
+
+    let mut sets = DisjointSet::new(features.len());
+    for (i, j) in edges {
+        sets.union(i, j);
+    }
+
+    let mut groups: Vec<Vec<PathBuf>> = vec![Vec::new(); features.len()];
+    for (i, path) in paths.iter().enumerate() {
+        groups[sets.find(i)].push((*path).clone());
+    }
+    let singletons = groups.iter().filter(|group| group.len() == 1).count();
+    let mut clusters: Vec<Vec<PathBuf>> = groups.into_iter().filter(|group| group.len() > 1).collect();
+    clusters.iter_mut().for_each(|cluster| cluster.sort());
+    clusters.sort_by(|a, b| b.len().cmp(&a.len()).then(a[0].cmp(&b[0])));
+
+    Clustering { clusters, singletons, failures }
+}
+
+struct DisjointSet {
+    parent: Vec<usize>,
+}
+
+impl DisjointSet {
+    fn new(size: usize) -> Self {
+        DisjointSet { parent: (0..size).collect() }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parent[b.max(a)] = a.min(b);
+        }
+    }
+}
+
+/// 本命令生成的聚类文件夹中的标记文件，重新生成时只删除带有该标记的文件夹
+const CLUSTER_MARKER: &str = ".unpack_cluster";
+
+/// 将每个聚类的图片复制到`output_dir/cluster_001`等文件夹，先删除上次生成的聚类文件夹
+///
+/// 输出目录中已有不是本命令生成的同名文件夹时拒绝写入，不会删除或混入其中。
+pub fn write_cluster_folders(clustering: &Clustering, output_dir: &Path) -> Result<()> {
+    let folder_name = |index: usize| format!("cluster_{:03}", index + 1);
+
+    let mut generated = Vec::new();
+    if output_dir.exists() {
+        for entry in fs::read_dir(output_dir).context("无法读取聚类目录")?.filter_map(|e| e.ok()) {
+            let is_cluster = entry.file_name().to_str().is_some_and(|name| name.starts_with("cluster_"));
+            if is_cluster && entry.path().join(CLUSTER_MARKER).is_file() {
+                generated.push(entry.path());
+            }
+        }
+    }
+    for index in 0..clustering.clusters.len() {
+        let folder = output_dir.join(folder_name(index));
+        if folder.exists() && !generated.contains(&folder) {
+            anyhow::bail!("{:?} 已存在且不是cluster命令生成的，请换一个输出目录或先移走该文件夹", folder);
+        }
+    }
+    for folder in generated {
+        fs::remove_dir_all(folder).context("无法删除旧的聚类文件夹")?;
+    }
+
+    fs::create_dir_all(output_dir).context("无法创建聚类目录")?;
+    for (index, cluster) in clustering.clusters.iter().enumerate() {
+        let folder = output_dir.join(folder_name(index));
+        fs::create_dir(&folder).context("无法创建聚类文件夹")?;
+        fs::write(folder.join(CLUSTER_MARKER), "").context("无法创建聚类文件夹")?;
+        for path in cluster {
+            let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("png");
+            let mut target = folder.join(format!("{}.png", stem));
+            let mut counter = 1;
+            while target.exists() {
+                target = folder.join(format!("{}_{}.png", stem, counter));
+                counter += 1;
+            }
+            fs::copy(path, &target).with_context(|| format!("无法复制 {:?}", path))?;
+        }
+    }
+    Ok(())
+}
+
+/// 生成带缩略图的聚类页面，返回页面路径
+pub fn write_cluster_html(clustering: &Clustering, output_dir: &Path, thumb_size: u32) -> Result<PathBuf> {
+    let thumbs_dir = output_dir.join("thumbs");
+    fs::create_dir_all(&thumbs_dir).context("无法创建聚类目录")?;
+    let output_dir = output_dir.canonicalize().context("无法定位聚类目录")?;
+
+    let members: Vec<&PathBuf> = clustering.clusters.iter().flatten().collect();
+    let thumbnails: Vec<Option<String>> = members.par_iter()
+        .enumerate()
+        .map(|(i, path)| {
+            let thumb_name = format!("thumbs/{:05}.png", i);
+            decode_rgba(path)
+                .and_then(|image| encode_png(&downscale(&image, thumb_size), &output_dir.join(&thumb_name)))
+                .ok()
+                .map(|_| thumb_name)
+        })
+        .collect();
+
+    let mut html = html_head("相似图片聚类");
+    let _ = writeln!(html, "<h1>相似图片聚类</h1>");
+    let _ = writeln!(html, "<p class=\"summary\">{} 个聚类，共 {} 张图片；{} 张没有相似图片</p>",
+        clustering.clusters.len(), members.len(), clustering.singletons);
+
+    let mut offset = 0;
+    for (index, cluster) in clustering.clusters.iter().enumerate() {
+        let _ = writeln!(html, "<section>");
+        let _ = writeln!(html, "<h2>聚类 {} <small>{} 张</small></h2>", index + 1, cluster.len());
+        let _ = writeln!(html, "<div class=\"grid\">");
+        for (i, path) in cluster.iter().enumerate() {
+            let href = relative_url(&output_dir, path);
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("?");
+            let _ = writeln!(html, "<figure>");
+            match &thumbnails[offset + i] {
+                Some(thumb) => {
+                    let _ = writeln!(html, "<a href=\"{}\"><img src=\"{}\" loading=\"lazy\" alt=\"{}\"></a>",
+                        escape_html(&href), thumb, escape_html(name));
+                }
+                None => {
+                    let _ = writeln!(html, "<a href=\"{}\" class=\"broken\">缩略图生成失败</a>", escape_html(&href));
+                }
+            }
+            let _ = writeln!(html, "<figcaption>{}</figcaption>", escape_html(name));
+            let _ = writeln!(html, "</figure>");
+        }
+        let _ = writeln!(html, "</div>");
+        let _ = writeln!(html, "</section>");
+        offset += cluster.len();
+    }
+
+    html.push_str("</body>\n</html>\n");
+
+    let index_path = output_dir.join("index.html");
+    fs::write(&index_path, html).context("无法写入聚类页面")?;
+    Ok(index_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clustering(paths: &[&Path]) -> Clustering {
+        Clustering { clusters: vec![paths.iter().map(|p| p.to_path_buf()).collect()], singletons: 0, failures: Vec::new() }
+    }
+
+    #[test]
+    fn folders_replace_only_generated_clusters() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (dir.path().join("a.png"), dir.path().join("b.png"));
+        fs::write(&a, "a").unwrap();
+        fs::write(&b, "b").unwrap();
+        let output = dir.path().join("clusters");
+        let own = output.join("cluster_009");
+        fs::create_dir_all(&own).unwrap();
+        fs::write(own.join("keep.txt"), "").unwrap();
+
+        write_cluster_folders(&clustering(&[&a, &b]), &output).unwrap();
+        write_cluster_folders(&clustering(&[&a]), &output).unwrap();
+        assert!(output.join("cluster_001/a.png").is_file());
+        assert!(!output.join("cluster_001/b.png").exists());
+        assert!(own.join("keep.txt").is_file());
+    }
+
+    #[test]
+    fn folders_refuse_foreign_cluster_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.png");
+        fs::write(&a, "a").unwrap();
+        let foreign = dir.path().join("cluster_001");
+        fs::create_dir_all(&foreign).unwrap();
+        fs::write(foreign.join("keep.txt"), "").unwrap();
+
+        let error = write_cluster_folders(&clustering(&[&a]), dir.path()).unwrap_err();
+        assert!(error.to_string().contains("不是cluster命令生成的"), "{:#}", error);
+        assert!(foreign.join("keep.txt").is_file());
+        assert!(!foreign.join("a.png").exists());
+    }
+}
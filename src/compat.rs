@@ -0,0 +1,124 @@
+use anyhow::Context;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::atlas::{load_atlas_dir, AtlasFormat};
+use crate::file_operations::collect_files_with_extension;
+use crate::matching::RegionIndex;
+use crate::skel::{parse_skel_setup, read_header, Skeleton};
+use crate::workspace::LoadFailure;
+
+/// Skel版本与Atlas格式的兼容性
+#[derive(Debug, Clone, Serialize)]
+pub struct Compatibility {
+    pub skel_version: String,
+    pub atlas_format: AtlasFormat,
+    /// 为空表示两者来自同一主版本
+    pub warnings: Vec<String>,
+}
+
+/// 工作区的兼容性汇总
+#[derive(Debug, Default, Serialize)]
+pub struct CompatibilitySummary {
+    /// Spine版本 -> Skel数量（只读文件头，解析器不支持的版本也会统计）
+    pub skel_versions: BTreeMap<String, usize>,
+    /// Atlas格式 -> 数量
+    pub atlas_formats: BTreeMap<AtlasFormat, usize>,
+    /// 每个可解析的Skel与附件命中最多的Atlas
+    pub pairs: Vec<PairCompatibility>,
+    /// 无法读取或解析的Atlas与Skel
+    pub failures: Vec<LoadFailure>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PairCompatibility {
+    pub skel: PathBuf,
+    pub atlas: PathBuf,
+    #[serde(flatten)]
+    pub compatibility: Compatibility,
+}
+
+impl CompatibilitySummary {
+    /// 主版本不一致的配对
+    pub fn mismatched(&self) -> impl Iterator<Item = &PairCompatibility> {
+        self.pairs.iter().filter(|pair| !pair.compatibility.warnings.is_empty())
+    }
+}
+
+/// 检查Skel版本（如 `3.8.99`）与Atlas格式是否来自同一主版本
+pub fn check_compatibility(skel_version: &str, atlas_format: AtlasFormat) -> Compatibility {
+    let major = skel_version.split('.').next().and_then(|major| major.parse::<u32>().ok());
+    let warning = match major {
+        None => Some(format!("无法识别Skel版本: {}", skel_version)),
+        Some(major) if major < atlas_format.major() => Some(format!(
+            "Skel为 {} 而Atlas为 {} 格式，{}.x运行时无法读取该Atlas",
+            skel_version, atlas_format.label(), major
+        )),
+        Some(major) if major > atlas_format.major() => Some(format!(
+            "Skel为 {} 而Atlas为 {} 格式，主版本不一致，部分查看器可能无法加载",
+            skel_version, atlas_format.label()
+        )),
+        Some(_) => None,
+    };
+
+    Compatibility {
+        skel_version: skel_version.to_string(),
+        atlas_format,
+        warnings: warning.into_iter().collect(),
+    }
+}
+
+/// 统计Skel版本与Atlas格式，并为每个Skel找到附件命中最多的Atlas检查兼容性
+///
+/// 每个Skel只读取一次：版本取自文件头，配对只需附件，因此不解析动画。
+pub fn summarize_compatibility(atlas_dir: &Path, skels_dir: &Path) -> CompatibilitySummary {
+    let (atlases, mut failures) = load_atlas_dir(atlas_dir);
+    let skels: Vec<(Option<String>, Result<Skeleton, LoadFailure>)> = collect_files_with_extension(skels_dir, "skel")
+        .par_iter()
+        .map(|path| {
+            let failure = |e: anyhow::Error| LoadFailure { path: path.clone(), error: format!("{:#}", e) };
+            let data = match fs::read(path).context("无法读取Skel文件") {
+                Ok(data) => data,
+                Err(e) => return (None, Err(failure(e))),
+            };
+            // 解析器不支持的版本也统计在内
+            let version = read_header(&data).ok().map(|(_, _, version)| version);
+            let skeleton = parse_skel_setup(&data)
+                .map(|skeleton| Skeleton { path: path.clone(), ..skeleton })
+                .map_err(failure);
+            (version, skeleton)
+        })
+        .collect();
+
+    let mut summary = CompatibilitySummary::default();
+    let mut skeletons = Vec::new();
+    for (version, skeleton) in skels {
+        *summary.skel_versions.entry(version.unwrap_or_else(|| "未知".to_string())).or_default() += 1;
+        match skeleton {
+            Ok(skeleton) => skeletons.push(skeleton),
+            Err(failure) => failures.push(failure),
+        }
+    }
+    for atlas in &atlases {
+        *summary.atlas_formats.entry(atlas.format).or_default() += 1;
+    }
+
+    let index = RegionIndex::new(&atlases);
+    for skeleton in &skeletons {
+        if let Some(&(best, _)) = index.coverage(skeleton).first() {
+            let atlas = &atlases[best];
+            summary.pairs.push(PairCompatibility {
+                skel: skeleton.path.clone(),
+                atlas: atlas.path.clone(),
+                compatibility: check_compatibility(&skeleton.version, atlas.format),
+            });
+        }
+    }
+    summary.pairs.sort_by(|a, b| a.skel.cmp(&b.skel));
+    failures.sort_by(|a, b| a.path.cmp(&b.path));
+    summary.failures = failures;
+    summary
+}
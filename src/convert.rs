@@ -0,0 +1,1961 @@
+use anyhow::{Result, Context};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use serde_json::{json, Map, Value};
+
+use crate::skel::{
+    Format,
+    ATTACHMENT_BOUNDING_BOX, ATTACHMENT_CLIPPING, ATTACHMENT_LINKED_MESH, ATTACHMENT_MESH,
+    ATTACHMENT_PATH, ATTACHMENT_POINT, ATTACHMENT_REGION, CURVE_BEZIER, CURVE_LINEAR, CURVE_STEPPED,
+};
+use crate::skel_data::*;
+
+/// 转换时无法原样表达的内容
+#[derive(Debug)]
+pub struct ConversionIssue {
+    /// 例如 `动画 idle / 骨骼 hip`
+    pub location: String,
+    pub kind: IssueKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueKind {
+    /// 已换为目标版本中最接近的写法，效果可能略有不同
+    Approximated,
+    /// 目标版本没有对应功能，已移除
+    Dropped,
+}
+
+impl IssueKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            IssueKind::Approximated => "近似",
+            IssueKind::Dropped => "丢弃",
+        }
+    }
+}
+
+/// 转换结果：符合目标版本的数据与无法原样转换的内容
+pub struct Conversion {
+    pub data: SkeletonData,
+    pub issues: Vec<ConversionIssue>,
+}
+
+const TRANSFORM_MODES: [&str; 5] = ["normal", "onlyTranslation", "noRotationOrReflection", "noScale", "noScaleOrReflection"];
+const BLEND_MODES: [&str; 4] = ["normal", "additive", "multiply", "screen"];
+const POSITION_MODES: [&str; 2] = ["fixed", "percent"];
+const SPACING_MODES: [&str; 4] = ["length", "fixed", "percent", "proportional"];
+const ROTATE_MODES: [&str; 3] = ["tangent", "chain", "chainScale"];
+const SEQUENCE_MODES: [&str; 7] = ["hold", "once", "loop", "pingpong", "onceReverse", "loopReverse", "pingpongReverse"];
+
+/// 4.0新增的proportional间距模式
+const SPACING_PROPORTIONAL: usize = 3;
+const SPACING_PERCENT: usize = 2;
+
+/// 各通道曲线视为相同的误差
+const CURVE_EPSILON: f32 = 1e-3;
+
+/// 将Skel数据转换为目标版本，`binary`表示将写出二进制格式
+pub fn convert_skeleton(source: &SkeletonData, target: Format, binary: bool) -> Conversion {
+    let mut converter = Converter {
+        data: source.clone(),
+        target,
+        issues: Vec::new(),
+    };
+    converter.data.format = target;
+    converter.data.version = target.version().to_string();
+
+    if binary && target != Format::V38 && !source.hash.is_empty() && hash_bytes(&source.hash).is_none() {
+        converter.issue("文件头".to_string(), IssueKind::Dropped,
+            format!("3.x的hash {} 无法写入4.x二进制文件头，已清零（按指纹设置的显示名称需重新设置）", source.hash));
+    }
+    if target < Format::V41 {
+        converter.strip_sequences();
+    }
+    if target == Format::V38 {
+        converter.downgrade_constraints();
+        for index in 0..converter.data.animations.len() {
+            converter.downgrade_animation(index);
+        }
+    }
+
+    Conversion { data: converter.data, issues: converter.issues }
+}
+
+struct Converter {
+    data: SkeletonData,
+    target: Format,
+    issues: Vec<ConversionIssue>,
+}
+
+impl Converter {
+    fn issue(&mut self, location: String, kind: IssueKind, message: String) {
+        self.issues.push(ConversionIssue { location, kind, message });
+    }
+
+    /// 4.1之前没有序列帧：附件只保留初始帧，序列帧时间轴移除
+    fn strip_sequences(&mut self) {
+        let target = self.target.label();
+        let mut issues = Vec::new();
+        for skin in &mut self.data.skins {
+            for attachment in &mut skin.attachments {
+                let base = attachment.name.clone().unwrap_or_else(|| attachment.placeholder.clone());
+                let (path, sequence) = match &mut attachment.kind {
+                    AttachmentKind::Region { path, sequence, .. }
+                    | AttachmentKind::Mesh { path, sequence, .. }
+                    | AttachmentKind::LinkedMesh { path, sequence, .. } => (path, sequence),
+                    _ => continue,
+                };
+                let Some(frames) = sequence.take() else {
+                    continue;
+                };
+                let setup = frames.frame_path(path.as_deref().unwrap_or(&base), frames.setup_index);
+                issues.push((
+                    format!("皮肤 {} / 插槽 {} / 附件 {}", skin.name, self.data.slots[attachment.slot].name, attachment.placeholder),
+                    IssueKind::Approximated,
+                    format!("{} 不支持序列帧（{}帧），只保留初始帧 {}", target, frames.count, setup),
+                ));
+                *path = Some(setup);
+            }
+        }
+
+        for animation in &mut self.data.animations {
+            animation.attachments.retain(|timeline| {
+                if !matches!(timeline.kind, AttachmentTimelineKind::Sequence(_)) {
+                    return true;
+                }
+                issues.push((
+                    format!("动画 {} / 插槽 {} / 附件 {}", animation.name, self.data.slots[timeline.slot].name, timeline.attachment),
+                    IssueKind::Dropped,
+                    format!("{} 没有序列帧时间轴，已移除", target),
+                ));
+                false
+            });
+        }
+
+        for (location, kind, message) in issues {
+            self.issue(location, kind, message);
+        }
+    }
+
+    /// 3.8的变换与路径约束X、Y共用一个混合值
+    fn downgrade_constraints(&mut self) {
+        let mut issues = Vec::new();
+        for constraint in &self.data.transform_constraints {
+            let location = format!("变换约束 {}", constraint.name);
+            if constraint.mix_x != constraint.mix_y {
+                issues.push((location.clone(), format!(
+                    "mixX（{}）与mixY（{}）不同，3.8只有translateMix，已取mixX", constraint.mix_x, constraint.mix_y)));
+            }
+            if constraint.mix_scale_x != constraint.mix_scale_y {
+                issues.push((location, format!(
+                    "mixScaleX（{}）与mixScaleY（{}）不同，3.8只有scaleMix，已取mixScaleX", constraint.mix_scale_x, constraint.mix_scale_y)));
+            }
+        }
+        for constraint in &mut self.data.path_constraints {
+            let location = format!("路径约束 {}", constraint.name);
+            if constraint.mix_x != constraint.mix_y {
+                issues.push((location.clone(), format!(
+                    "mixX（{}）与mixY（{}）不同，3.8只有translateMix，已取mixX", constraint.mix_x, constraint.mix_y)));
+            }
+            if constraint.spacing_mode == SPACING_PROPORTIONAL {
+                constraint.spacing_mode = SPACING_PERCENT;
+                issues.push((location, "3.8没有proportional间距模式，已改为percent".to_string()));
+            }
+        }
+        for (location, message) in issues {
+            self.issue(location, IssueKind::Approximated, message);
+        }
+    }
+
+    fn downgrade_animation(&mut self, index: usize) {
+        let name = self.data.animations[index].name.clone();
+        let mut slots = std::mem::take(&mut self.data.animations[index].slots);
+        for timelines in &mut slots {
+            self.merge_slot_colors(&name, timelines);
+        }
+        self.data.animations[index].slots = slots;
+
+        let mut bones = std::mem::take(&mut self.data.animations[index].bones);
+        for timelines in &mut bones {
+            self.merge_bone_channels(&name, timelines);
+        }
+        self.data.animations[index].bones = bones;
+
+        let animation = &self.data.animations[index];
+        let mut issues = Vec::new();
+        for timeline in &animation.transform_constraints {
+            let differs = timeline.frames.iter()
+                .any(|frame| frame.values[1] != frame.values[2] || frame.values[3] != frame.values[4]);
+            if differs {
+                issues.push((
+                    format!("动画 {} / 变换约束 {}", animation.name, self.data.transform_constraints[timeline.constraint].name),
+                    "关键帧的mixX与mixY（或mixScaleX与mixScaleY）不同，3.8只有translateMix与scaleMix，已取X的值".to_string(),
+                ));
+            }
+        }
+        for timelines in &animation.path_constraints {
+            for timeline in &timelines.timelines {
+                if timeline.kind == PathTimelineKind::Mix && timeline.frames.iter().any(|frame| frame.values[1] != frame.values[2]) {
+                    issues.push((
+                        format!("动画 {} / 路径约束 {}", animation.name, self.data.path_constraints[timelines.constraint].name),
+                        "关键帧的mixX与mixY不同，3.8只有translateMix，已取mixX".to_string(),
+                    ));
+                }
+            }
+        }
+
+        // 3.8每帧只有一条作用于全部通道的曲线
+        let mut curves: Vec<(String, &[Keyframe])> = Vec::new();
+        for timelines in &animation.slots {
+            for timeline in &timelines.timelines {
+                if let SlotTimeline::Color { kind, frames } = timeline {
+                    curves.push((format!("插槽 {} / {}", self.data.slots[timelines.slot].name, kind.label()), frames));
+                }
+            }
+        }
+        for timelines in &animation.bones {
+            for timeline in &timelines.timelines {
+                curves.push((format!("骨骼 {} / {}", self.data.bones[timelines.bone].name, timeline.kind.label()), &timeline.frames));
+            }
+        }
+        for timeline in &animation.ik_constraints {
+            curves.push((format!("IK约束 {}", self.data.ik_constraints[timeline.constraint].name), &timeline.frames));
+        }
+        for timeline in &animation.transform_constraints {
+            curves.push((format!("变换约束 {}", self.data.transform_constraints[timeline.constraint].name), &timeline.frames));
+        }
+        for timelines in &animation.path_constraints {
+            for timeline in &timelines.timelines {
+                curves.push((format!("路径约束 {}", self.data.path_constraints[timelines.constraint].name), &timeline.frames));
+            }
+        }
+        for (location, frames) in curves {
+            if !uniform_curves(frames) {
+                issues.push((
+                    format!("动画 {} / {}", animation.name, location),
+                    "各通道的曲线不同，3.8每帧只有一条曲线，已使用第一个有变化的通道的曲线".to_string(),
+                ));
+            }
+        }
+
+        for (location, message) in issues {
+            self.issue(location, IssueKind::Approximated, message);
+        }
+    }
+
+    /// 3.8只有RGBA与RGBA2（亮色+暗色）颜色时间轴，其余类型合并为这两种
+    fn merge_slot_colors(&mut self, animation: &str, timelines: &mut SlotTimelines) {
+        let colors: Vec<(ColorKind, &Vec<Keyframe>)> = timelines.timelines.iter()
+            .filter_map(|timeline| match timeline {
+                SlotTimeline::Color { kind, frames } => Some((*kind, frames)),
+                SlotTimeline::Attachment(_) => None,
+            })
+            .collect();
+        let supported = matches!(colors.as_slice(), [] | [(ColorKind::Rgba | ColorKind::Rgba2, _)]);
+        if supported {
+            return;
+        }
+
+        let slot = &self.data.slots[timelines.slot];
+        let two_color = colors.iter().any(|(kind, _)| matches!(kind, ColorKind::Rgba2 | ColorKind::Rgb2));
+        let kind = if two_color { ColorKind::Rgba2 } else { ColorKind::Rgba };
+        let mut defaults = rgba_values(slot.color).to_vec();
+        if two_color {
+            defaults.extend(slot.dark_color.map(rgb_values).unwrap_or([0.0; 3]));
+        }
+        let sources: Vec<ChannelSource> = colors.iter()
+            .map(|(kind, frames)| ChannelSource { frames, channels: kind.rgba2_channels() })
+            .collect();
+        let (frames, exact) = merge_channels(&sources, &defaults);
+
+        let names: Vec<&str> = colors.iter().map(|(kind, _)| kind.label()).collect();
+        let location = format!("动画 {} / 插槽 {}", animation, slot.name);
+        let message = format!("{} 时间轴已合并为 {}，{}", names.join("、"), kind.label(), merge_note(exact, "插槽初始颜色"));
+
+        timelines.timelines.retain(|timeline| matches!(timeline, SlotTimeline::Attachment(_)));
+        timelines.timelines.push(SlotTimeline::Color { kind, frames });
+        self.issue(location, IssueKind::Approximated, message);
+    }
+
+    /// 3.8没有单独的X/Y骨骼时间轴，合并为位移、缩放与斜切
+    fn merge_bone_channels(&mut self, animation: &str, timelines: &mut BoneTimelines) {
+        let groups = [
+            (BoneTimelineKind::Translate, BoneTimelineKind::TranslateX, BoneTimelineKind::TranslateY, 0.0),
+            (BoneTimelineKind::Scale, BoneTimelineKind::ScaleX, BoneTimelineKind::ScaleY, 1.0),
+            (BoneTimelineKind::Shear, BoneTimelineKind::ShearX, BoneTimelineKind::ShearY, 0.0),
+        ];
+        for (combined, x, y, default) in groups {
+            let members: Vec<&BoneTimeline> = timelines.timelines.iter()
+                .filter(|timeline| [combined, x, y].contains(&timeline.kind))
+                .collect();
+            if members.iter().all(|timeline| timeline.kind == combined) {
+                continue;
+            }
+
+            let sources: Vec<ChannelSource> = members.iter()
+                .map(|timeline| ChannelSource {
+                    frames: &timeline.frames,
+                    channels: if timeline.kind == combined { &[0, 1] } else if timeline.kind == x { &[0] } else { &[1] },
+                })
+                .collect();
+            let (frames, exact) = merge_channels(&sources, &[default, default]);
+
+            let names: Vec<&str> = members.iter().map(|timeline| timeline.kind.label()).collect();
+            let location = format!("动画 {} / 骨骼 {}", animation, self.data.bones[timelines.bone].name);
+            let message = format!("{} 时间轴已合并为 {}，{}", names.join("、"), combined.label(),
+                merge_note(exact, if default == 0.0 { "0" } else { "1" }));
+
+            timelines.timelines.retain(|timeline| ![combined, x, y].contains(&timeline.kind));
+            timelines.timelines.push(BoneTimeline { kind: combined, frames });
+            self.issue(location, IssueKind::Approximated, message);
+        }
+    }
+}
+
+fn merge_note(exact: bool, default: &str) -> String {
+    if exact {
+        format!("未设关键帧的通道取{}", default)
+    } else {
+        "各时间轴的关键帧时间不同，已在全部关键帧时间上重新取值（曲线改为直线）".to_string()
+    }
+}
+
+/// 参与合并的一条时间轴：关键帧与各个值在合并后时间轴中的通道
+struct ChannelSource<'a> {
+    frames: &'a [Keyframe],
+    channels: &'a [usize],
+}
+
+/// 合并为一条多通道时间轴，未设关键帧的通道取`defaults`，返回合并结果与是否与原时间轴完全一致
+fn merge_channels(sources: &[ChannelSource], defaults: &[f32]) -> (Vec<Keyframe>, bool) {
+    let same_times = sources.windows(2).all(|pair| {
+        pair[0].frames.len() == pair[1].frames.len()
+            && pair[0].frames.iter().zip(pair[1].frames).all(|(a, b)| a.time == b.time)
+    });
+
+    if !same_times {
+        let mut times: Vec<f32> = sources.iter().flat_map(|source| source.frames.iter().map(|frame| frame.time)).collect();
+        times.sort_by(f32::total_cmp);
+        times.dedup();
+        let frames = times.into_iter()
+            .map(|time| {
+                let mut values = defaults.to_vec();
+                for source in sources {
+                    for (value, &channel) in source.channels.iter().enumerate() {
+                        values[channel] = sample(source.frames, time, value);
+                    }
+                }
+                Keyframe { time, values, curve: Curve::Linear }
+            })
+            .collect();
+        return (frames, false);
+    }
+
+    let count = sources.first().map(|source| source.frames.len()).unwrap_or(0);
+    let mut frames: Vec<Keyframe> = (0..count)
+        .map(|i| {
+            let mut values = defaults.to_vec();
+            for source in sources {
+                for (value, &channel) in source.frames[i].values.iter().zip(source.channels) {
+                    values[channel] = *value;
+                }
+            }
+            Keyframe { time: sources[0].frames[i].time, values, curve: Curve::Linear }
+        })
+        .collect();
+
+    let mut exact = true;
+    for i in 0..count.saturating_sub(1) {
+        let curves: Vec<&Curve> = sources.iter().map(|source| &source.frames[i].curve).collect();
+        frames[i].curve = if curves.iter().all(|curve| **curve == Curve::Linear) {
+            Curve::Linear
+        } else if curves.iter().all(|curve| **curve == Curve::Stepped) {
+            Curve::Stepped
+        } else if curves.iter().any(|curve| **curve == Curve::Stepped) {
+            exact = false;
+            Curve::Stepped
+        } else {
+            // 直线通道也用等效的贝塞尔控制点表示
+            let (from, to) = (&frames[i], &frames[i + 1]);
+            let mut controls: Vec<[f32; 4]> = channel_ranges(from, to).into_iter()
+                .map(|(v1, v2)| linear_control(from.time, v1, to.time, v2))
+                .collect();
+            for source in sources {
+                if let Curve::Bezier(channels) = &source.frames[i].curve {
+                    for (control, &channel) in channels.iter().zip(source.channels) {
+                        controls[channel] = *control;
+                    }
+                }
+            }
+            Curve::Bezier(controls)
+        };
+    }
+    (frames, exact)
+}
+
+fn linear_control(t1: f32, v1: f32, t2: f32, v2: f32) -> [f32; 4] {
+    let (dt, dv) = (t2 - t1, v2 - v1);
+    [t1 + dt / 3.0, v1 + dv / 3.0, t1 + dt * 2.0 / 3.0, v1 + dv * 2.0 / 3.0]
+}
+
+/// 计算时间轴在`time`时第`channel`个通道的值
+fn sample(frames: &[Keyframe], time: f32, channel: usize) -> f32 {
+    let Some(first) = frames.first() else {
+        return 0.0;
+    };
+    if time <= first.time {
+        return first.values[channel];
+    }
+    for pair in frames.windows(2) {
+        let (from, to) = (&pair[0], &pair[1]);
+        if time >= to.time {
+            continue;
+        }
+        let (v1, v2) = (from.values[channel], to.values[channel]);
+        return match &from.curve {
+            Curve::Stepped => v1,
+            Curve::Linear => v1 + (v2 - v1) * (time - from.time) / (to.time - from.time),
+            Curve::Bezier(channels) => match channels.get(channel) {
+                Some(&control) => bezier_value(control, from.time, v1, to.time, v2, time),
+                None => v1 + (v2 - v1) * (time - from.time) / (to.time - from.time),
+            },
+        };
+    }
+    frames.last().map(|frame| frame.values[channel]).unwrap_or(0.0)
+}
+
+/// 二分求出贝塞尔曲线横坐标为`time`处的纵坐标
+fn bezier_value(control: [f32; 4], t1: f32, v1: f32, t2: f32, v2: f32, time: f32) -> f32 {
+    let [cx1, cy1, cx2, cy2] = control;
+    let point = |p0: f32, p1: f32, p2: f32, p3: f32, s: f32| {
+        let u = 1.0 - s;
+        u * u * u * p0 + 3.0 * u * u * s * p1 + 3.0 * u * s * s * p2 + s * s * s * p3
+    };
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..32 {
+        let middle = (low + high) / 2.0;
+        if point(t1, cx1, cx2, t2, middle) < time {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    point(v1, cy1, cy2, v2, (low + high) / 2.0)
+}
+
+/// 各帧所有值有变化的通道换算成3.8相对坐标后是否为同一条曲线
+fn uniform_curves(frames: &[Keyframe]) -> bool {
+    frames.windows(2).all(|pair| {
+        let (from, to) = (&pair[0], &pair[1]);
+        let Curve::Bezier(channels) = &from.curve else {
+            return true;
+        };
+        let normalized: Vec<[f32; 4]> = channels.iter()
+            .zip(channel_ranges(from, to))
+            .filter(|(_, (v1, v2))| v1 != v2)
+            .map(|(control, range)| normalize_control(*control, from.time, to.time, range))
+            .collect();
+        normalized.windows(2).all(|pair| {
+            pair[0].iter().zip(&pair[1]).all(|(a, b)| (a - b).abs() <= CURVE_EPSILON)
+        })
+    })
+}
+
+/// 4.x文件头的hash为8字节整数，以16位十六进制保存
+fn hash_bytes(hash: &str) -> Option<[u8; 8]> {
+    if hash.len() != 16 {
+        return None;
+    }
+    let mut bytes = [0u8; 8];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hash.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(bytes)
+}
+
+/// 按`data.format`写出JSON或二进制Skel
+///
+/// 输出与源文件相同时拒绝写出；输出文件已存在时只有`force`为真才覆盖。
+pub fn write_converted(data: &SkeletonData, source: &Path, path: &Path, binary: bool, force: bool) -> Result<()> {
+    if let (Ok(source), Ok(output)) = (fs::canonicalize(source), fs::canonicalize(path)) {
+        if source == output {
+            anyhow::bail!("输出文件与源文件相同，请另选输出路径: {:?}", path);
+        }
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("无法创建输出目录")?;
+    }
+    let bytes = if binary {
+        to_binary(data)
+    } else {
+        let mut content = serde_json::to_string_pretty(&to_json(data))?;
+        content.push('\n');
+        content.into_bytes()
+    };
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true);
+    if force {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    let mut file = match options.open(path) {
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            anyhow::bail!("输出文件已存在: {:?}（使用 --force 覆盖）", path)
+        }
+        result => result.with_context(|| format!("无法写入 {:?}", path))?,
+    };
+    file.write_all(&bytes).with_context(|| format!("无法写入 {:?}", path))
+}
+
+// ---------- JSON ----------
+
+/// 生成目标版本的Spine JSON
+pub fn to_json(data: &SkeletonData) -> Value {
+    let v38 = data.format == Format::V38;
+    let mut root = Map::new();
+
+    let mut skeleton = Map::new();
+    skeleton.insert("hash".into(), json!(data.hash));
+    skeleton.insert("spine".into(), json!(data.version));
+    skeleton.insert("x".into(), num(data.x));
+    skeleton.insert("y".into(), num(data.y));
+    skeleton.insert("width".into(), num(data.width));
+    skeleton.insert("height".into(), num(data.height));
+    if let Some(nonessential) = &data.nonessential {
+        skeleton.insert("fps".into(), num(nonessential.fps));
+        insert_some(&mut skeleton, "images", &nonessential.images);
+        insert_some(&mut skeleton, "audio", &nonessential.audio);
+    }
+    root.insert("skeleton".into(), Value::Object(skeleton));
+
+    let bones: Vec<Value> = data.bones.iter()
+        .map(|bone| {
+            let mut map = Map::new();
+            map.insert("name".into(), json!(bone.name));
+            if let Some(parent) = bone.parent {
+                map.insert("parent".into(), json!(data.bones[parent].name));
+            }
+            map.insert("length".into(), num(bone.length));
+            map.insert("rotation".into(), num(bone.rotation));
+            map.insert("x".into(), num(bone.x));
+            map.insert("y".into(), num(bone.y));
+            map.insert("scaleX".into(), num(bone.scale_x));
+            map.insert("scaleY".into(), num(bone.scale_y));
+            map.insert("shearX".into(), num(bone.shear_x));
+            map.insert("shearY".into(), num(bone.shear_y));
+            map.insert("transform".into(), json!(enum_name(&TRANSFORM_MODES, bone.transform_mode)));
+            map.insert("skin".into(), json!(bone.skin_required));
+            if let Some(color) = bone.color {
+                map.insert("color".into(), json!(rgba_hex(color)));
+            }
+            Value::Object(map)
+        })
+        .collect();
+    root.insert("bones".into(), Value::Array(bones));
+
+    let slots: Vec<Value> = data.slots.iter()
+        .map(|slot| {
+            let mut map = Map::new();
+            map.insert("name".into(), json!(slot.name));
+            map.insert("bone".into(), json!(data.bones[slot.bone].name));
+            map.insert("color".into(), json!(rgba_hex(slot.color)));
+            if let Some(dark) = slot.dark_color {
+                map.insert("dark".into(), json!(rgb_hex(dark)));
+            }
+            insert_some(&mut map, "attachment", &slot.attachment);
+            map.insert("blend".into(), json!(enum_name(&BLEND_MODES, slot.blend_mode)));
+            Value::Object(map)
+        })
+        .collect();
+    root.insert("slots".into(), Value::Array(slots));
+
+    let bone_names = |bones: &[usize]| -> Value { json!(bones.iter().map(|&b| &data.bones[b].name).collect::<Vec<_>>()) };
+
+    let ik: Vec<Value> = data.ik_constraints.iter()
+        .map(|constraint| json!({
+            "name": constraint.name,
+            "order": constraint.order,
+            "skin": constraint.skin_required,
+            "bones": bone_names(&constraint.bones),
+            "target": data.bones[constraint.target].name,
+            "mix": num(constraint.mix),
+            "softness": num(constraint.softness),
+            "bendPositive": constraint.bend_direction >= 0,
+            "compress": constraint.compress,
+            "stretch": constraint.stretch,
+            "uniform": constraint.uniform,
+        }))
+        .collect();
+    root.insert("ik".into(), Value::Array(ik));
+
+    let transform: Vec<Value> = data.transform_constraints.iter()
+        .map(|constraint| {
+            let mut map = json_object(json!({
+                "name": constraint.name,
+                "order": constraint.order,
+                "skin": constraint.skin_required,
+                "bones": bone_names(&constraint.bones),
+                "target": data.bones[constraint.target].name,
+                "local": constraint.local,
+                "relative": constraint.relative,
+                "rotation": num(constraint.offset_rotation),
+                "x": num(constraint.offset_x),
+                "y": num(constraint.offset_y),
+                "scaleX": num(constraint.offset_scale_x),
+                "scaleY": num(constraint.offset_scale_y),
+                "shearY": num(constraint.offset_shear_y),
+            }));
+            if v38 {
+                map.insert("rotateMix".into(), num(constraint.mix_rotate));
+                map.insert("translateMix".into(), num(constraint.mix_x));
+                map.insert("scaleMix".into(), num(constraint.mix_scale_x));
+                map.insert("shearMix".into(), num(constraint.mix_shear_y));
+            } else {
+                map.insert("mixRotate".into(), num(constraint.mix_rotate));
+                map.insert("mixX".into(), num(constraint.mix_x));
+                map.insert("mixY".into(), num(constraint.mix_y));
+                map.insert("mixScaleX".into(), num(constraint.mix_scale_x));
+                map.insert("mixScaleY".into(), num(constraint.mix_scale_y));
+                map.insert("mixShearY".into(), num(constraint.mix_shear_y));
+            }
+            Value::Object(map)
+        })
+        .collect();
+    root.insert("transform".into(), Value::Array(transform));
+
+    let path: Vec<Value> = data.path_constraints.iter()
+        .map(|constraint| {
+            let mut map = json_object(json!({
+                "name": constraint.name,
+                "order": constraint.order,
+                "skin": constraint.skin_required,
+                "bones": bone_names(&constraint.bones),
+                "target": data.slots[constraint.target].name,
+                "positionMode": enum_name(&POSITION_MODES, constraint.position_mode),
+                "spacingMode": enum_name(&SPACING_MODES, constraint.spacing_mode),
+                "rotateMode": enum_name(&ROTATE_MODES, constraint.rotate_mode),
+                "rotation": num(constraint.offset_rotation),
+                "position": num(constraint.position),
+                "spacing": num(constraint.spacing),
+            }));
+            if v38 {
+                map.insert("rotateMix".into(), num(constraint.mix_rotate));
+                map.insert("translateMix".into(), num(constraint.mix_x));
+            } else {
+                map.insert("mixRotate".into(), num(constraint.mix_rotate));
+                map.insert("mixX".into(), num(constraint.mix_x));
+                map.insert("mixY".into(), num(constraint.mix_y));
+            }
+            Value::Object(map)
+        })
+        .collect();
+    root.insert("path".into(), Value::Array(path));
+
+    let skins: Vec<Value> = data.skins.iter()
+        .map(|skin| {
+            let mut map = Map::new();
+            map.insert("name".into(), json!(skin.name));
+            if !skin.bones.is_empty() {
+                map.insert("bones".into(), bone_names(&skin.bones));
+            }
+            for (key, indices, names) in [
+                ("ik", &skin.ik_constraints, data.ik_constraints.iter().map(|c| &c.name).collect::<Vec<_>>()),
+                ("transform", &skin.transform_constraints, data.transform_constraints.iter().map(|c| &c.name).collect()),
+                ("path", &skin.path_constraints, data.path_constraints.iter().map(|c| &c.name).collect()),
+            ] {
+                if !indices.is_empty() {
+                    map.insert(key.into(), json!(indices.iter().map(|&i| names[i]).collect::<Vec<_>>()));
+                }
+            }
+            let mut attachments = Map::new();
+            for attachment in &skin.attachments {
+                let slot = attachments.entry(data.slots[attachment.slot].name.clone())
+                    .or_insert_with(|| Value::Object(Map::new()));
+                if let Value::Object(slot) = slot {
+                    slot.insert(attachment.placeholder.clone(), attachment_json(data, attachment));
+                }
+            }
+            map.insert("attachments".into(), Value::Object(attachments));
+            Value::Object(map)
+        })
+        .collect();
+    root.insert("skins".into(), Value::Array(skins));
+
+    let mut events = Map::new();
+    for event in &data.events {
+        let mut map = Map::new();
+        map.insert("int".into(), json!(event.int));
+        map.insert("float".into(), num(event.float));
+        insert_some(&mut map, "string", &event.string);
+        if let Some(audio) = &event.audio {
+            map.insert("audio".into(), json!(audio));
+            map.insert("volume".into(), num(event.volume));
+            map.insert("balance".into(), num(event.balance));
+        }
+        events.insert(event.name.clone(), Value::Object(map));
+    }
+    root.insert("events".into(), Value::Object(events));
+
+    let mut animations = Map::new();
+    for animation in &data.animations {
+        animations.insert(animation.name.clone(), animation_json(data, animation));
+    }
+    root.insert("animations".into(), Value::Object(animations));
+
+    Value::Object(root)
+}
+
+fn attachment_json(data: &SkeletonData, attachment: &SkinAttachment) -> Value {
+    let mut map = Map::new();
+    if let Some(name) = attachment.name.as_ref().filter(|name| **name != attachment.placeholder) {
+        map.insert("name".into(), json!(name));
+    }
+    let sequence_json = |sequence: &Option<Sequence>, map: &mut Map<String, Value>| {
+        if let Some(sequence) = sequence {
+            map.insert("sequence".into(), json!({
+                "count": sequence.count,
+                "start": sequence.start,
+                "digits": sequence.digits,
+                "setupIndex": sequence.setup_index,
+            }));
+        }
+    };
+
+    match &attachment.kind {
+        AttachmentKind::Region { path, rotation, x, y, scale_x, scale_y, width, height, color, sequence } => {
+            map.insert("type".into(), json!("region"));
+            insert_some(&mut map, "path", path);
+            map.insert("x".into(), num(*x));
+            map.insert("y".into(), num(*y));
+            map.insert("scaleX".into(), num(*scale_x));
+            map.insert("scaleY".into(), num(*scale_y));
+            map.insert("rotation".into(), num(*rotation));
+            map.insert("width".into(), num(*width));
+            map.insert("height".into(), num(*height));
+            map.insert("color".into(), json!(rgba_hex(*color)));
+            sequence_json(sequence, &mut map);
+        }
+        AttachmentKind::BoundingBox { vertices, color } => {
+            map.insert("type".into(), json!("boundingbox"));
+            map.insert("vertexCount".into(), json!(vertices.count()));
+            map.insert("vertices".into(), vertices_json(vertices));
+            insert_color(&mut map, *color);
+        }
+        AttachmentKind::Mesh { path, color, uvs, triangles, vertices, hull, sequence, edges, size } => {
+            map.insert("type".into(), json!("mesh"));
+            insert_some(&mut map, "path", path);
+            map.insert("color".into(), json!(rgba_hex(*color)));
+            map.insert("uvs".into(), floats(uvs));
+            map.insert("triangles".into(), json!(triangles.iter().map(|&i| i as u16).collect::<Vec<_>>()));
+            map.insert("vertices".into(), vertices_json(vertices));
+            map.insert("hull".into(), json!(hull));
+            if let Some(edges) = edges {
+                map.insert("edges".into(), json!(edges.iter().map(|&i| i as u16).collect::<Vec<_>>()));
+            }
+            if let Some((width, height)) = size {
+                map.insert("width".into(), num(*width));
+                map.insert("height".into(), num(*height));
+            }
+            sequence_json(sequence, &mut map);
+        }
+        AttachmentKind::LinkedMesh { path, color, skin, parent, inherit_timelines, sequence, size } => {
+            map.insert("type".into(), json!("linkedmesh"));
+            insert_some(&mut map, "path", path);
+            map.insert("color".into(), json!(rgba_hex(*color)));
+            insert_some(&mut map, "skin", skin);
+            insert_some(&mut map, "parent", parent);
+            // 4.1起该字段改名为timelines
+            let key = if data.format >= Format::V41 { "timelines" } else { "deform" };
+            map.insert(key.into(), json!(inherit_timelines));
+            if let Some((width, height)) = size {
+                map.insert("width".into(), num(*width));
+                map.insert("height".into(), num(*height));
+            }
+            sequence_json(sequence, &mut map);
+        }
+        AttachmentKind::Path { closed, constant_speed, vertices, lengths, color } => {
+            map.insert("type".into(), json!("path"));
+            map.insert("closed".into(), json!(closed));
+            map.insert("constantSpeed".into(), json!(constant_speed));
+            map.insert("vertexCount".into(), json!(vertices.count()));
+            map.insert("vertices".into(), vertices_json(vertices));
+            map.insert("lengths".into(), floats(lengths));
+            insert_color(&mut map, *color);
+        }
+        AttachmentKind::Point { rotation, x, y, color } => {
+            map.insert("type".into(), json!("point"));
+            map.insert("x".into(), num(*x));
+            map.insert("y".into(), num(*y));
+            map.insert("rotation".into(), num(*rotation));
+            insert_color(&mut map, *color);
+        }
+        AttachmentKind::Clipping { end_slot, vertices, color } => {
+            map.insert("type".into(), json!("clipping"));
+            map.insert("end".into(), json!(data.slots[*end_slot].name));
+            map.insert("vertexCount".into(), json!(vertices.count()));
+            map.insert("vertices".into(), vertices_json(vertices));
+            insert_color(&mut map, *color);
+        }
+    }
+    Value::Object(map)
+}
+
+/// 带权重的顶点展开为 [骨骼数, 骨骼, x, y, 权重, ...]
+fn vertices_json(vertices: &Vertices) -> Value {
+    match vertices {
+        Vertices::Plain(values) => floats(values),
+        Vertices::Weighted(vertices) => {
+            let mut values = Vec::new();
+            for weights in vertices {
+                values.push(json!(weights.len()));
+                for weight in weights {
+                    values.push(json!(weight.bone));
+                    values.push(num(weight.x));
+                    values.push(num(weight.y));
+                    values.push(num(weight.weight));
+                }
+            }
+            Value::Array(values)
+        }
+    }
+}
+
+fn animation_json(data: &SkeletonData, animation: &AnimationData) -> Value {
+    let v38 = data.format == Format::V38;
+    let mut map = Map::new();
+
+    let mut slots = Map::new();
+    for timelines in &animation.slots {
+        let mut slot = Map::new();
+        for timeline in &timelines.timelines {
+            match timeline {
+                SlotTimeline::Attachment(keys) => {
+                    let keys: Vec<Value> = keys.iter()
+                        .map(|key| json!({ "time": num(key.time), "name": key.name }))
+                        .collect();
+                    slot.insert("attachment".into(), Value::Array(keys));
+                }
+                SlotTimeline::Color { kind, frames } => {
+                    let name = match (v38, kind) {
+                        (true, ColorKind::Rgba) => "color",
+                        (true, _) => "twoColor",
+                        (false, kind) => kind.label(),
+                    };
+                    let keys = keyframes_json(data.format, frames, |values, key| match kind {
+                        ColorKind::Rgba => { key.insert("color".into(), json!(hex(values))); }
+                        ColorKind::Rgb => { key.insert("color".into(), json!(hex(values))); }
+                        ColorKind::Alpha => { key.insert("value".into(), num(values[0])); }
+                        ColorKind::Rgba2 => {
+                            key.insert("light".into(), json!(hex(&values[..4])));
+                            key.insert("dark".into(), json!(hex(&values[4..])));
+                        }
+                        ColorKind::Rgb2 => {
+                            key.insert("light".into(), json!(hex(&values[..3])));
+                            key.insert("dark".into(), json!(hex(&values[3..])));
+                        }
+                    });
+                    slot.insert(name.into(), keys);
+                }
+            }
+        }
+        slots.insert(data.slots[timelines.slot].name.clone(), Value::Object(slot));
+    }
+    if !slots.is_empty() {
+        map.insert("slots".into(), Value::Object(slots));
+    }
+
+    let mut bones = Map::new();
+    for timelines in &animation.bones {
+        let mut bone = Map::new();
+        for timeline in &timelines.timelines {
+            let keys = keyframes_json(data.format, &timeline.frames, |values, key| {
+                if timeline.kind.channels() == 2 {
+                    key.insert("x".into(), num(values[0]));
+                    key.insert("y".into(), num(values[1]));
+                } else {
+                    let name = if v38 { "angle" } else { "value" };
+                    key.insert(name.into(), num(values[0]));
+                }
+            });
+            bone.insert(timeline.kind.label().into(), keys);
+        }
+        bones.insert(data.bones[timelines.bone].name.clone(), Value::Object(bone));
+    }
+    if !bones.is_empty() {
+        map.insert("bones".into(), Value::Object(bones));
+    }
+
+    let mut ik = Map::new();
+    for timeline in &animation.ik_constraints {
+        let mut flags = timeline.flags.iter();
+        let keys = keyframes_json(data.format, &timeline.frames, |values, key| {
+            key.insert("mix".into(), num(values[0]));
+            key.insert("softness".into(), num(values[1]));
+            if let Some(flags) = flags.next() {
+                key.insert("bendPositive".into(), json!(flags.bend_direction >= 0));
+                key.insert("compress".into(), json!(flags.compress));
+                key.insert("stretch".into(), json!(flags.stretch));
+            }
+        });
+        ik.insert(data.ik_constraints[timeline.constraint].name.clone(), keys);
+    }
+    if !ik.is_empty() {
+        map.insert("ik".into(), Value::Object(ik));
+    }
+
+    let mut transform = Map::new();
+    for timeline in &animation.transform_constraints {
+        let keys = keyframes_json(data.format, &timeline.frames, |values, key| {
+            if v38 {
+                key.insert("rotateMix".into(), num(values[0]));
+                key.insert("translateMix".into(), num(values[1]));
+                key.insert("scaleMix".into(), num(values[3]));
+                key.insert("shearMix".into(), num(values[5]));
+            } else {
+                for (name, value) in ["mixRotate", "mixX", "mixY", "mixScaleX", "mixScaleY", "mixShearY"].iter().zip(values) {
+                    key.insert((*name).into(), num(*value));
+                }
+            }
+        });
+        transform.insert(data.transform_constraints[timeline.constraint].name.clone(), keys);
+    }
+    if !transform.is_empty() {
+        map.insert("transform".into(), Value::Object(transform));
+    }
+
+    let mut paths = Map::new();
+    for timelines in &animation.path_constraints {
+        let mut path = Map::new();
+        for timeline in &timelines.timelines {
+            let (name, keys) = match timeline.kind {
+                PathTimelineKind::Position | PathTimelineKind::Spacing => {
+                    let name = if timeline.kind == PathTimelineKind::Position { "position" } else { "spacing" };
+                    let value_name = if v38 { name } else { "value" };
+                    (name, keyframes_json(data.format, &timeline.frames, |values, key| {
+                        key.insert(value_name.into(), num(values[0]));
+                    }))
+                }
+                PathTimelineKind::Mix => ("mix", keyframes_json(data.format, &timeline.frames, |values, key| {
+                    if v38 {
+                        key.insert("rotateMix".into(), num(values[0]));
+                        key.insert("translateMix".into(), num(values[1]));
+                    } else {
+                        key.insert("mixRotate".into(), num(values[0]));
+                        key.insert("mixX".into(), num(values[1]));
+                        key.insert("mixY".into(), num(values[2]));
+                    }
+                })),
+            };
+            path.insert(name.into(), keys);
+        }
+        paths.insert(data.path_constraints[timelines.constraint].name.clone(), Value::Object(path));
+    }
+    if !paths.is_empty() {
+        map.insert("path".into(), Value::Object(paths));
+    }
+
+    // 4.0及以前为 deform: {皮肤: {插槽: {附件: [...]}}}，4.1为 attachments: {皮肤: {插槽: {附件: {deform, sequence}}}}
+    let mut attachments = Map::new();
+    for timeline in &animation.attachments {
+        let keys = match &timeline.kind {
+            AttachmentTimelineKind::Deform { frames, offsets } => {
+                let mut offsets = offsets.iter();
+                ("deform", keyframes_json(data.format, frames, |_, key| {
+                    if let Some(offsets) = offsets.next().filter(|offsets| !offsets.vertices.is_empty()) {
+                        key.insert("offset".into(), json!(offsets.start));
+                        key.insert("vertices".into(), floats(&offsets.vertices));
+                    }
+                }))
+            }
+            AttachmentTimelineKind::Sequence(keys) => ("sequence", Value::Array(keys.iter()
+                .map(|key| json!({
+                    "time": num(key.time),
+                    "mode": enum_name(&SEQUENCE_MODES, key.mode as usize),
+                    "index": key.index,
+                    "delay": num(key.delay),
+                }))
+                .collect())),
+        };
+        let skin = object_entry(&mut attachments, &data.skins[timeline.skin].name);
+        let slot = object_entry(skin, &data.slots[timeline.slot].name);
+        if data.format >= Format::V41 {
+            object_entry(slot, &timeline.attachment).insert(keys.0.into(), keys.1);
+        } else {
+            slot.insert(timeline.attachment.clone(), keys.1);
+        }
+    }
+    if !attachments.is_empty() {
+        let name = if data.format >= Format::V41 { "attachments" } else { "deform" };
+        map.insert(name.into(), Value::Object(attachments));
+    }
+
+    if !animation.draw_order.is_empty() {
+        let keys: Vec<Value> = animation.draw_order.iter()
+            .map(|key| json!({
+                "time": num(key.time),
+                "offsets": key.offsets.iter()
+                    .map(|offset| json!({ "slot": data.slots[offset.slot].name, "offset": offset.offset }))
+                    .collect::<Vec<_>>(),
+            }))
+            .collect();
+        map.insert("drawOrder".into(), Value::Array(keys));
+    }
+
+    if !animation.events.is_empty() {
+        let keys: Vec<Value> = animation.events.iter()
+            .map(|key| {
+                let event = &data.events[key.event];
+                let mut map = Map::new();
+                map.insert("time".into(), num(key.time));
+                map.insert("name".into(), json!(event.name));
+                map.insert("int".into(), json!(key.int));
+                map.insert("float".into(), num(key.float));
+                insert_some(&mut map, "string", &key.string);
+                if event.audio.is_some() {
+                    map.insert("volume".into(), num(key.volume));
+                    map.insert("balance".into(), num(key.balance));
+                }
+                Value::Object(map)
+            })
+            .collect();
+        map.insert("events".into(), Value::Array(keys));
+    }
+
+    Value::Object(map)
+}
+
+/// 生成关键帧数组，`values`写入各帧的值，曲线按目标版本的写法附加
+fn keyframes_json(format: Format, frames: &[Keyframe], mut values: impl FnMut(&[f32], &mut Map<String, Value>)) -> Value {
+    let keys = frames.iter()
+        .enumerate()
+        .map(|(i, frame)| {
+            let mut key = Map::new();
+            key.insert("time".into(), num(frame.time));
+            values(&frame.values, &mut key);
+            if let Some(next) = frames.get(i + 1) {
+                insert_curve(format, &mut key, frame, next);
+            }
+            Value::Object(key)
+        })
+        .collect();
+    Value::Array(keys)
+}
+
+/// 3.8为 curve（cx1）、c2、c3、c4 的相对坐标；4.x为各通道绝对坐标控制点组成的数组
+fn insert_curve(format: Format, key: &mut Map<String, Value>, frame: &Keyframe, next: &Keyframe) {
+    if format == Format::V38 {
+        match frame.curve.to_legacy(frame, next) {
+            LegacyCurve::Linear => {}
+            LegacyCurve::Stepped => { key.insert("curve".into(), json!("stepped")); }
+            LegacyCurve::Bezier([cx1, cy1, cx2, cy2]) => {
+                key.insert("curve".into(), num(cx1));
+                key.insert("c2".into(), num(cy1));
+                key.insert("c3".into(), num(cx2));
+                key.insert("c4".into(), num(cy2));
+            }
+        }
+        return;
+    }
+    match &frame.curve {
+        Curve::Linear => {}
+        Curve::Stepped => { key.insert("curve".into(), json!("stepped")); }
+        Curve::Bezier(channels) => {
+            let values: Vec<f32> = channels.iter().flatten().copied().collect();
+            key.insert("curve".into(), floats(&values));
+        }
+    }
+}
+
+fn object_entry<'a>(map: &'a mut Map<String, Value>, key: &str) -> &'a mut Map<String, Value> {
+    let value = map.entry(key.to_string()).or_insert_with(|| Value::Object(Map::new()));
+    match value {
+        Value::Object(object) => object,
+        _ => unreachable!("只插入对象"),
+    }
+}
+
+fn json_object(value: Value) -> Map<String, Value> {
+    match value {
+        Value::Object(map) => map,
+        _ => Map::new(),
+    }
+}
+
+/// f32按最短的十进制形式写出，避免 0.1 变成 0.10000000149011612
+fn num(value: f32) -> Value {
+    value.to_string()
+        .parse::<f64>()
+        .ok()
+        .and_then(serde_json::Number::from_f64)
+        .map(Value::Number)
+        .unwrap_or(Value::Null)
+}
+
+fn floats(values: &[f32]) -> Value {
+    Value::Array(values.iter().map(|&value| num(value)).collect())
+}
+
+fn insert_some(map: &mut Map<String, Value>, key: &str, value: &Option<String>) {
+    if let Some(value) = value {
+        map.insert(key.into(), json!(value));
+    }
+}
+
+fn insert_color(map: &mut Map<String, Value>, color: Option<u32>) {
+    if let Some(color) = color {
+        map.insert("color".into(), json!(rgba_hex(color)));
+    }
+}
+
+fn enum_name(names: &[&'static str], index: usize) -> &'static str {
+    names.get(index).copied().unwrap_or(names[0])
+}
+
+fn rgba_hex(color: u32) -> String {
+    format!("{:08x}", color)
+}
+
+fn rgb_hex(color: u32) -> String {
+    format!("{:06x}", color & 0x00ff_ffff)
+}
+
+/// 0~1的通道值 -> 十六进制颜色
+fn hex(values: &[f32]) -> String {
+    values.iter().map(|&value| format!("{:02x}", color_byte(value))).collect()
+}
+
+fn color_byte(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+// ---------- 二进制 ----------
+
+/// 生成目标版本的二进制Skel
+pub fn to_binary(data: &SkeletonData) -> Vec<u8> {
+    let mut encoder = Encoder {
+        format: data.format,
+        nonessential: data.nonessential.is_some(),
+        out: Vec::new(),
+        strings: Vec::new(),
+        string_index: HashMap::new(),
+    };
+    encoder.write_body(data);
+    let body = std::mem::take(&mut encoder.out);
+
+    // 字符串表位于文件头之后，需等正文写完才能确定
+    if data.format == Format::V38 {
+        encoder.string(Some(&data.hash));
+    } else {
+        encoder.out.extend(hash_bytes(&data.hash).unwrap_or([0; 8]));
+    }
+    encoder.string(Some(&data.version));
+    for value in [data.x, data.y, data.width, data.height] {
+        encoder.float(value);
+    }
+    encoder.bool(data.nonessential.is_some());
+    if let Some(nonessential) = &data.nonessential {
+        encoder.float(nonessential.fps);
+        encoder.string(nonessential.images.as_deref());
+        encoder.string(nonessential.audio.as_deref());
+    }
+    let strings = std::mem::take(&mut encoder.strings);
+    encoder.varint(strings.len());
+    for value in &strings {
+        encoder.string(Some(value));
+    }
+
+    encoder.out.extend(body);
+    encoder.out
+}
+
+/// 编辑器默认的骨骼颜色，非必要数据缺少颜色时使用
+const DEFAULT_BONE_COLOR: u32 = 0x9b9b9bff;
+const DEFAULT_ATTACHMENT_COLOR: u32 = 0x00000000;
+
+struct Encoder {
+    format: Format,
+    nonessential: bool,
+    out: Vec<u8>,
+    strings: Vec<String>,
+    string_index: HashMap<String, usize>,
+}
+
+impl Encoder {
+    fn write_body(&mut self, data: &SkeletonData) {
+        self.varint(data.bones.len());
+        for (i, bone) in data.bones.iter().enumerate() {
+            self.string(Some(&bone.name));
+            if i > 0 {
+                self.varint(bone.parent.unwrap_or(0));
+            }
+            for value in [bone.rotation, bone.x, bone.y, bone.scale_x, bone.scale_y, bone.shear_x, bone.shear_y, bone.length] {
+                self.float(value);
+            }
+            self.varint(bone.transform_mode);
+            self.bool(bone.skin_required);
+            self.nonessential_color(bone.color, DEFAULT_BONE_COLOR);
+        }
+
+        self.varint(data.slots.len());
+        for slot in &data.slots {
+            self.string(Some(&slot.name));
+            self.varint(slot.bone);
+            self.int(slot.color as i32);
+            self.int(slot.dark_color.map(|color| color as i32).unwrap_or(-1));
+            self.string_ref(slot.attachment.as_deref());
+            self.varint(slot.blend_mode);
+        }
+
+        self.varint(data.ik_constraints.len());
+        for constraint in &data.ik_constraints {
+            self.constraint_head(&constraint.name, constraint.order, constraint.skin_required, &constraint.bones);
+            self.varint(constraint.target);
+            self.float(constraint.mix);
+            self.float(constraint.softness);
+            self.byte(constraint.bend_direction as u8);
+            self.bool(constraint.compress);
+            self.bool(constraint.stretch);
+            self.bool(constraint.uniform);
+        }
+
+        self.varint(data.transform_constraints.len());
+        for constraint in &data.transform_constraints {
+            self.constraint_head(&constraint.name, constraint.order, constraint.skin_required, &constraint.bones);
+            self.varint(constraint.target);
+            self.bool(constraint.local);
+            self.bool(constraint.relative);
+            for value in [
+                constraint.offset_rotation, constraint.offset_x, constraint.offset_y,
+                constraint.offset_scale_x, constraint.offset_scale_y, constraint.offset_shear_y,
+            ] {
+                self.float(value);
+            }
+            if self.format == Format::V38 {
+                for value in [constraint.mix_rotate, constraint.mix_x, constraint.mix_scale_x, constraint.mix_shear_y] {
+                    self.float(value);
+                }
+            } else {
+                for value in [
+                    constraint.mix_rotate, constraint.mix_x, constraint.mix_y,
+                    constraint.mix_scale_x, constraint.mix_scale_y, constraint.mix_shear_y,
+                ] {
+                    self.float(value);
+                }
+            }
+        }
+
+        self.varint(data.path_constraints.len());
+        for constraint in &data.path_constraints {
+            self.constraint_head(&constraint.name, constraint.order, constraint.skin_required, &constraint.bones);
+            self.varint(constraint.target);
+            self.varint(constraint.position_mode);
+            self.varint(constraint.spacing_mode);
+            self.varint(constraint.rotate_mode);
+            for value in [constraint.offset_rotation, constraint.position, constraint.spacing, constraint.mix_rotate, constraint.mix_x] {
+                self.float(value);
+            }
+            if self.format != Format::V38 {
+                self.float(constraint.mix_y);
+            }
+        }
+
+        let (default_skin, skins) = match data.skins.split_first() {
+            Some((first, rest)) if first.name == "default" => (Some(first), rest),
+            _ => (None, data.skins.as_slice()),
+        };
+        match default_skin {
+            Some(skin) => self.skin_attachments(skin),
+            None => self.varint(0),
+        }
+        self.varint(skins.len());
+        for skin in skins {
+            self.string_ref(Some(&skin.name));
+            for indices in [&skin.bones, &skin.ik_constraints, &skin.transform_constraints, &skin.path_constraints] {
+                self.indices(indices);
+            }
+            self.skin_attachments(skin);
+        }
+
+        self.varint(data.events.len());
+        for event in &data.events {
+            self.string_ref(Some(&event.name));
+            self.signed_varint(event.int);
+            self.float(event.float);
+            self.string(event.string.as_deref());
+            self.string(event.audio.as_deref());
+            if event.audio.is_some() {
+                self.float(event.volume);
+                self.float(event.balance);
+            }
+        }
+
+        self.varint(data.animations.len());
+        for animation in &data.animations {
+            self.string(Some(&animation.name));
+            self.animation(data, animation);
+        }
+    }
+
+    fn constraint_head(&mut self, name: &str, order: usize, skin_required: bool, bones: &[usize]) {
+        self.string(Some(name));
+        self.varint(order);
+        self.bool(skin_required);
+        self.indices(bones);
+    }
+
+    /// 按插槽分组写出皮肤中的附件（保持原有顺序，同一插槽的附件连续排列）
+    fn skin_attachments(&mut self, skin: &SkinData) {
+        let groups = group_by(&skin.attachments, |attachment| attachment.slot);
+        self.varint(groups.len());
+        for (slot, attachments) in groups {
+            self.varint(slot);
+            self.varint(attachments.len());
+            for attachment in attachments {
+                self.string_ref(Some(&attachment.placeholder));
+                self.string_ref(attachment.name.as_deref());
+                self.attachment(&attachment.kind);
+            }
+        }
+    }
+
+    fn attachment(&mut self, kind: &AttachmentKind) {
+        match kind {
+            AttachmentKind::Region { path, rotation, x, y, scale_x, scale_y, width, height, color, sequence } => {
+                self.byte(ATTACHMENT_REGION);
+                self.string_ref(path.as_deref());
+                for value in [*rotation, *x, *y, *scale_x, *scale_y, *width, *height] {
+                    self.float(value);
+                }
+                self.int(*color as i32);
+                self.sequence(sequence);
+            }
+            AttachmentKind::BoundingBox { vertices, color } => {
+                self.byte(ATTACHMENT_BOUNDING_BOX);
+                self.varint(vertices.count());
+                self.vertices(vertices);
+                self.nonessential_color(*color, DEFAULT_ATTACHMENT_COLOR);
+            }
+            AttachmentKind::Mesh { path, color, uvs, triangles, vertices, hull, sequence, edges, size } => {
+                self.byte(ATTACHMENT_MESH);
+                self.string_ref(path.as_deref());
+                self.int(*color as i32);
+                self.varint(uvs.len() / 2);
+                for &value in uvs {
+                    self.float(value);
+                }
+                self.shorts(triangles);
+                self.vertices(vertices);
+                self.varint(*hull);
+                self.sequence(sequence);
+                if self.nonessential {
+                    self.shorts(edges.as_deref().unwrap_or_default());
+                    let (width, height) = size.unwrap_or_default();
+                    self.float(width);
+                    self.float(height);
+                }
+            }
+            AttachmentKind::LinkedMesh { path, color, skin, parent, inherit_timelines, sequence, size } => {
+                self.byte(ATTACHMENT_LINKED_MESH);
+                self.string_ref(path.as_deref());
+                self.int(*color as i32);
+                self.string_ref(skin.as_deref());
+                self.string_ref(parent.as_deref());
+                self.bool(*inherit_timelines);
+                self.sequence(sequence);
+                if self.nonessential {
+                    let (width, height) = size.unwrap_or_default();
+                    self.float(width);
+                    self.float(height);
+                }
+            }
+            AttachmentKind::Path { closed, constant_speed, vertices, lengths, color } => {
+                self.byte(ATTACHMENT_PATH);
+                self.bool(*closed);
+                self.bool(*constant_speed);
+                self.varint(vertices.count());
+                self.vertices(vertices);
+                for &value in lengths {
+                    self.float(value);
+                }
+                self.nonessential_color(*color, DEFAULT_ATTACHMENT_COLOR);
+            }
+            AttachmentKind::Point { rotation, x, y, color } => {
+                self.byte(ATTACHMENT_POINT);
+                for value in [*rotation, *x, *y] {
+                    self.float(value);
+                }
+                self.nonessential_color(*color, DEFAULT_ATTACHMENT_COLOR);
+            }
+            AttachmentKind::Clipping { end_slot, vertices, color } => {
+                self.byte(ATTACHMENT_CLIPPING);
+                self.varint(*end_slot);
+                self.varint(vertices.count());
+                self.vertices(vertices);
+                self.nonessential_color(*color, DEFAULT_ATTACHMENT_COLOR);
+            }
+        }
+    }
+
+    fn sequence(&mut self, sequence: &Option<Sequence>) {
+        if self.format < Format::V41 {
+            return;
+        }
+        self.bool(sequence.is_some());
+        if let Some(sequence) = sequence {
+            for value in [sequence.count, sequence.start, sequence.digits, sequence.setup_index] {
+                self.varint(value);
+            }
+        }
+    }
+
+    fn vertices(&mut self, vertices: &Vertices) {
+        match vertices {
+            Vertices::Plain(values) => {
+                self.bool(false);
+                for &value in values {
+                    self.float(value);
+                }
+            }
+            Vertices::Weighted(vertices) => {
+                self.bool(true);
+                for weights in vertices {
+                    self.varint(weights.len());
+                    for weight in weights {
+                        self.varint(weight.bone);
+                        self.float(weight.x);
+                        self.float(weight.y);
+                        self.float(weight.weight);
+                    }
+                }
+            }
+        }
+    }
+
+    fn animation(&mut self, data: &SkeletonData, animation: &AnimationData) {
+        let v38 = self.format == Format::V38;
+
+        self.varint(animation.slots.len());
+        for timelines in &animation.slots {
+            self.varint(timelines.slot);
+            self.varint(timelines.timelines.len());
+            for timeline in &timelines.timelines {
+                match timeline {
+                    SlotTimeline::Attachment(keys) => {
+                        self.byte(0);
+                        self.varint(keys.len());
+                        for key in keys {
+                            self.float(key.time);
+                            self.string_ref(key.name.as_deref());
+                        }
+                    }
+                    SlotTimeline::Color { kind, frames } => {
+                        let type_byte = match (v38, kind) {
+                            (true, ColorKind::Rgba2 | ColorKind::Rgb2) => 2,
+                            (true, _) => 1,
+                            (false, kind) => *kind as u8 + 1,
+                        };
+                        self.byte(type_byte);
+                        self.varint(frames.len());
+                        let kind = *kind;
+                        self.timeline(frames, &[(); 0], kind.channels(), |encoder, values| {
+                            if encoder.format == Format::V38 {
+                                encoder.int(u32::from_be_bytes([
+                                    color_byte(values[0]), color_byte(values[1]), color_byte(values[2]), color_byte(values[3]),
+                                ]) as i32);
+                                if kind == ColorKind::Rgba2 {
+                                    encoder.int(u32::from_be_bytes([
+                                        0, color_byte(values[4]), color_byte(values[5]), color_byte(values[6]),
+                                    ]) as i32);
+                                }
+                            } else {
+                                for &value in values {
+                                    encoder.byte(color_byte(value));
+                                }
+                            }
+                        }, |_, _| {});
+                    }
+                }
+            }
+        }
+
+        self.varint(animation.bones.len());
+        for timelines in &animation.bones {
+            self.varint(timelines.bone);
+            self.varint(timelines.timelines.len());
+            for timeline in &timelines.timelines {
+                let type_byte = if v38 {
+                    match timeline.kind {
+                        BoneTimelineKind::Rotate => 0,
+                        BoneTimelineKind::Translate => 1,
+                        BoneTimelineKind::Scale => 2,
+                        _ => 3,
+                    }
+                } else {
+                    timeline.kind as u8
+                };
+                self.byte(type_byte);
+                self.varint(timeline.frames.len());
+                self.timeline(&timeline.frames, &[(); 0], timeline.kind.channels(), Self::floats, |_, _| {});
+            }
+        }
+
+        self.varint(animation.ik_constraints.len());
+        for timeline in &animation.ik_constraints {
+            self.varint(timeline.constraint);
+            self.varint(timeline.frames.len());
+            self.timeline(&timeline.frames, &timeline.flags, 2, Self::floats, |encoder, flags| {
+                encoder.byte(flags.bend_direction as u8);
+                encoder.bool(flags.compress);
+                encoder.bool(flags.stretch);
+            });
+        }
+
+        self.varint(animation.transform_constraints.len());
+        for timeline in &animation.transform_constraints {
+            self.varint(timeline.constraint);
+            self.varint(timeline.frames.len());
+            self.timeline(&timeline.frames, &[(); 0], 6, |encoder, values| {
+                if encoder.format == Format::V38 {
+                    encoder.floats(&[values[0], values[1], values[3], values[5]]);
+                } else {
+                    encoder.floats(values);
+                }
+            }, |_, _| {});
+        }
+
+        self.varint(animation.path_constraints.len());
+        for timelines in &animation.path_constraints {
+            self.varint(timelines.constraint);
+            self.varint(timelines.timelines.len());
+            for timeline in &timelines.timelines {
+                let (type_byte, channels) = match timeline.kind {
+                    PathTimelineKind::Position => (0, 1),
+                    PathTimelineKind::Spacing => (1, 1),
+                    PathTimelineKind::Mix => (2, 3),
+                };
+                self.byte(type_byte);
+                self.varint(timeline.frames.len());
+                self.timeline(&timeline.frames, &[(); 0], channels, |encoder, values| {
+                    if encoder.format == Format::V38 && channels == 3 {
+                        encoder.floats(&values[..2]);
+                    } else {
+                        encoder.floats(values);
+                    }
+                }, |_, _| {});
+            }
+        }
+
+        let skins = group_by(&animation.attachments, |timeline| timeline.skin);
+        self.varint(skins.len());
+        for (skin, timelines) in skins {
+            self.varint(skin);
+            let slots = group_by(&timelines, |timeline| timeline.slot);
+            self.varint(slots.len());
+            for (slot, timelines) in slots {
+                self.varint(slot);
+                self.varint(timelines.len());
+                for timeline in timelines {
+                    self.string_ref(Some(&timeline.attachment));
+                    match &timeline.kind {
+                        AttachmentTimelineKind::Deform { frames, offsets } => {
+                            if self.format >= Format::V41 {
+                                self.byte(0);
+                            }
+                            self.varint(frames.len());
+                            self.timeline(frames, offsets, 1, |_, _| {}, |encoder, offsets| {
+                                encoder.varint(offsets.vertices.len());
+                                if !offsets.vertices.is_empty() {
+                                    encoder.varint(offsets.start);
+                                    encoder.floats(&offsets.vertices);
+                                }
+                            });
+                        }
+                        AttachmentTimelineKind::Sequence(keys) => {
+                            self.byte(1);
+                            self.varint(keys.len());
+                            for key in keys {
+                                self.float(key.time);
+                                self.int(key.index << 4 | key.mode as i32);
+                                self.float(key.delay);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        self.varint(animation.draw_order.len());
+        for key in &animation.draw_order {
+            self.float(key.time);
+            self.varint(key.offsets.len());
+            for offset in &key.offsets {
+                self.varint(offset.slot);
+                self.varint(offset.offset as u32 as usize);
+            }
+        }
+
+        self.varint(animation.events.len());
+        for key in &animation.events {
+            self.float(key.time);
+            self.varint(key.event);
+            self.signed_varint(key.int);
+            self.float(key.float);
+            self.bool(key.string.is_some());
+            if let Some(string) = &key.string {
+                self.string(Some(string));
+            }
+            if data.events[key.event].audio.is_some() {
+                self.float(key.volume);
+                self.float(key.balance);
+            }
+        }
+    }
+
+    /// 写出曲线时间轴，布局与读取时相同（见`Decoder::read_timeline`）
+    ///
+    /// `extras`为空时每帧没有其余数据。
+    fn timeline<T>(
+        &mut self,
+        frames: &[Keyframe],
+        extras: &[T],
+        channels: usize,
+        mut head: impl FnMut(&mut Self, &[f32]),
+        mut tail: impl FnMut(&mut Self, &T),
+    ) {
+        if self.format == Format::V38 {
+            for (i, frame) in frames.iter().enumerate() {
+                self.float(frame.time);
+                head(self, &frame.values);
+                if let Some(extra) = extras.get(i) {
+                    tail(self, extra);
+                }
+                if let Some(next) = frames.get(i + 1) {
+                    match frame.curve.to_legacy(frame, next) {
+                        LegacyCurve::Linear => self.byte(CURVE_LINEAR),
+                        LegacyCurve::Stepped => self.byte(CURVE_STEPPED),
+                        LegacyCurve::Bezier(control) => {
+                            self.byte(CURVE_BEZIER);
+                            self.floats(&control);
+                        }
+                    }
+                }
+            }
+            return;
+        }
+
+        let beziers = frames.iter()
+            .take(frames.len().saturating_sub(1))
+            .filter(|frame| matches!(frame.curve, Curve::Bezier(_)))
+            .count();
+        self.varint(beziers * channels);
+        for (i, frame) in frames.iter().enumerate() {
+            self.float(frame.time);
+            head(self, &frame.values);
+            if i > 0 {
+                let previous = &frames[i - 1];
+                match &previous.curve {
+                    Curve::Linear => self.byte(CURVE_LINEAR),
+                    Curve::Stepped => self.byte(CURVE_STEPPED),
+                    Curve::Bezier(controls) => {
+                        self.byte(CURVE_BEZIER);
+                        let ranges = channel_ranges(previous, frame);
+                        for channel in 0..channels {
+                            let control = controls.get(channel).copied().unwrap_or_else(|| {
+                                let (v1, v2) = ranges.get(channel).copied().unwrap_or((0.0, 1.0));
+                                linear_control(previous.time, v1, frame.time, v2)
+                            });
+                            self.floats(&control);
+                        }
+                    }
+                }
+            }
+            if let Some(extra) = extras.get(i) {
+                tail(self, extra);
+            }
+        }
+    }
+
+    fn floats(&mut self, values: &[f32]) {
+        for &value in values {
+            self.float(value);
+        }
+    }
+
+    fn shorts(&mut self, values: &[i16]) {
+        self.varint(values.len());
+        for &value in values {
+            self.out.extend(value.to_be_bytes());
+        }
+    }
+
+    fn indices(&mut self, indices: &[usize]) {
+        self.varint(indices.len());
+        for &index in indices {
+            self.varint(index);
+        }
+    }
+
+    fn nonessential_color(&mut self, color: Option<u32>, default: u32) {
+        if self.nonessential {
+            self.int(color.unwrap_or(default) as i32);
+        }
+    }
+
+    fn byte(&mut self, value: u8) {
+        self.out.push(value);
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.byte(value as u8);
+    }
+
+    fn int(&mut self, value: i32) {
+        self.out.extend(value.to_be_bytes());
+    }
+
+    fn float(&mut self, value: f32) {
+        self.out.extend(value.to_be_bytes());
+    }
+
+    fn varint(&mut self, value: usize) {
+        let mut value = value as u32;
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.out.push(byte);
+                return;
+            }
+            self.out.push(byte | 0x80);
+        }
+    }
+
+    fn signed_varint(&mut self, value: i32) {
+        self.varint(((value << 1) ^ (value >> 31)) as u32 as usize);
+    }
+
+    fn string(&mut self, value: Option<&str>) {
+        match value {
+            None => self.varint(0),
+            Some(value) => {
+                self.varint(value.len() + 1);
+                self.out.extend(value.as_bytes());
+            }
+        }
+    }
+
+    /// 写出字符串表索引（0表示null）
+    fn string_ref(&mut self, value: Option<&str>) {
+        let Some(value) = value else {
+            self.varint(0);
+            return;
+        };
+        let index = match self.string_index.get(value) {
+            Some(&index) => index,
+            None => {
+                self.strings.push(value.to_string());
+                self.string_index.insert(value.to_string(), self.strings.len() - 1);
+                self.strings.len() - 1
+            }
+        };
+        self.varint(index + 1);
+    }
+}
+
+/// 将相邻且键相同的元素分为一组
+fn group_by<T, K: PartialEq>(items: &[T], key: impl Fn(&T) -> K) -> Vec<(K, Vec<&T>)> {
+    let mut groups: Vec<(K, Vec<&T>)> = Vec::new();
+    for item in items {
+        let item_key = key(item);
+        match groups.last_mut() {
+            Some((last, group)) if *last == item_key => group.push(item),
+            _ => groups.push((item_key, vec![item])),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::skel::Skeleton;
+
+    const SPINE_38: &[u8] = include_bytes!("../tests/fixtures/spine-3.8.skel");
+    const SPINE_40_SPLIT: &[u8] = include_bytes!("../tests/fixtures/spine-4.0-split.skel");
+    const SPINE_41: &[u8] = include_bytes!("../tests/fixtures/spine-4.1.skel");
+
+    /// 骨骼、插槽、皮肤、附件、动画与时间轴的数量
+    fn counts(data: &SkeletonData) -> [usize; 6] {
+        let attachments = data.skins.iter().map(|skin| skin.attachments.len()).sum();
+        let timelines = data.animations.iter()
+            .map(|animation| {
+                animation.slots.iter().map(|slot| slot.timelines.len()).sum::<usize>()
+                    + animation.bones.iter().map(|bone| bone.timelines.len()).sum::<usize>()
+                    + animation.ik_constraints.len()
+                    + animation.transform_constraints.len()
+                    + animation.attachments.len()
+            })
+            .sum();
+        [data.bones.len(), data.slots.len(), data.skins.len(), attachments, data.animations.len(), timelines]
+    }
+
+    /// 解析 -> 转换并写出二进制 -> 再次解析
+    fn round_trip(bytes: &[u8], target: Format) -> (SkeletonData, Conversion, SkeletonData) {
+        let source = decode_skeleton_data(bytes).unwrap();
+        let conversion = convert_skeleton(&source, target, true);
+        let parsed = decode_skeleton_data(&to_binary(&conversion.data)).unwrap();
+        (source, conversion, parsed)
+    }
+
+    fn assert_same_content(source: &SkeletonData, parsed: &SkeletonData) {
+        assert_eq!(counts(source), counts(parsed));
+        assert_eq!(Skeleton::from_data(source).names(), Skeleton::from_data(parsed).names());
+    }
+
+    #[test]
+    fn round_trip_3_8() {
+        let (source, conversion, parsed) = round_trip(SPINE_38, Format::V38);
+        assert!(conversion.issues.is_empty());
+        assert_eq!(parsed.version, "3.8.99");
+        assert_eq!(parsed.hash, source.hash);
+        assert_same_content(&source, &parsed);
+        assert_eq!(to_binary(&parsed), to_binary(&conversion.data));
+    }
+
+    #[test]
+    fn round_trip_4_1() {
+        let (source, conversion, parsed) = round_trip(SPINE_41, Format::V41);
+        assert!(conversion.issues.is_empty());
+        assert_eq!(parsed.version, "4.1.24");
+        assert_eq!(parsed.hash, source.hash);
+        assert_same_content(&source, &parsed);
+        assert_eq!(Skeleton::from_data(&parsed).region_paths, Skeleton::from_data(&source).region_paths);
+        assert_eq!(to_binary(&parsed), to_binary(&conversion.data));
+    }
+
+    #[test]
+    fn upgrade_3_8_drops_string_hash() {
+        let (source, conversion, parsed) = round_trip(SPINE_38, Format::V41);
+        assert_eq!(parsed.version, "4.1.24");
+        assert_eq!(parsed.hash, "0000000000000000");
+        assert_same_content(&source, &parsed);
+        assert!(matches!(conversion.issues.as_slice(),
+            [ConversionIssue { kind: IssueKind::Dropped, location, .. }] if location == "文件头"));
+    }
+
+    #[test]
+    fn downgrade_sequence_keeps_setup_frame() {
+        let (source, conversion, parsed) = round_trip(SPINE_41, Format::V38);
+        assert_eq!(parsed.version, "3.8.99");
+        assert_eq!(counts(&source), counts(&parsed));
+
+        let mesh = parsed.skins[0].attachments.iter()
+            .find(|attachment| attachment.placeholder == "mesh1")
+            .unwrap();
+        let AttachmentKind::Mesh { path, sequence, .. } = &mesh.kind else {
+            panic!("mesh1 应为网格附件");
+        };
+        assert_eq!(path.as_deref(), Some("mesh101"));
+        assert!(sequence.is_none());
+        assert!(conversion.issues.iter()
+            .any(|issue| issue.kind == IssueKind::Approximated && issue.location.ends_with("附件 mesh1")));
+    }
+
+    #[test]
+    fn downgrade_merges_split_timelines() {
+        let (source, conversion, parsed) = round_trip(SPINE_40_SPLIT, Format::V38);
+        assert_eq!(parsed.version, "3.8.99");
+        assert_eq!(&counts(&source)[..5], &counts(&parsed)[..5]);
+
+        for animation in &parsed.animations {
+            for timeline in animation.bones.iter().flat_map(|bone| &bone.timelines) {
+                assert!(matches!(timeline.kind,
+                    BoneTimelineKind::Rotate | BoneTimelineKind::Translate | BoneTimelineKind::Scale | BoneTimelineKind::Shear));
+            }
+            for timeline in animation.slots.iter().flat_map(|slot| &slot.timelines) {
+                if let SlotTimeline::Color { kind, .. } = timeline {
+                    assert!(matches!(kind, ColorKind::Rgba | ColorKind::Rgba2));
+                }
+            }
+        }
+
+        // translatex 0→1 取 1→5，translatey 0→1 取 2→6
+        let translate = &parsed.animations[0].bones[0].timelines[0];
+        assert_eq!(translate.kind, BoneTimelineKind::Translate);
+        assert_eq!(translate.frames.first().unwrap().values, [1.0, 2.0]);
+        assert_eq!(translate.frames.last().unwrap().values, [5.0, 6.0]);
+
+        let merged: Vec<&str> = conversion.issues.iter().map(|issue| issue.message.as_str()).collect();
+        assert!(merged.iter().any(|message| message.starts_with("translatex、translatey 时间轴已合并为 translate")));
+        assert!(merged.iter().any(|message| message.starts_with("alpha 时间轴已合并为 rgba")));
+
+        // alpha 0→255 合并到插槽初始颜色（白色）上
+        let color = parsed.animations[1].slots[0].timelines.iter()
+            .find_map(|timeline| match timeline {
+                SlotTimeline::Color { kind, frames } => Some((*kind, frames)),
+                SlotTimeline::Attachment(_) => None,
+            })
+            .unwrap();
+        assert_eq!(color.0, ColorKind::Rgba);
+        let values: Vec<&[f32]> = color.1.iter().map(|frame| frame.values.as_slice()).collect();
+        assert_eq!(values, [[1.0, 1.0, 1.0, 0.0].as_slice(), [1.0, 1.0, 1.0, 1.0].as_slice()]);
+    }
+
+    #[test]
+    fn downgrade_4_1_to_3_8_exact_values() {
+        let mut source = decode_skeleton_data(SPINE_41).unwrap();
+        // 加入alpha与序列帧时间轴
+        let frames = vec![
+            Keyframe { time: 0.0, values: vec![0.0], curve: Curve::Linear },
+            Keyframe { time: 1.0, values: vec![1.0], curve: Curve::Linear },
+        ];
+        source.animations[0].slots[0].timelines.retain(|timeline| matches!(timeline, SlotTimeline::Attachment(_)));
+        source.animations[0].slots[0].timelines.push(SlotTimeline::Color { kind: ColorKind::Alpha, frames });
+        source.animations[0].attachments.push(AttachmentTimeline {
+            skin: 0,
+            slot: 1,
+            attachment: "mesh1".to_string(),
+            kind: AttachmentTimelineKind::Sequence(vec![SequenceKey { time: 0.0, mode: 2, index: 0, delay: 0.1 }]),
+        });
+
+        let conversion = convert_skeleton(&source, Format::V38, true);
+        let parsed = decode_skeleton_data(&to_binary(&conversion.data)).unwrap();
+
+        let dropped: Vec<(&str, &str)> = conversion.issues.iter()
+            .filter(|issue| issue.kind == IssueKind::Dropped)
+            .map(|issue| (issue.location.as_str(), issue.message.as_str()))
+            .collect();
+        assert_eq!(dropped, [("动画 idle / 插槽 body / 附件 mesh1", "3.8 没有序列帧时间轴，已移除")]);
+        assert_eq!(parsed.animations[0].attachments.len(), source.animations[0].attachments.len() - 1);
+
+        let SlotTimeline::Color { kind, frames } = parsed.animations[0].slots[0].timelines.last().unwrap() else {
+            panic!("应有颜色时间轴");
+        };
+        assert_eq!(*kind, ColorKind::Rgba);
+        assert_eq!(frames[0].values, [1.0, 1.0, 1.0, 0.0]);
+        assert_eq!(frames[1].values, [1.0, 1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn write_converted_refuses_to_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("role.skel");
+        fs::write(&source, SPINE_41).unwrap();
+        let data = convert_skeleton(&decode_skeleton_data(SPINE_41).unwrap(), Format::V38, true).data;
+
+        assert!(write_converted(&data, &source, &source, true, true).is_err());
+        assert_eq!(fs::read(&source).unwrap(), SPINE_41);
+
+        let output = dir.path().join("role_3.8.skel");
+        write_converted(&data, &source, &output, true, false).unwrap();
+        assert!(write_converted(&data, &source, &output, true, false).is_err());
+        write_converted(&data, &source, &output, true, true).unwrap();
+        assert_eq!(fs::read(&output).unwrap(), to_binary(&data));
+    }
+}
@@ -0,0 +1,224 @@
+use anyhow::{Result, Context};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::Serialize;
+
+use crate::file_operations::collect_files_with_extension;
+use crate::sidecar::{original_names, NameSidecar};
+use crate::skel::read_skel_header;
+
+/// Skel指纹键的前缀，例如 `skel:1f2e3d4c5b6a7980`
+const FINGERPRINT_PREFIX: &str = "skel:";
+
+/// 中文显示名称词典：资源hash（文件名去掉扩展名）或Skel指纹 -> 角色/皮肤名称
+///
+/// 以按键排序、每行一条的JSON保存，便于团队成员各自编辑后用`dict merge`合并或直接用git合并。
+/// 改过名的文件按names.json中记录的原始文件名查找，改名不会使已有的名称失效。
+#[derive(Debug, Default, Serialize)]
+#[serde(transparent)]
+pub struct Dictionary {
+    entries: BTreeMap<String, String>,
+    /// 目录 -> 该目录的names.json，查找时按需读取
+    #[serde(skip)]
+    sidecars: Mutex<HashMap<PathBuf, NameSidecar>>,
+}
+
+/// 词典中的一条记录及其在工作区中对应的文件
+#[derive(Debug, Serialize)]
+pub struct DictionaryEntry {
+    pub key: String,
+    pub name: String,
+    pub files: Vec<PathBuf>,
+}
+
+/// 合并结果
+#[derive(Debug, Default)]
+pub struct MergeSummary {
+    pub added: usize,
+    pub unchanged: usize,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// 同一个键在两份词典中名称不同
+#[derive(Debug)]
+pub struct MergeConflict {
+    pub key: String,
+    pub ours: String,
+    pub theirs: String,
+}
+
+impl Dictionary {
+    /// 读取词典，文件不存在时返回空词典
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path).context("无法读取名称词典")?;
+        let raw: BTreeMap<String, String> = serde_json::from_str(&content)
+            .with_context(|| format!("名称词典格式错误: {:?}", path))?;
+        Ok(Self {
+            entries: raw.into_iter().map(|(key, name)| (normalize_key(&key), name)).collect(),
+            sidecars: Mutex::default(),
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut content = serde_json::to_string_pretty(self)?;
+        content.push('\n');
+        fs::write(path, content).context("无法写入名称词典")
+    }
+
+    /// 设置名称，返回被覆盖的旧名称
+    pub fn set(&mut self, key: &str, name: &str) -> Option<String> {
+        self.entries.insert(normalize_key(key), name.to_string())
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.entries.remove(&normalize_key(key))
+    }
+
+    /// 查找文件的显示名称：先按文件名hash，再按names.json中记录的原始文件名，Skel最后按指纹
+    pub fn lookup(&self, path: &Path) -> Option<&str> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        if let Some(name) = file_key(path).and_then(|key| self.entries.get(&key)) {
+            return Some(name);
+        }
+        if let Some(name) = self.original_key(path).and_then(|key| self.entries.get(&key)) {
+            return Some(name);
+        }
+        if is_skel(path) {
+            let header = read_skel_header(path).ok()?;
+            return self.entries.get(&fingerprint_key(&header.hash)).map(String::as_str);
+        }
+        None
+    }
+
+    /// 改过名的文件在游戏中的原始文件名对应的键
+    fn original_key(&self, path: &Path) -> Option<String> {
+        let dir = path.parent()?;
+        let name = path.file_name()?.to_str()?;
+        let mut sidecars = self.sidecars.lock().unwrap_or_else(|e| e.into_inner());
+        let sidecar = sidecars.entry(dir.to_path_buf())
+            .or_insert_with(|| NameSidecar::load(dir).unwrap_or_default());
+        sidecar.find(name).and_then(|record| file_key(Path::new(&record.original)))
+    }
+
+    /// 在`shown`（路径的显示形式）后附上`path`的显示名称，用于终端列表
+    pub fn annotate(&self, path: &Path, shown: String) -> String {
+        match self.lookup(path) {
+            Some(name) => format!("{} 〔{}〕", shown, name),
+            None => shown,
+        }
+    }
+
+    /// 列出名称包含`text`（不区分大小写）的记录；`text`为空时列出全部
+    ///
+    /// 在`dirs`中查找与键对应的文件（按文件名hash、原始文件名或Skel指纹）。
+    pub fn search(&self, text: &str, dirs: &[&Path]) -> Vec<DictionaryEntry> {
+        let text = text.to_lowercase();
+        let mut entries: Vec<DictionaryEntry> = self.entries.iter()
+            .filter(|(_, name)| name.to_lowercase().contains(&text))
+            .map(|(key, name)| DictionaryEntry { key: key.clone(), name: name.clone(), files: Vec::new() })
+            .collect();
+        if entries.is_empty() {
+            return entries;
+        }
+
+        let files: Vec<PathBuf> = dirs.iter()
+            .flat_map(|dir| ["png", "atlas", "skel"].map(|extension| collect_files_with_extension(dir, extension)))
+            .flatten()
+            .collect();
+        let originals = original_names(files.iter().map(PathBuf::as_path));
+
+        let mut keys: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+        for path in files {
+            let current = file_key(&path);
+            let original = originals.get(&path).and_then(|original| file_key(Path::new(original)));
+            if let Some(key) = original.filter(|key| Some(key) != current.as_ref()) {
+                keys.entry(key).or_default().push(path.clone());
+            }
+            if let Some(key) = current {
+                keys.entry(key).or_default().push(path.clone());
+            }
+            if is_skel(&path) {
+                if let Ok(header) = read_skel_header(&path) {
+                    keys.entry(fingerprint_key(&header.hash)).or_default().push(path);
+                }
+            }
+        }
+
+        for entry in &mut entries {
+            entry.files = keys.remove(&entry.key).unwrap_or_default();
+        }
+        entries
+    }
+
+    /// 合并另一份词典：新键直接加入；名称不同的键记为冲突，`prefer_theirs`时采用对方的名称
+    pub fn merge(&mut self, other: Dictionary, prefer_theirs: bool) -> MergeSummary {
+        let mut summary = MergeSummary::default();
+        for (key, theirs) in other.entries {
+            match self.entries.get(&key) {
+                None => {
+                    self.entries.insert(key, theirs);
+                    summary.added += 1;
+                }
+                Some(ours) if *ours == theirs => summary.unchanged += 1,
+                Some(ours) => {
+                    let ours = ours.clone();
+                    if prefer_theirs {
+                        self.entries.insert(key.clone(), theirs.clone());
+                    }
+                    summary.conflicts.push(MergeConflict { key, ours, theirs });
+                }
+            }
+        }
+        summary
+    }
+}
+
+/// 将文件或键转换为词典键：`--fingerprint`时读取Skel指纹，已存在的文件取原始文件名（names.json中有记录时）
+/// 或当前文件名的hash，其余视为键本身
+pub fn resolve_key(target: &str, fingerprint: bool) -> Result<String> {
+    let path = Path::new(target);
+    if fingerprint {
+        if !is_skel(path) {
+            anyhow::bail!("只有.skel文件才有指纹: {:?}", path);
+        }
+        return Ok(fingerprint_key(&read_skel_header(path)?.hash));
+    }
+    if path.is_file() {
+        // 以原始文件名为键，以后再改名也不影响
+        let original = original_names([path]).remove(path);
+        let key_path = original.as_deref().map(Path::new).unwrap_or(path);
+        return file_key(key_path).ok_or_else(|| anyhow::anyhow!("无法从文件名得到hash: {:?}", path));
+    }
+    Ok(normalize_key(target))
+}
+
+fn fingerprint_key(fingerprint: &str) -> String {
+    format!("{}{}", FINGERPRINT_PREFIX, fingerprint)
+}
+
+/// 文件名hash不区分大小写，统一为小写；3.x的Skel指纹区分大小写，保持原样
+fn normalize_key(key: &str) -> String {
+    if key.starts_with(FINGERPRINT_PREFIX) {
+        key.to_string()
+    } else {
+        key.to_lowercase()
+    }
+}
+
+fn file_key(path: &Path) -> Option<String> {
+    path.file_stem().and_then(|stem| stem.to_str()).map(str::to_lowercase)
+}
+
+fn is_skel(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case("skel"))
+        .unwrap_or(false)
+}
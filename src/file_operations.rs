@@ -1,71 +1,233 @@
 use anyhow::{Result, Context};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::fs::{self, File};
-use std::io::Read;
+use std::io::{ErrorKind, Read};
 use walkdir::WalkDir;
 use rayon::prelude::*;
-use indicatif::{ProgressBar, ProgressStyle};
 
-pub fn restore_file_extensions(dir_path: &Path) -> Result<()> {
+use crate::progress::Reporter;
+use crate::sidecar::{record_renames, Rename, SIDECAR_FILE};
+use crate::workspace::LoadFailure;
+
+/// 一批改名或移动的计划，目标名称已集中分配，互不相同
+#[derive(Debug, Default)]
+pub struct RenamePlan {
+    pub renames: Vec<Rename>,
+    /// 无法读取而未列入计划的文件
+    pub failures: Vec<LoadFailure>,
+}
+
+/// 执行改名计划的结果
+#[derive(Debug, Default)]
+pub struct RenameOutcome {
+    /// 已完成的改名；执行时目标被其他程序占用的，目标为重新分配的名称
+    pub done: Vec<Rename>,
+    /// 计划中与执行时失败的文件
+    pub failures: Vec<LoadFailure>,
+}
+
+/// 集中分配的目标名称
+///
+/// 所有目标名称在改名前分配，同一批中的两个文件不会得到相同的名称。
+#[derive(Debug, Default)]
+pub struct NameReservations {
+    reserved: HashSet<PathBuf>,
+}
+
+impl NameReservations {
+    /// `dir`中名为`stem.extension`的第一个可用路径，重名时添加自增数字后缀
+    pub fn reserve(&mut self, dir: &Path, stem: &str, extension: &str) -> PathBuf {
+        self.reserve_for(None, dir, stem, extension)
+    }
+
+    /// 同[`reserve`](Self::reserve)，但`file`自身的路径也视为可用；返回`file`时表示无需改名
+    pub fn reserve_for(&mut self, file: Option<&Path>, dir: &Path, stem: &str, extension: &str) -> PathBuf {
+        let taken = |candidate: &PathBuf| Some(candidate.as_path()) != file && candidate.exists();
+        let mut candidate = dir.join(format!("{}.{}", stem, extension));
+        let mut counter = 1;
+        while self.reserved.contains(&candidate) || taken(&candidate) {
+            candidate = dir.join(format!("{}_{}.{}", stem, counter, extension));
+            counter += 1;
+        }
+        self.reserved.insert(candidate.clone());
+        candidate
+    }
+}
+
+/// 计划为工作目录中没有扩展名的文件按文件头补上扩展名
+pub fn plan_extension_restore(dir_path: &Path, reporter: &dyn Reporter) -> RenamePlan {
     let files: Vec<PathBuf> = WalkDir::new(dir_path)
         .into_iter()
         .filter_map(|e| e.ok())
         .filter(|e| e.file_type().is_file())
         .map(|e| e.into_path())
+        .filter(|path| path.extension().is_none()) // 已有扩展名的跳过
         .collect();
 
-    let pb = ProgressBar::new(files.len() as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}")
-            .unwrap()
-            .progress_chars("#>-")
-    );
-    pb.set_message("恢复文件扩展名中...");
-
-    files.par_iter().for_each(|file_path| {
-        if let Err(e) = restore_single_file_extension(file_path) {
-            eprintln!("处理文件 {:?} 时出错: {}", file_path, e);
+    reporter.started("检测文件类型", files.len());
+
+    // 读取文件头较慢，并行检测；目标名称之后统一分配
+    let mut detected: Vec<(PathBuf, Result<Option<&'static str>>)> = files.into_par_iter()
+        .map(|file_path| {
+            let file_type = detect_file_type(&file_path);
+            match &file_type {
+                Ok(Some(_)) => reporter.processed(&file_path, None),
+                Ok(None) => reporter.skipped(&file_path, "无法识别文件类型"),
+                Err(e) => reporter.error(&file_path, &format!("{:#}", e)),
+            }
+            (file_path, file_type)
+        })
+        .collect();
+
+    reporter.finished("检测文件类型", "文件类型检测完成");
+
+    detected.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut plan = RenamePlan::default();
+    let mut reservations = NameReservations::default();
+    for (file_path, file_type) in detected {
+        match file_type {
+            Ok(Some(extension)) => {
+                let dir = file_path.parent().unwrap_or(Path::new("."));
+                let stem = file_path.file_stem().and_then(|s| s.to_str()).unwrap_or("file");
+                let to = reservations.reserve(dir, stem, extension);
+                plan.renames.push(Rename { from: file_path, to });
+            }
+            Ok(None) => {}
+            Err(e) => plan.failures.push(LoadFailure { path: file_path, error: format!("{:#}", e) }),
         }
-        pb.inc(1);
-    });
+    }
+    plan
+}
 
-    pb.finish_with_message("文件扩展名恢复完成");
-    Ok(())
+/// 计划将`source_dir`下指定扩展名的文件移入`target_dir`
+pub fn plan_organize(source_dir: &Path, extension: &str, target_dir: &Path) -> RenamePlan {
+    let extension = extension.trim_start_matches('.');
+    let mut files = collect_files_with_extension(source_dir, extension);
+    files.sort();
+
+    let mut reservations = NameReservations::default();
+    let renames = files.into_iter()
+        .filter(|path| path.parent() != Some(target_dir))
+        .map(|from| {
+            let stem = from.file_stem().and_then(|s| s.to_str()).unwrap_or("file");
+            let extension = from.extension().and_then(|s| s.to_str()).unwrap_or(extension);
+            let to = reservations.reserve(target_dir, stem, extension);
+            Rename { from, to }
+        })
+        .collect();
+    RenamePlan { renames, failures: Vec::new() }
 }
 
-fn restore_single_file_extension(file_path: &Path) -> Result<()> {
-    if file_path.extension().is_some() {
-        return Ok(()); // 已有扩展名，跳过
+/// 按计划改名或移动文件，按需创建目标目录，并将原始名称记入各目录的names.json
+///
+/// 绝不覆盖已有文件：目标在计划之后被其他程序占用时重新分配名称。
+/// 计划中的失败已在生成计划时报告过，这里只报告执行时的失败；names.json写入失败时同样记入`failures`，
+/// 已完成的改名仍在`done`中。
+pub fn execute_renames(plan: RenamePlan, reporter: &dyn Reporter) -> RenameOutcome {
+    let mut reservations = NameReservations {
+        reserved: plan.renames.iter().map(|rename| rename.to.clone()).collect(),
+    };
+    reporter.started("处理文件", plan.renames.len());
+    let mut outcome = RenameOutcome { done: Vec::new(), failures: plan.failures };
+    for rename in plan.renames {
+        match execute_rename(rename, &mut reservations) {
+            Ok(rename) => {
+                reporter.processed(&rename.from, Some(&describe_rename(&rename)));
+                outcome.done.push(rename);
+            }
+            Err(failure) => {
+                reporter.error(&failure.path, &failure.error);
+                outcome.failures.push(failure);
+            }
+        }
     }
+    reporter.finished("处理文件", &format!("已处理 {} 个文件", outcome.done.len()));
 
-    let file_type = detect_file_type(file_path)?;
+    if let Err(e) = record_renames(&outcome.done) {
+        let path = PathBuf::from(SIDECAR_FILE);
+        let error = format!("改名已完成，但未能记录原始名称: {:#}", e);
+        reporter.error(&path, &error);
+        outcome.failures.push(LoadFailure { path, error });
+    }
+    outcome
+}
 
-    if let Some(extension) = file_type {
-        let mut new_path = file_path.to_path_buf();
-        new_path.set_extension(extension);
+/// 例如 "a -> a.png"，移到其他目录时为 "移动: a.png -> a.png"
+fn describe_rename(rename: &Rename) -> String {
+    let name = |path: &Path| path.file_name().and_then(|n| n.to_str()).unwrap_or("?").to_string();
+    let prefix = if rename.from.parent() == rename.to.parent() { "" } else { "移动: " };
+    format!("{}{} -> {}", prefix, name(&rename.from), name(&rename.to))
+}
 
-        // 重名文件添加自增数字后缀
-        let mut counter = 1;
-        while new_path.exists() {
-            let stem = file_path.file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or("file");
-            new_path = file_path.with_file_name(format!("{}_{}.{}", stem, counter, extension));
-            counter += 1;
+fn execute_rename(mut rename: Rename, reservations: &mut NameReservations) -> std::result::Result<Rename, LoadFailure> {
+    let failure = |rename: Rename, e: std::io::Error| LoadFailure { path: rename.from, error: e.to_string() };
+    let dir = rename.to.parent().unwrap_or(Path::new(".")).to_path_buf();
+    if let Err(e) = fs::create_dir_all(&dir) {
+        return Err(failure(rename, e));
+    }
+    loop {
+        match rename_no_clobber(&rename.from, &rename.to) {
+            Ok(()) => return Ok(rename),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                let stem = rename.to.file_stem().and_then(|s| s.to_str()).unwrap_or("file").to_string();
+                let extension = rename.to.extension().and_then(|s| s.to_str()).unwrap_or("").to_string();
+                rename.to = reservations.reserve(&dir, &stem, &extension);
+            }
+            Err(e) => return Err(failure(rename, e)),
         }
+    }
+}
 
-        fs::rename(file_path, &new_path)?;
-        println!("✓ {} -> {}",
-            file_path.file_name().and_then(|n| n.to_str()).unwrap_or("?"),
-            new_path.file_name().and_then(|n| n.to_str()).unwrap_or("?")
-        );
+/// 改名但不覆盖已存在的目标，目标已存在时返回`ErrorKind::AlreadyExists`
+///
+/// Linux上使用renameat2的RENAME_NOREPLACE；文件系统不支持时退回硬链接后删除源文件，
+/// 再不支持（如FAT格式的共享文件夹）或跨文件系统时以create_new复制后删除源文件。
+pub fn rename_no_clobber(from: &Path, to: &Path) -> std::io::Result<()> {
+    #[cfg(target_os = "linux")]
+    let cross_device = {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        let c_from = CString::new(from.as_os_str().as_bytes())?;
+        let c_to = CString::new(to.as_os_str().as_bytes())?;
+        // SAFETY: 两个路径都是以NUL结尾的有效C字符串，调用期间一直存活
+        let result = unsafe {
+            libc::renameat2(libc::AT_FDCWD, c_from.as_ptr(), libc::AT_FDCWD, c_to.as_ptr(), libc::RENAME_NOREPLACE)
+        };
+        if result == 0 {
+            return Ok(());
+        }
+        let error = std::io::Error::last_os_error();
+        match error.raw_os_error() {
+            Some(libc::EXDEV) => true, // 跨文件系统，硬链接同样不可行
+            Some(libc::EINVAL) | Some(libc::ENOSYS) | Some(libc::EOPNOTSUPP) => false, // 文件系统或内核不支持
+            _ => return Err(error),
+        }
+    };
+    #[cfg(not(target_os = "linux"))]
+    let cross_device = false;
+
+    if !cross_device {
+        match fs::hard_link(from, to) {
+            Ok(()) => return fs::remove_file(from),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => return Err(e),
+            Err(_) => {} // 不支持硬链接
+        }
     }
 
-    Ok(())
+    let mut source = File::open(from)?;
+    let mut target = fs::OpenOptions::new().write(true).create_new(true).open(to)?;
+    if let Err(e) = std::io::copy(&mut source, &mut target).and_then(|_| target.sync_all()) {
+        drop(target);
+        let _ = fs::remove_file(to);
+        return Err(e);
+    }
+    fs::remove_file(from)
 }
 
-fn detect_file_type(file_path: &Path) -> Result<Option<&'static str>> {
+/// 按文件头判断文件类型，返回应补上的扩展名（png、json、xml、atlas或skel）
+pub fn detect_file_type(file_path: &Path) -> Result<Option<&'static str>> {
     let mut file = File::open(file_path).context("无法打开文件")?;
     let mut header = [0u8; 64]; // 增加到64字节以更好检测XML头部
     let bytes_read = file.read(&mut header).context("无法读取文件头")?;
@@ -126,167 +288,34 @@ fn can_read_to_string(file_path: &Path) -> Result<bool> {
     }
 }
 
-/// 按扩展名组织文件
-pub fn organize_files_by_extension(source_dir: &Path, extension: &str, target_dir: &Path) -> Result<()> {
-    if !target_dir.exists() {
-        fs::create_dir_all(target_dir)?;
-    }
-
-    let extension = if !extension.starts_with('.') {
-        format!(".{}", extension)
-    } else {
-        extension.to_string()
-    };
-
-    let files: Vec<PathBuf> = WalkDir::new(source_dir)
+/// 递归收集目录下指定扩展名（不区分大小写）的文件
+pub fn collect_files_with_extension(dir_path: &Path, extension: &str) -> Vec<PathBuf> {
+    WalkDir::new(dir_path)
         .into_iter()
         .filter_map(|e| e.ok())
         .filter(|e| e.file_type().is_file())
         .filter(|e| {
             e.path().extension()
                 .and_then(|ext| ext.to_str())
-                .map(|ext| ext == extension.trim_start_matches('.'))
+                .map(|ext| ext.eq_ignore_ascii_case(extension))
                 .unwrap_or(false)
         })
         .map(|e| e.into_path())
-        .collect();
-
-    if files.is_empty() {
-        println!("📁 未找到扩展名为 {} 的文件", extension);
-        return Ok(());
-    }
-
-    let pb = ProgressBar::new(files.len() as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}")
-            .unwrap()
-            .progress_chars("#>-")
-    );
-    pb.set_message(format!("移动 {} 文件中...", extension));
-
-    for file_path in files {
-        if let Err(e) = move_single_file(&file_path, target_dir) {
-            eprintln!("移动文件 {:?} 时出错: {}", file_path, e);
-        }
-        pb.inc(1);
-    }
-
-    pb.finish_with_message(format!("{} 文件移动完成", extension));
-    Ok(())
-}
-
-fn move_single_file(file_path: &Path, target_dir: &Path) -> Result<()> {
-    let file_name = file_path.file_name()
-        .and_then(|n| n.to_str())
-        .ok_or_else(|| anyhow::anyhow!("无效的文件名"))?;
-
-    let target_path = target_dir.join(file_name);
-
-    // 如果目标文件已存在，添加数字后缀
-    let final_target_path = if target_path.exists() {
-        let stem = Path::new(file_name)
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("file");
-        let extension = Path::new(file_name)
-            .extension()
-            .and_then(|s| s.to_str())
-            .unwrap_or("");
-
-        let mut counter = 1;
-        let mut new_target = target_dir.join(format!("{}_{}.{}", stem, counter, extension));
-        while new_target.exists() {
-            counter += 1;
-            new_target = target_dir.join(format!("{}_{}.{}", stem, counter, extension));
-        }
-        new_target
-    } else {
-        target_path
-    };
-
-    fs::rename(file_path, &final_target_path)?;
-    println!("✓ 移动: {} -> {}",
-        file_name,
-        final_target_path.file_name().and_then(|n| n.to_str()).unwrap_or("?")
-    );
-
-    Ok(())
+        .collect()
 }
 
-/// 根据尺寸重命名PNG文件
-pub fn rename_png_by_size(dir_path: &Path) -> Result<()> {
-    let files: Vec<PathBuf> = WalkDir::new(dir_path)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-        .filter(|e| {
-            e.path().extension()
-                .and_then(|ext| ext.to_str())
-                .map(|ext| ext.eq_ignore_ascii_case("png"))
-                .unwrap_or(false)
-        })
-        .map(|e| e.into_path())
-        .collect();
-
-    let pb = ProgressBar::new(files.len() as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}")
-            .unwrap()
-            .progress_chars("#>-")
-    );
-    pb.set_message("重命名PNG文件中...");
-
-    for file_path in files {
-        if let Err(e) = rename_single_png(&file_path) {
-            eprintln!("重命名PNG文件 {:?} 时出错: {}", file_path, e);
-        }
-        pb.inc(1);
+/// 角色文件夹中唯一的指定扩展名文件（例如唯一的.atlas或.skel）
+pub fn single_file_with_extension(dir: &Path, extension: &str) -> Result<PathBuf> {
+    let mut files = collect_files_with_extension(dir, extension);
+    match files.len() {
+        1 => Ok(files.remove(0)),
+        0 => anyhow::bail!("文件夹中没有 .{} 文件: {:?}", extension, dir),
+        n => anyhow::bail!("文件夹中有 {} 个 .{} 文件，无法确定使用哪一个: {:?}", n, extension, dir),
     }
-
-    pb.finish_with_message("PNG文件重命名完成");
-    Ok(())
-}
-
-fn rename_single_png(file_path: &Path) -> Result<()> {
-    let (width, height) = get_png_dimensions(file_path)?;
-
-    let new_name = format!("size_{}x{}.png", width, height);
-    let new_path = file_path.parent()
-        .unwrap_or(file_path)
-        .join(&new_name);
-
-    // 如果新文件名已存在，添加数字后缀
-    let final_new_path = if new_path.exists() {
-        let mut counter = 1;
-        let mut new_path_with_counter = file_path.parent()
-            .unwrap_or(file_path)
-            .join(format!("size_{}x{}_{}.png", width, height, counter));
-
-        while new_path_with_counter.exists() {
-            counter += 1;
-            new_path_with_counter = file_path.parent()
-                .unwrap_or(file_path)
-                .join(format!("size_{}x{}_{}.png", width, height, counter));
-        }
-        new_path_with_counter
-    } else {
-        new_path
-    };
-
-    fs::rename(file_path, &final_new_path)?;
-
-    println!("✓ {} -> {}",
-        file_path.file_name().and_then(|n| n.to_str()).unwrap_or("?"),
-        final_new_path.file_name().and_then(|n| n.to_str()).unwrap_or("?")
-    );
-
-    Ok(())
 }
 
-fn get_png_dimensions(file_path: &Path) -> Result<(u32, u32)> {
-  
+/// 读取PNG的宽高（仅解析签名与IHDR）
+pub fn get_png_dimensions(file_path: &Path) -> Result<(u32, u32)> {
     let mut file = File::open(file_path).context("无法打开PNG文件")?;
 
     // 检查PNG签名
@@ -314,4 +343,97 @@ fn get_png_dimensions(file_path: &Path) -> Result<(u32, u32)> {
     let height = u32::from_be_bytes([ihdr_data[4], ihdr_data[5], ihdr_data[6], ihdr_data[7]]);
 
     Ok((width, height))
-}
\ No newline at end of file
+}
+
+/// 计算文件内容的MD5（小写十六进制，与游戏资源的hash文件名同一形式）
+pub fn file_hash(file_path: &Path) -> Result<String> {
+    let mut file = File::open(file_path).context("无法打开文件")?;
+    let mut context = md5::Context::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).context("无法读取文件")?;
+        if read == 0 {
+            break;
+        }
+        context.consume(&buffer[..read]);
+    }
+    Ok(format!("{:x}", context.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::QuietReporter;
+
+    const PNG_HEADER: &[u8] = b"\x89PNG\r\n\x1a\n0000";
+
+    #[test]
+    fn planned_renames_to_same_stem_get_distinct_targets() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b, target) = (dir.path().join("a"), dir.path().join("b"), dir.path().join("atlas"));
+        fs::create_dir_all(&a).unwrap();
+        fs::create_dir_all(&b).unwrap();
+        fs::write(a.join("hero.atlas"), "a").unwrap();
+        fs::write(b.join("hero.atlas"), "b").unwrap();
+
+        let plan = plan_organize(dir.path(), "atlas", &target);
+        let targets: Vec<&Path> = plan.renames.iter().map(|rename| rename.to.as_path()).collect();
+        assert_eq!(targets, [target.join("hero.atlas"), target.join("hero_1.atlas")]);
+    }
+
+    #[test]
+    fn extension_restore_skips_existing_names() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("9f3c"), PNG_HEADER).unwrap();
+        fs::write(dir.path().join("9f3c.png"), "existing").unwrap();
+
+        let plan = plan_extension_restore(dir.path(), &QuietReporter);
+        assert_eq!(plan.renames.len(), 1);
+        assert_eq!(plan.renames[0].to, dir.path().join("9f3c_1.png"));
+    }
+
+    #[test]
+    fn rename_no_clobber_keeps_both_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+        fs::write(&from, "source").unwrap();
+        fs::write(&to, "target").unwrap();
+
+        let error = rename_no_clobber(&from, &to).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&from).unwrap(), "source");
+        assert_eq!(fs::read_to_string(&to).unwrap(), "target");
+    }
+
+    #[test]
+    fn execute_renames_reserves_again_when_target_appears() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("9f3c"), PNG_HEADER).unwrap();
+        let plan = plan_extension_restore(dir.path(), &QuietReporter);
+        assert_eq!(plan.renames[0].to, dir.path().join("9f3c.png"));
+
+        // 计划之后其他程序占用了目标名称
+        fs::write(dir.path().join("9f3c.png"), "other").unwrap();
+        let outcome = execute_renames(plan, &QuietReporter);
+
+        assert!(outcome.failures.is_empty());
+        assert_eq!(outcome.done[0].to, dir.path().join("9f3c_1.png"));
+        assert_eq!(fs::read_to_string(dir.path().join("9f3c.png")).unwrap(), "other");
+        assert_eq!(fs::read(dir.path().join("9f3c_1.png")).unwrap(), PNG_HEADER);
+        assert!(!dir.path().join("9f3c").exists());
+    }
+
+    #[test]
+    fn execute_renames_keeps_outcome_when_sidecar_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("9f3c"), PNG_HEADER).unwrap();
+        // names.json无法读写
+        fs::create_dir(dir.path().join(SIDECAR_FILE)).unwrap();
+
+        let outcome = execute_renames(plan_extension_restore(dir.path(), &QuietReporter), &QuietReporter);
+        assert_eq!(outcome.done.len(), 1);
+        assert_eq!(outcome.failures.len(), 1);
+        assert_eq!(outcome.failures[0].path, Path::new(SIDECAR_FILE));
+        assert!(dir.path().join("9f3c.png").exists());
+    }
+}
@@ -0,0 +1,282 @@
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use rayon::prelude::*;
+use serde::Serialize;
+use strsim::normalized_levenshtein;
+
+use crate::atlas::parse_atlas_file;
+use crate::file_operations::collect_files_with_extension;
+use crate::names::NameKind;
+use crate::progress::Reporter;
+use crate::skel::parse_skel_file;
+use crate::workspace::LoadFailure;
+
+/// 模糊搜索得到的一个名称
+#[derive(Debug, Serialize)]
+pub struct FuzzyMatch {
+    pub name: String,
+    /// 相似度（0~1）
+    pub score: f64,
+    /// 该名称出现过的类别
+    pub kinds: Vec<NameKind>,
+    /// 包含该名称的文件
+    pub files: Vec<PathBuf>,
+}
+
+/// 低于该相似度的名称不列出
+const MIN_SCORE: f64 = 0.5;
+
+/// 常用拼音音节，用于把`jiangdongzhizhi`这类连写的拼音切分为音节
+const PINYIN_SYLLABLES: &str = "\
+a ai an ang ao \
+ba bai ban bang bao bei ben beng bi bian biao bie bin bing bo bu \
+ca cai can cang cao ce cen ceng cha chai chan chang chao che chen cheng chi chong chou chu chua chuai chuan chuang chui chun chuo ci cong cou cu cuan cui cun cuo \
+da dai dan dang dao de dei den deng di dia dian diao die ding diu dong dou du duan dui dun duo \
+e ei en eng er \
+fa fan fang fei fen feng fo fou fu \
+ga gai gan gang gao ge gei gen geng gong gou gu gua guai guan guang gui gun guo \
+ha hai han hang hao he hei hen heng hong hou hu hua huai huan huang hui hun huo \
+ji jia jian jiang jiao jie jin jing jiong jiu ju juan jue jun \
+ka kai kan kang kao ke kei ken keng kong kou ku kua kuai kuan kuang kui kun kuo \
+la lai lan lang lao le lei leng li lia lian liang liao lie lin ling liu lo long lou lu luan lun luo lv lve \
+ma mai man mang mao me mei men meng mi mian miao mie min ming miu mo mou mu \
+na nai nan nang nao ne nei nen neng ni nian niang niao nie nin ning niu nong nou nu nuan nun nuo nv nve \
+o ou \
+pa pai pan pang pao pei pen peng pi pian piao pie pin ping po pou pu \
+qi qia qian qiang qiao qie qin qing qiong qiu qu quan que qun \
+ran rang rao re ren reng ri rong rou ru rua ruan rui run ruo \
+sa sai san sang sao se sen seng sha shai shan shang shao she shei shen sheng shi shou shu shua shuai shuan shuang shui shun shuo si song sou su suan sui sun suo \
+ta tai tan tang tao te teng ti tian tiao tie ting tong tou tu tuan tui tun tuo \
+wa wai wan wang wei wen weng wo wu \
+xi xia xian xiang xiao xie xin xing xiong xiu xu xuan xue xun \
+ya yan yang yao ye yi yin ying yo yong you yu yuan yue yun \
+za zai zan zang zao ze zei zen zeng zha zhai zhan zhang zhao zhe zhei zhen zheng zhi zhong zhou zhu zhua zhuai zhuan zhuang zhui zhun zhuo zi zong zou zu zuan zui zun zuo";
+
+/// 模糊搜索的结果
+#[derive(Debug, Default)]
+pub struct FuzzyResults {
+    pub matches: Vec<FuzzyMatch>,
+    /// 无法解析、未参与搜索的文件
+    pub failures: Vec<LoadFailure>,
+}
+
+/// 在Atlas与Skel解析出的名称中模糊搜索，返回相似度最高的`limit`个名称及包含它们的文件
+///
+/// `kinds`为空时不限类别。
+pub fn fuzzy_search_names(atlas_dir: &Path, skels_dir: &Path, query: &str, kinds: &[NameKind], limit: usize, reporter: &dyn Reporter) -> FuzzyResults {
+    let (index, failures) = collect_names(atlas_dir, skels_dir, reporter);
+    let syllables: BTreeSet<&str> = PINYIN_SYLLABLES.split_whitespace().collect();
+    let query = NameTokens::new(query, &syllables);
+
+    let mut matches: Vec<FuzzyMatch> = index.into_par_iter()
+        .filter(|(_, entry)| kinds.is_empty() || entry.kinds.iter().any(|kind| kinds.contains(kind)))
+        .filter_map(|(name, entry)| {
+            let score = similarity(&query, &NameTokens::new(&name, &syllables));
+            (score >= MIN_SCORE).then(|| FuzzyMatch {
+                name,
+                score,
+                kinds: entry.kinds.into_iter().collect(),
+                files: entry.files.into_iter().collect(),
+            })
+        })
+        .collect();
+
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.name.cmp(&b.name)));
+    matches.truncate(limit);
+    FuzzyResults { matches, failures }
+}
+
+#[derive(Default)]
+struct NameEntry {
+    kinds: BTreeSet<NameKind>,
+    files: BTreeSet<PathBuf>,
+}
+
+/// 并行解析全部Atlas与Skel，汇总名称 -> 类别与所在文件
+fn collect_names(atlas_dir: &Path, skels_dir: &Path, reporter: &dyn Reporter) -> (BTreeMap<String, NameEntry>, Vec<LoadFailure>) {
+    let mut files: Vec<PathBuf> = collect_files_with_extension(atlas_dir, "atlas");
+    files.extend(collect_files_with_extension(skels_dir, "skel"));
+
+    reporter.started("读取名称", files.len());
+
+    let parsed: Vec<(PathBuf, Result<Vec<_>>)> = files.par_iter()
+        .map(|path| {
+            let is_atlas = path.extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| ext.eq_ignore_ascii_case("atlas"))
+                .unwrap_or(false);
+            let names: Result<Vec<(NameKind, String)>> = if is_atlas {
+                parse_atlas_file(path).map(|atlas| {
+                    atlas.pages.iter().map(|page| (NameKind::Page, page.name.clone()))
+                        .chain(atlas.regions.iter().map(|region| (NameKind::Region, region.name.clone())))
+                        .collect()
+                })
+            } else {
+                parse_skel_file(path).map(|skeleton| {
+                    skeleton.names().into_iter()
+                        .map(|(kind, name)| (kind, name.to_string()))
+                        .collect()
+                })
+            };
+            match &names {
+                Ok(_) => reporter.processed(path, None),
+                Err(e) => reporter.error(path, &format!("{:#}", e)),
+            }
+            (path.clone(), names)
+        })
+        .collect();
+
+    reporter.finished("读取名称", "名称读取完成");
+
+    let mut index: BTreeMap<String, NameEntry> = BTreeMap::new();
+    let mut failures = Vec::new();
+    for (path, names) in parsed {
+        let names = match names {
+            Ok(names) => names,
+            Err(e) => {
+                failures.push(LoadFailure { path, error: format!("{:#}", e) });
+                continue;
+            }
+        };
+        for (kind, name) in names {
+            let entry = index.entry(name).or_default();
+            entry.kinds.insert(kind);
+            entry.files.insert(path.clone());
+        }
+    }
+    (index, failures)
+}
+
+/// 名称的规范化形式：整体小写、按非字母数字切分的词，以及切分出的拼音音节
+struct NameTokens {
+    full: String,
+    /// 排序后用下划线连接的词，与词序无关
+    sorted: String,
+    syllables: Vec<String>,
+}
+
+impl NameTokens {
+    fn new(name: &str, table: &BTreeSet<&str>) -> Self {
+        let full = name.to_lowercase();
+        let mut words: Vec<&str> = full.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect();
+        let syllables = words.iter()
+            .flat_map(|word| split_syllables(word, table).unwrap_or_else(|| vec![word.to_string()]))
+            .collect();
+        words.sort_unstable();
+        let sorted = words.join("_");
+        NameTokens { full, sorted, syllables }
+    }
+}
+
+/// 把一个词切分为音节数最少的拼音序列，无法完整切分时返回None
+fn split_syllables(word: &str, table: &BTreeSet<&str>) -> Option<Vec<String>> {
+    if !word.is_ascii() {
+        return None;
+    }
+
+    // best[i]：word[..i]的最少音节切分
+    let mut best: Vec<Option<Vec<&str>>> = vec![None; word.len() + 1];
+    best[0] = Some(Vec::new());
+    for end in 1..=word.len() {
+        // 拼音音节最长6个字母（如zhuang）
+        for start in end.saturating_sub(6)..end {
+            let (Some(prefix), true) = (&best[start], table.contains(&word[start..end])) else {
+                continue;
+            };
+            if best[end].as_ref().is_none_or(|current| prefix.len() + 1 < current.len()) {
+                let mut split = prefix.clone();
+                split.push(&word[start..end]);
+                best[end] = Some(split);
+            }
+        }
+    }
+
+    best.pop().flatten().map(|split| split.into_iter().map(str::to_string).collect())
+}
+
+/// 综合整体编辑距离与不计词序的音节匹配计算相似度
+fn similarity(query: &NameTokens, name: &NameTokens) -> f64 {
+    let whole = normalized_levenshtein(&query.full, &name.full);
+    let sorted = normalized_levenshtein(&query.sorted, &name.sorted);
+
+    // 查询的每个音节在名称中找最相近的音节，取平均作为覆盖度
+    let coverage = if query.syllables.is_empty() || name.syllables.is_empty() {
+        0.0
+    } else {
+        let total: f64 = query.syllables.iter()
+            .map(|q| name.syllables.iter().map(|n| syllable_similarity(q, n)).fold(0.0, f64::max))
+            .sum();
+        total / query.syllables.len() as f64
+    };
+
+    whole.max(0.85 * coverage + 0.15 * sorted)
+}
+
+/// 音节相似度，平翘舌（z/zh、c/ch、s/sh）与前后鼻音（an/ang、en/eng、in/ing）视为近似
+fn syllable_similarity(a: &str, b: &str) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+    if loosen(a) == loosen(b) {
+        return 0.9;
+    }
+    normalized_levenshtein(a, b)
+}
+
+fn loosen(syllable: &str) -> String {
+    let syllable = ["zh", "ch", "sh"].iter()
+        .find_map(|initial| syllable.strip_prefix(initial).map(|rest| format!("{}{}", &initial[..1], rest)))
+        .unwrap_or_else(|| syllable.to_string());
+    match syllable.strip_suffix("ng") {
+        Some(rest) if rest.ends_with(['a', 'e', 'i']) => format!("{}n", rest),
+        _ => syllable,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> BTreeSet<&'static str> {
+        PINYIN_SYLLABLES.split_whitespace().collect()
+    }
+
+    fn score(query: &str, name: &str) -> f64 {
+        let table = table();
+        similarity(&NameTokens::new(query, &table), &NameTokens::new(name, &table))
+    }
+
+    #[test]
+    fn split_into_fewest_syllables() {
+        let table = table();
+        let cases = [
+            ("jiangdongzhizhi", Some(vec!["jiang", "dong", "zhi", "zhi"])),
+            ("shengzi", Some(vec!["sheng", "zi"])),
+            ("xian", Some(vec!["xian"])),
+            ("l", None),
+            ("skel01", None),
+        ];
+        for (word, expected) in cases {
+            let expected = expected.map(|split| split.into_iter().map(str::to_string).collect::<Vec<_>>());
+            assert_eq!(split_syllables(word, &table), expected, "{}", word);
+        }
+    }
+
+    #[test]
+    fn similarity_ignores_word_order() {
+        assert!(score("shengzi_jianjia", "jianjia_shengzi_l_01") >= MIN_SCORE);
+        assert!(score("jiangdong zhizhi", "jiangdongzhizhi") >= MIN_SCORE);
+        assert!(score("jianjia", "jianjia") > score("jianjia", "jiangjia"));
+        assert!(score("shengzi_jianjia", "biaoqing_xiao_01") < MIN_SCORE);
+    }
+
+    #[test]
+    fn similar_initials_and_finals_are_close() {
+        assert_eq!(syllable_similarity("zhi", "zi"), 0.9);
+        assert_eq!(syllable_similarity("jiang", "jian"), 0.9);
+        assert!(syllable_similarity("zhi", "ma") < 0.9);
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::{Result, Context};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use rayon::prelude::*;
+
+use crate::dictionary::Dictionary;
+use crate::imaging::{decode_rgba, downscale, encode_png};
+use crate::sidecar::original_names;
+use crate::progress::Reporter;
+use crate::workspace::{LoadFailure, Workspace};
+
+/// 画廊生成结果
+pub struct GallerySummary {
+    pub index_path: PathBuf,
+    pub images: usize,
+    pub groups: usize,
+    /// 无法生成缩略图的PNG
+    pub thumbnail_failures: Vec<LoadFailure>,
+}
+
+/// 生成离线HTML画廊：按尺寸分组展示PNG缩略图，并标注尺寸匹配的Atlas与词典中的显示名称
+pub fn build_gallery(workspace: &Workspace, output_dir: &Path, thumb_size: u32, dictionary: &Dictionary, reporter: &dyn Reporter) -> Result<GallerySummary> {
+    let thumbs_dir = output_dir.join("thumbs");
+    fs::create_dir_all(&thumbs_dir).context("无法创建画廊目录")?;
+    let output_dir = output_dir.canonicalize().context("无法定位画廊目录")?;
+
+    // 尺寸 -> 拥有该尺寸页面的 (Atlas文件名, 页面名)
+    let mut atlas_pages: BTreeMap<(u32, u32), Vec<(String, String)>> = BTreeMap::new();
+    for atlas in &workspace.atlases {
+        let atlas_name = dictionary.annotate(&atlas.path, file_name(&atlas.path));
+        for page in &atlas.pages {
+            atlas_pages.entry((page.width, page.height))
+                .or_default()
+                .push((atlas_name.clone(), page.name.clone()));
+        }
+    }
+
+    reporter.started("生成缩略图", workspace.pngs.len());
+
+    let thumbnails: Vec<Result<String, LoadFailure>> = workspace.pngs.par_iter()
+        .enumerate()
+        .map(|(i, png)| {
+            let thumb_name = format!("thumbs/{:05}.png", i);
+            let result = decode_rgba(&png.path)
+                .and_then(|image| encode_png(&downscale(&image, thumb_size), &output_dir.join(&thumb_name)));
+            match result {
+                Ok(()) => {
+                    reporter.processed(&png.path, None);
+                    Ok(thumb_name)
+                }
+                Err(e) => {
+                    let error = format!("{:#}", e);
+                    reporter.error(&png.path, &error);
+                    Err(LoadFailure { path: png.path.clone(), error })
+                }
+            }
+        })
+        .collect();
+
+    reporter.finished("生成缩略图", "缩略图生成完成");
+
+    // 按尺寸分组，面积大的（更可能是Atlas页面）排在前面
+    let mut groups: BTreeMap<(u32, u32), Vec<usize>> = BTreeMap::new();
+    for (i, png) in workspace.pngs.iter().enumerate() {
+        groups.entry((png.width, png.height)).or_default().push(i);
+    }
+    let mut group_keys: Vec<(u32, u32)> = groups.keys().copied().collect();
+    group_keys.sort_by_key(|&(w, h)| (std::cmp::Reverse(w as u64 * h as u64), w, h));
+
+    let originals = original_names(workspace.pngs.iter().map(|png| png.path.as_path()));
+
+    let mut html = String::new();
+    html.push_str(&html_head("PNG画廊"));
+    let _ = writeln!(html, "<h1>PNG画廊</h1>");
+    let _ = writeln!(html, "<p class=\"summary\">共 {} 张图片，{} 种尺寸</p>", workspace.pngs.len(), group_keys.len());
+
+    for key in &group_keys {
+        let (width, height) = *key;
+        let members = &groups[key];
+        let matches = atlas_pages.get(key).map(Vec::as_slice).unwrap_or_default();
+
+        let _ = writeln!(html, "<section>");
+        let _ = writeln!(html, "<h2>{}×{} <small>{} 张</small></h2>", width, height, members.len());
+        if matches.is_empty() {
+            let _ = writeln!(html, "<p class=\"atlas none\">无匹配的Atlas页面</p>");
+        } else {
+            let names: Vec<String> = matches.iter()
+                .map(|(atlas, page)| format!("{} <span>({})</span>", escape_html(atlas), escape_html(page)))
+                .collect();
+            let _ = writeln!(html, "<p class=\"atlas\">匹配Atlas: {}</p>", names.join("、"));
+        }
+
+        let _ = writeln!(html, "<div class=\"grid\">");
+        for &i in members {
+            let png = &workspace.pngs[i];
+            let href = relative_url(&output_dir, &png.path);
+            let name = dictionary.annotate(&png.path, file_name(&png.path));
+            let _ = writeln!(html, "<figure>");
+            match &thumbnails[i] {
+                Ok(thumb) => {
+                    let _ = writeln!(html, "<a href=\"{}\"><img src=\"{}\" loading=\"lazy\" alt=\"{}\"></a>",
+                        escape_html(&href), thumb, escape_html(&name));
+                }
+                Err(_) => {
+                    let _ = writeln!(html, "<a href=\"{}\" class=\"broken\">缩略图生成失败</a>", escape_html(&href));
+                }
+            }
+            // 改过名的文件显示游戏中的原始hash文件名，便于对照游戏资源
+            let original = originals.get(&png.path)
+                .map(|original| format!("<br><small>原始: {}</small>", escape_html(original)))
+                .unwrap_or_default();
+            let _ = writeln!(html, "<figcaption><b>{}×{}</b><br>{}{}<br><i>匹配 {} 个Atlas页面</i></figcaption>",
+                width, height, escape_html(&name), original, matches.len());
+            let _ = writeln!(html, "</figure>");
+        }
+        let _ = writeln!(html, "</div>");
+        let _ = writeln!(html, "</section>");
+    }
+
+    html.push_str("</body>\n</html>\n");
+
+    let index_path = output_dir.join("index.html");
+    fs::write(&index_path, html).context("无法写入画廊页面")?;
+
+    Ok(GallerySummary {
+        index_path,
+        images: workspace.pngs.len(),
+        groups: group_keys.len(),
+        thumbnail_failures: thumbnails.into_iter().filter_map(Result::err).collect(),
+    })
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("?")
+        .to_string()
+}
+
+/// 计算从`from_dir`指向`target`的相对URL（各段百分号编码）
+pub fn relative_url(from_dir: &Path, target: &Path) -> String {
+    let target = target.canonicalize().unwrap_or_else(|_| target.to_path_buf());
+    let from: Vec<Component> = from_dir.components().collect();
+    let to: Vec<Component> = target.components().collect();
+
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut segments: Vec<String> = vec!["..".to_string(); from.len() - common];
+    segments.extend(to[common..].iter().map(|c| encode_segment(&c.as_os_str().to_string_lossy())));
+    segments.join("/")
+}
+
+fn encode_segment(segment: &str) -> String {
+    let mut encoded = String::new();
+    for byte in segment.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            let _ = write!(encoded, "%{:02X}", byte);
+        }
+    }
+    encoded
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// 离线页面的头部与样式（画廊与相似图片聚类共用）
+pub fn html_head(title: &str) -> String {
+    format!("<!DOCTYPE html>\n<html lang=\"zh-CN\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n{}",
+        escape_html(title), HTML_STYLE)
+}
+
+const HTML_STYLE: &str = r#"<style>
+body { font-family: sans-serif; margin: 24px; background: #1e1f22; color: #ddd; }
+h1 { margin-top: 0; }
+h2 { border-bottom: 1px solid #444; padding-bottom: 4px; }
+h2 small { color: #999; font-weight: normal; }
+.summary { color: #999; }
+.atlas { color: #8fc98f; }
+.atlas span { color: #999; }
+.atlas.none { color: #c98f8f; }
+.grid { display: flex; flex-wrap: wrap; gap: 12px; }
+figure { margin: 0; width: 200px; background: #2b2d31; padding: 8px; border-radius: 4px; }
+figure img { display: block; max-width: 100%; max-height: 200px; margin: 0 auto;
+  background: repeating-conic-gradient(#555 0% 25%, #444 0% 50%) 50% / 16px 16px; }
+figcaption { font-size: 12px; margin-top: 6px; word-break: break-all; }
+figcaption i, figcaption small { color: #999; }
+a { color: #8ab4f8; }
+.broken { display: block; padding: 40px 0; text-align: center; }
+</style>
+</head>
+<body>
+"#;
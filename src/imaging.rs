@@ -0,0 +1,113 @@
+use anyhow::{Result, Context};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// 8位RGBA像素图
+#[derive(Debug, Clone)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// 解码PNG为RGBA8（调色板、灰度与16位图都会被转换）
+pub fn decode_rgba(file_path: &Path) -> Result<RgbaImage> {
+    let file = File::open(file_path).context("无法打开PNG文件")?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().context("无法读取PNG头部")?;
+
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).context("无法解码PNG图像数据")?;
+    let data = &buf[..info.buffer_size()];
+
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => data.to_vec(),
+        png::ColorType::Rgb => data.chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => data.chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => data.iter()
+            .flat_map(|&v| [v, v, v, 255])
+            .collect(),
+        png::ColorType::Indexed => anyhow::bail!("调色板图像未能展开"),
+    };
+
+    Ok(RgbaImage {
+        width: info.width,
+        height: info.height,
+        pixels,
+    })
+}
+
+/// 按面积平均缩小图像，使长边不超过`max_side`（不会放大）
+pub fn downscale(image: &RgbaImage, max_side: u32) -> RgbaImage {
+    let longest = image.width.max(image.height);
+    if longest <= max_side || longest == 0 {
+        return image.clone();
+    }
+
+    let width = ((image.width as u64 * max_side as u64 / longest as u64) as u32).max(1);
+    let height = ((image.height as u64 * max_side as u64 / longest as u64) as u32).max(1);
+    resample(image, width, height)
+}
+
+/// 按面积平均缩放到指定尺寸（不保持宽高比），用于缩小
+pub fn resample(image: &RgbaImage, width: u32, height: u32) -> RgbaImage {
+    let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+
+    for dy in 0..height {
+        let y0 = dy as u64 * image.height as u64 / height as u64;
+        let y1 = ((dy as u64 + 1) * image.height as u64 / height as u64).max(y0 + 1);
+        for dx in 0..width {
+            let x0 = dx as u64 * image.width as u64 / width as u64;
+            let x1 = ((dx as u64 + 1) * image.width as u64 / width as u64).max(x0 + 1);
+
+            // 以alpha加权平均颜色，避免透明像素把边缘染黑
+            let mut sum = [0u64; 4];
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let i = ((y * image.width as u64 + x) * 4) as usize;
+                    let alpha = image.pixels[i + 3] as u64;
+                    sum[0] += image.pixels[i] as u64 * alpha;
+                    sum[1] += image.pixels[i + 1] as u64 * alpha;
+                    sum[2] += image.pixels[i + 2] as u64 * alpha;
+                    sum[3] += alpha;
+                }
+            }
+
+            let count = (y1 - y0) * (x1 - x0);
+            match std::num::NonZeroU64::new(sum[3]) {
+                Some(weight) => {
+                    pixels.push((sum[0] / weight) as u8);
+                    pixels.push((sum[1] / weight) as u8);
+                    pixels.push((sum[2] / weight) as u8);
+                    pixels.push((sum[3] / count) as u8);
+                }
+                None => pixels.extend_from_slice(&[0, 0, 0, 0]),
+            }
+        }
+    }
+
+    RgbaImage { width, height, pixels }
+}
+
+/// 将RGBA图像写入PNG文件
+pub fn encode_png(image: &RgbaImage, file_path: &Path) -> Result<()> {
+    let file = File::create(file_path).context("无法创建PNG文件")?;
+    encode_png_to(image, BufWriter::new(file))
+}
+
+/// 将RGBA图像编码为PNG写入任意输出
+pub fn encode_png_to<W: Write>(image: &RgbaImage, writer: W) -> Result<()> {
+    let mut encoder = png::Encoder::new(writer, image.width, image.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().context("无法写入PNG头部")?;
+    writer.write_image_data(&image.pixels).context("无法写入PNG图像数据")?;
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+use serde::Serialize;
+
+use crate::compat::CompatibilitySummary;
+use crate::png_meta::PngSummary;
+
+/// 工作区各目录的文件统计
+#[derive(Debug, Serialize)]
+pub struct WorkspaceInfo {
+    pub work_dir: DirInfo,
+    pub atlas_dir: DirInfo,
+    pub skels_dir: DirInfo,
+    /// 工作目录中PNG的格式与透明情况
+    pub pngs: PngSummary,
+    /// Skel版本与Atlas格式的兼容性
+    pub compatibility: CompatibilitySummary,
+}
+
+/// 单个目录的文件统计
+#[derive(Debug, Serialize)]
+pub struct DirInfo {
+    pub path: PathBuf,
+    pub exists: bool,
+    pub files: usize,
+    pub bytes: u64,
+    /// 扩展名（小写，无扩展名记为`none`） -> 统计
+    pub types: BTreeMap<String, TypeStats>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct TypeStats {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// 递归统计目录中各类型文件的数量与字节数
+pub fn collect_dir_info(dir_path: &Path) -> DirInfo {
+    let mut info = DirInfo {
+        path: dir_path.to_path_buf(),
+        exists: dir_path.exists(),
+        files: 0,
+        bytes: 0,
+        types: BTreeMap::new(),
+    };
+
+    for entry in WalkDir::new(dir_path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        let kind = entry.path().extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase())
+            .unwrap_or_else(|| "none".to_string());
+
+        let stats = info.types.entry(kind).or_default();
+        stats.files += 1;
+        stats.bytes += size;
+        info.files += 1;
+        info.bytes += size;
+    }
+
+    info
+}
@@ -0,0 +1,79 @@
+//! 三幻Spine动态立绘还原的核心库
+//!
+//! 命令行工具只是其中一个前端：库中的函数只返回数据，不在终端输出结果，
+//! 可以在自己的工具中直接调用检测、解析、搜索与匹配。
+//!
+//! - 检测：[`detect_file_type`] 按文件头判断游戏中去掉了扩展名的文件类型
+//! - 解析：[`parse_atlas_file`]、[`parse_skel_file`]，或用 [`Workspace::load`] 一次读取整个工作区
+//! - 搜索：[`search_atlas_content`]、[`search_skel_content`]、[`fuzzy_search_names`]
+//! - 匹配：[`RegionIndex`] 判断Skel的附件能在哪些Atlas中找到，[`rank_skeletons`] 为Atlas排序候选Skel
+//! - 文件操作：先生成计划（[`plan_extension_restore`]、[`plan_organize`]、[`bucket::plan_bucket`]、
+//!   [`rename::plan_template_renames`]、[`verify::plan_quarantine`]），确认后用 [`execute_renames`] 执行；
+//!   执行时绝不覆盖已有文件，并将原始名称记入各目录的names.json
+//!
+//! 无法读取或解析的文件记在各结果的`failures`中（[`LoadFailure`]），不会中断整批处理。
+//! 耗时的操作接收一个 [`Reporter`] 报告进度：[`progress`] 中有进度条、静默、逐条日志与NDJSON事件流四种实现，
+//! 也可以自行实现该trait把进度接入图形界面。
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use unpack_sgzhxdl_cli::progress::QuietReporter;
+//! use unpack_sgzhxdl_cli::{execute_renames, parse_atlas_file, plan_extension_restore};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let plan = plan_extension_restore(Path::new("udp"), &QuietReporter);
+//! for rename in &plan.renames {
+//!     println!("{} -> {}", rename.from.display(), rename.to.display());
+//! }
+//! let outcome = execute_renames(plan, &QuietReporter);
+//! println!("恢复了 {} 个文件，{} 个失败", outcome.done.len(), outcome.failures.len());
+//!
+//! let atlas = parse_atlas_file(Path::new("atlas/hero.atlas"))?;
+//! println!("{} 个页面，{} 个区域", atlas.pages.len(), atlas.regions.len());
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod atlas;
+pub mod bucket;
+pub mod bundle;
+pub mod catalog;
+pub mod cluster;
+pub mod compat;
+pub mod convert;
+pub mod dictionary;
+pub mod file_operations;
+pub mod fuzzy;
+pub mod gallery;
+pub mod imaging;
+pub mod info;
+pub mod matching;
+pub mod names;
+pub mod paths;
+pub mod pattern;
+pub mod pma;
+pub mod png_meta;
+pub mod progress;
+pub mod rename;
+pub mod report;
+pub mod search;
+pub mod sidecar;
+pub mod skel;
+pub mod skel_data;
+pub mod snapshot;
+pub mod template;
+pub mod validate;
+pub mod verify;
+pub mod workspace;
+
+pub use atlas::{parse_atlas, parse_atlas_file, Atlas};
+pub use file_operations::{
+    detect_file_type, execute_renames, plan_extension_restore, plan_organize, RenameOutcome, RenamePlan,
+};
+pub use fuzzy::fuzzy_search_names;
+pub use matching::{rank_skeletons, RegionIndex};
+pub use progress::{Event, ProgressMode, Reporter};
+pub use search::{search_atlas_content, search_skel_content, SearchResults, SkelSearchOptions};
+pub use sidecar::Rename;
+pub use skel::{parse_skel, parse_skel_file, Skeleton};
+pub use workspace::{LoadFailure, Workspace};
@@ -1,39 +1,413 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use anyhow::Result;
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::fs;
-use dialoguer::{Select, Input, Confirm};
+use dialoguer::{Select, MultiSelect, Input, Confirm};
 use console::Style;
-use walkdir::WalkDir;
-
-mod file_operations;
-mod search;
-
+use indicatif::HumanBytes;
+
+mod output;
+mod server;
+
+use unpack_sgzhxdl_cli::{
+    atlas, bucket, catalog, cluster, compat, convert, dictionary, file_operations, fuzzy, gallery, info, names,
+    pattern, pma, png_meta, progress, rename, report, search, sidecar, skel, skel_data, snapshot, template, validate, verify,
+    workspace,
+};
+
+use atlas::{parse_atlas_file, AtlasFormat};
+use bucket::{plan_bucket, SizeClass};
+use catalog::{entry_from_dir, Catalog, CatalogEntry};
+use cluster::{cluster_pngs, write_cluster_folders, write_cluster_html, ClusterLayout, ClusterOptions};
+use compat::{summarize_compatibility, CompatibilitySummary};
+use convert::{convert_skeleton, write_converted};
+use dictionary::{resolve_key, Dictionary, DictionaryEntry};
 use file_operations::*;
+use fuzzy::{fuzzy_search_names, FuzzyMatch};
+use gallery::build_gallery;
+use info::{collect_dir_info, DirInfo, WorkspaceInfo};
+use names::NameKind;
+use output::{highlight, print_csv, print_json, print_table, OutputFormat};
+use pattern::{PatternMode, SearchScope};
+use pma::{check_atlas_pma, convert_pages, PageStatus};
+use png_meta::{summarize_pngs, AlphaUsage, PngSummary};
+use progress::{ProgressMode, Reporter};
+use rename::{available_placeholders, default_template, parse_template, plan_template_renames, DEFAULT_PNG_TEMPLATE};
+use report::{build_report, Report};
 use search::*;
+use sidecar::{whence, Whence};
+use snapshot::{diff_snapshots, AssetKind, Snapshot, SnapshotDiff, SnapshotFile};
+use template::NameTemplate;
+use validate::{validate_folder, ValidationReport};
+use verify::{plan_quarantine, verify_pngs, VerifySummary};
+use workspace::{LoadFailure, Workspace};
 
 #[derive(Parser)]
 #[command(name = "三幻Spine动态立绘还原工具")]
 #[command(about = "一个用于三幻Spine动态立绘还原的CLI工具")]
 struct Cli {
     /// 工作目录路径
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     work_dir: Option<PathBuf>,
+
+    /// 进度显示方式：bar为进度条，quiet不显示，verbose逐条记录，json每行输出一个JSON事件
+    #[arg(long, value_enum, global = true, default_value_t = ProgressMode::Bar)]
+    progress: ProgressMode,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 交叉比对PNG、Atlas与Skel，列出尚未还原的资源
+    Report {
+        /// 解码未匹配PNG的像素，检查是否真的含透明像素（较慢）
+        #[arg(long)]
+        alpha: bool,
+        /// 输出格式
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// 按尺寸等文本搜索Atlas内容（例如：2017,1937）
+    SearchAtlas {
+        /// 搜索内容
+        query: String,
+        /// 匹配方式：literal为全文子串，regex/glob只匹配页面名与区域名
+        #[arg(long, value_enum, default_value_t = PatternMode::Literal)]
+        mode: PatternMode,
+        /// 只匹配指定类别的名称（page、region），可用逗号分隔多个
+        #[arg(long = "kind", value_enum, value_delimiter = ',')]
+        kinds: Vec<NameKind>,
+        /// 输出格式
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// 按命中关键词的多少对Skel排序搜索
+    SearchSkel {
+        /// 搜索关键词（可多个）
+        #[arg(required = true)]
+        terms: Vec<String>,
+        /// 按关键词在全部Skel中的稀有程度加权计分
+        #[arg(long)]
+        weighted: bool,
+        /// 最低匹配得分（0~1），1表示必须命中全部关键词
+        #[arg(long, default_value_t = 0.0)]
+        min_score: f64,
+        /// 匹配方式：literal为原始内容子串，regex/glob只匹配解析出的名称
+        #[arg(long, value_enum, default_value_t = PatternMode::Literal)]
+        mode: PatternMode,
+        /// 只匹配指定类别的名称（bone、slot、attachment、animation等），可用逗号分隔多个
+        #[arg(long = "kind", value_enum, value_delimiter = ',')]
+        kinds: Vec<NameKind>,
+        /// 输出格式
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// 模糊搜索Atlas与Skel中的名称（按编辑距离与拼音音节匹配，不计词序）
+    Fuzzy {
+        /// 记得不太准的名称，例如 shengzi_jianjia
+        query: String,
+        /// 只搜索指定类别的名称，可用逗号分隔多个
+        #[arg(long = "kind", value_enum, value_delimiter = ',')]
+        kinds: Vec<NameKind>,
+        /// 最多列出的名称数量
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
+        /// 输出格式
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// 显示工作目录信息与各类型文件统计
+    Info {
+        /// 解码像素检查PNG是否真的含透明像素（较慢）
+        #[arg(long)]
+        alpha: bool,
+        /// 输出格式
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// 生成按尺寸分组的PNG缩略图画廊（离线HTML）
+    Gallery {
+        /// 输出目录，默认为工作目录同级的gallery文件夹
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// 缩略图长边像素
+        #[arg(long, default_value_t = 256)]
+        thumb_size: u32,
+    },
+    /// 启动本地浏览器界面（仅监听127.0.0.1）
+    Serve {
+        /// 监听端口
+        #[arg(short, long, default_value_t = 8765)]
+        port: u16,
+    },
+    /// 按模板重命名工作目录中的PNG（默认按尺寸命名为 size_宽x高.png），或归类后的Atlas、Skel（默认按内容命名）
+    Rename {
+        /// 要重命名的资源类型
+        #[arg(long = "type", value_enum, default_value_t = AssetKind::Png)]
+        kind: AssetKind,
+        /// 文件名模板（不含扩展名），PNG默认 size_{w}x{h}，Atlas与Skel默认按内容命名。通用占位符：{orig} {parent} {type} {hash8}；
+        /// PNG：{w} {h} {atlas_page} {color} {depth} {format} {interlace} {trns} {alpha}；
+        /// Atlas：{w} {h}（第一个页面）{atlas_page} {pages} {regions} {main_page} {prefix}；
+        /// Skel：{version} {bones} {slots} {skins} {animations} {atlas_page} {skin} {anims}
+        #[arg(long)]
+        template: Option<String>,
+    },
+    /// 将工作目录中的PNG按尺寸类别移入子文件夹：icons（长边<256）、pages（与Atlas页面同尺寸或宽高为2的幂）、portraits（长边≥1024）、ui（其余）
+    Bucket {
+        /// 在类别文件夹中再按具体尺寸分文件夹，例如 pages/2048x2048
+        #[arg(long)]
+        exact_size: bool,
+    },
+    /// 计算PNG的感知哈希（aHash/dHash）与颜色直方图，将外观相似的图片聚类（仅使用CPU）
+    Cluster {
+        /// 输出目录，默认为工作目录同级的clusters文件夹
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// 输出方式
+        #[arg(long, value_enum, default_value_t = ClusterLayout::Html)]
+        layout: ClusterLayout,
+        /// aHash与dHash汉明距离之和的上限（0~128），越小越严格
+        #[arg(long, default_value_t = 20)]
+        max_distance: u32,
+        /// 颜色直方图相似度的下限（0~1），越大越严格
+        #[arg(long, default_value_t = 0.6)]
+        min_color: f32,
+        /// HTML缩略图长边像素
+        #[arg(long, default_value_t = 160)]
+        thumb_size: u32,
+    },
+    /// 查询文件的原始名称与来源（恢复扩展名、归类、重命名与隔离时记录在各目录的names.json中）
+    Whence {
+        /// 改名后的文件；不存在时视为游戏中的原始文件名，查找它现在的位置
+        file: PathBuf,
+        /// 输出格式
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// 完整校验工作目录中的PNG（chunk、CRC、IDAT数据流与IEND），找出传输中被截断或损坏的文件
+    Verify {
+        /// 将损坏的PNG移入工作目录同级的quarantine文件夹
+        #[arg(long)]
+        quarantine: bool,
+        /// 输出格式
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// 保存当前工作区（PNG、Atlas、Skel）的内容hash快照，供游戏更新后比较
+    Snapshot {
+        /// 快照文件路径
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// 比较两个快照（快照文件或目录），列出新增、删除与修改的资源
+    Diff {
+        /// 旧快照文件或目录
+        old: PathBuf,
+        /// 新快照文件或目录，默认为当前工作区
+        new: Option<PathBuf>,
+        /// 输出格式
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// 将二进制Skel转换为其他Spine主版本的JSON或二进制格式，并列出无法原样转换的内容
+    Convert {
+        /// 要转换的.skel文件
+        skel: PathBuf,
+        /// 目标Spine版本
+        #[arg(long, value_enum)]
+        to: skel::Format,
+        /// 写出二进制.skel（默认为JSON）
+        #[arg(long)]
+        binary: bool,
+        /// 输出文件，默认为输出目录下的 `<文件名>_<版本>.json`
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// 有无法原样转换的内容时不写出文件
+        #[arg(long)]
+        strict: bool,
+        /// 覆盖已存在的输出文件（不能覆盖源文件）
+        #[arg(long)]
+        force: bool,
+    },
+    /// 校验角色文件夹能否被查看器加载：页面PNG、区域范围、附件区域与版本兼容性
+    Validate {
+        /// 角色文件夹（含一个.atlas、一个.skel与页面PNG）
+        dir: PathBuf,
+        /// 输出格式
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// 检查或转换角色文件夹中页面PNG的预乘Alpha
+    Pma {
+        #[command(subcommand)]
+        action: PmaAction,
+    },
+    /// 管理已还原角色目录
+    Catalog {
+        #[command(subcommand)]
+        action: CatalogAction,
+    },
+    /// 管理中文显示名称词典（资源hash或Skel指纹 -> 角色/皮肤名称）
+    Dict {
+        #[command(subcommand)]
+        action: DictAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum PmaAction {
+    /// 根据像素估计各页面是否预乘，并与Atlas的pma标记比较
+    Check {
+        /// 角色文件夹（含一个.atlas及页面PNG）
+        dir: PathBuf,
+        /// 输出格式
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// 将页面PNG预乘Alpha，并在4.x Atlas中标记 pma:true
+    Premultiply {
+        /// 角色文件夹（含一个.atlas及页面PNG）
+        dir: PathBuf,
+        /// 只处理指定页面（可重复），默认为全部页面
+        #[arg(long)]
+        page: Vec<String>,
+        /// 像素看起来已是预乘或无法判断时仍然处理
+        #[arg(long)]
+        force: bool,
+    },
+    /// 取消页面PNG的预乘Alpha，并在4.x Atlas中标记 pma:false
+    Unpremultiply {
+        /// 角色文件夹（含一个.atlas及页面PNG）
+        dir: PathBuf,
+        /// 只处理指定页面（可重复），默认为全部页面
+        #[arg(long)]
+        page: Vec<String>,
+        /// 像素看起来已是非预乘或无法判断时仍然处理
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum CatalogAction {
+    /// 将已还原的角色文件夹记入目录（文件夹中需有一个.atlas、一个.skel及各页面PNG）
+    Add {
+        /// 角色文件夹
+        dir: PathBuf,
+        /// 角色名，默认为文件夹名
+        #[arg(long)]
+        name: Option<String>,
+        /// 皮肤名
+        #[arg(long)]
+        skin: Option<String>,
+    },
+    /// 列出全部已还原角色
+    List {
+        /// 输出格式
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// 显示角色的详细记录
+    Show {
+        /// 目录编号
+        id: u32,
+        /// 输出格式
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// 按角色名、皮肤、文件名或hash搜索
+    Search {
+        text: String,
+        /// 输出格式
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// 从目录中删除角色（不删除文件）
+    Remove {
+        /// 目录编号
+        id: u32,
+    },
+}
+
+#[derive(Subcommand)]
+enum DictAction {
+    /// 设置显示名称，例如：dict set ../atlas/6c3caaaaad29cff6e2f06e92950ee759.atlas SP孙策
+    Set {
+        /// 文件路径或hash
+        target: String,
+        /// 显示名称
+        name: String,
+        /// 使用Skel文件头中的指纹作为键（文件改名后仍能识别）
+        #[arg(long)]
+        fingerprint: bool,
+    },
+    /// 删除显示名称
+    Remove {
+        /// 文件路径或hash
+        target: String,
+        /// 使用Skel文件头中的指纹作为键
+        #[arg(long)]
+        fingerprint: bool,
+    },
+    /// 列出全部显示名称及对应的文件
+    List {
+        /// 输出格式
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// 按中文名称搜索，列出对应的文件
+    Search {
+        /// 名称中包含的文字，例如：孙策
+        text: String,
+        /// 输出格式
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// 合并队友的词典文件
+    Merge {
+        /// 要合并的词典文件
+        file: PathBuf,
+        /// 名称冲突时采用对方的名称（默认保留自己的）
+        #[arg(long)]
+        theirs: bool,
+    },
 }
 
 struct AppState {
     work_dir: PathBuf,
     atlas_dir: PathBuf,
     skels_dir: PathBuf,
+    gallery_dir: PathBuf,
+    /// 相似图片聚类的输出目录
+    clusters_dir: PathBuf,
+    output_dir: PathBuf,
+    /// 损坏PNG的隔离目录
+    quarantine_dir: PathBuf,
+    /// 中文显示名称词典
+    dictionary_path: PathBuf,
+    /// 已还原角色目录
+    catalog_path: PathBuf,
+    /// 接收各项操作的进度事件
+    reporter: Box<dyn Reporter + Send>,
 }
 
 impl AppState {
-    fn new(work_dir: PathBuf) -> Self {
+    fn new(work_dir: PathBuf, progress: ProgressMode) -> Self {
         let parent_dir = work_dir.parent().unwrap_or(&work_dir).to_path_buf();
         Self {
             work_dir: work_dir.clone(),
             atlas_dir: parent_dir.join("atlas"),
             skels_dir: parent_dir.join("skels"),
+            gallery_dir: parent_dir.join("gallery"),
+            clusters_dir: parent_dir.join("clusters"),
+            output_dir: parent_dir.join("output"),
+            quarantine_dir: parent_dir.join("quarantine"),
+            dictionary_path: parent_dir.join("display_names.json"),
+            catalog_path: parent_dir.join("catalog.json"),
+            reporter: progress.reporter(),
         }
     }
 }
@@ -43,17 +417,23 @@ fn main() -> Result<()> {
 
     let work_dir = if let Some(dir) = cli.work_dir {
         dir
+    } else if cli.command.is_some() {
+        anyhow::bail!("请通过 --work-dir 指定工作目录");
     } else {
         select_work_directory()?
     };
 
-    let state = AppState::new(work_dir);
+    let state = AppState::new(work_dir, cli.progress);
     let green = Style::new().green();
 
     if !state.work_dir.exists() {
         anyhow::bail!("工作目录不存在: {:?}", state.work_dir);
     }
 
+    if let Some(command) = cli.command {
+        return run_command(&state, command);
+    }
+
     println!("✅ 工作目录设置为: {:?}", green.apply_to(state.work_dir.display()));
 
     loop {
@@ -61,6 +441,44 @@ fn main() -> Result<()> {
     }
 }
 
+fn run_command(state: &AppState, command: Command) -> Result<()> {
+    match command {
+        Command::Report { alpha, format } => run_report(state, alpha, format),
+        Command::SearchAtlas { query, mode, kinds, format } => {
+            run_search_atlas(state, &query, &SearchScope { mode, kinds }, format)
+        }
+        Command::SearchSkel { terms, weighted, min_score, mode, kinds, format } => {
+            let terms: Vec<&str> = terms.iter().map(String::as_str).collect();
+            let options = SkelSearchOptions { weighted, min_score, scope: SearchScope { mode, kinds } };
+            run_search_skel(state, &terms, &options, format)
+        }
+        Command::Fuzzy { query, kinds, limit, format } => run_fuzzy(state, &query, &kinds, limit, format),
+        Command::Info { alpha, format } => show_work_dir_info(state, alpha, format),
+        Command::Gallery { output, thumb_size } => {
+            let output = output.unwrap_or_else(|| state.gallery_dir.clone());
+            run_gallery(state, &output, thumb_size)
+        }
+        Command::Serve { port } => run_serve(state, port),
+        Command::Rename { kind, template } => run_rename(state, kind, template.as_deref()),
+        Command::Bucket { exact_size } => run_bucket(state, exact_size),
+        Command::Cluster { output, layout, max_distance, min_color, thumb_size } => {
+            let output = output.unwrap_or_else(|| state.clusters_dir.clone());
+            run_cluster(state, &output, layout, ClusterOptions { max_distance, min_color }, thumb_size)
+        }
+        Command::Whence { file, format } => run_whence(state, &file, format),
+        Command::Verify { quarantine, format } => run_verify(state, quarantine, format),
+        Command::Snapshot { output } => run_snapshot(state, &output),
+        Command::Diff { old, new, format } => run_diff(state, &old, new.as_deref(), format),
+        Command::Convert { skel, to, binary, output, strict, force } => {
+            run_convert(state, &skel, to, binary, output, strict, force)
+        }
+        Command::Validate { dir, format } => run_validate(&dir, format),
+        Command::Pma { action } => run_pma(action),
+        Command::Catalog { action } => run_catalog(state, action),
+        Command::Dict { action } => run_dict(state, action),
+    }
+}
+
 fn select_work_directory() -> Result<PathBuf> {
     let cyan = Style::new().cyan();
     let blue = Style::new().blue();
@@ -139,9 +557,14 @@ fn show_main_menu(state: &AppState) -> Result<()> {
     let items = vec![
         "恢复文件扩展名",
         "归类文件 (.atlas 和 .skel)",
-        "重命名PNG文件（按尺寸）",
+        "重命名PNG文件（按尺寸或模板）",
+        "校验PNG完整性",
         "搜索Atlas内容",
         "搜索Skel内容",
+        "模糊搜索名称",
+        "生成还原进度报告",
+        "生成PNG缩略图画廊",
+        "启动本地浏览器界面",
         "显示当前工作目录信息",
         "退出"
     ];
@@ -155,10 +578,15 @@ fn show_main_menu(state: &AppState) -> Result<()> {
         0 => handle_restore_extensions(state),
         1 => handle_organize_files(state),
         2 => handle_rename_png_files(state),
-        3 => handle_search_atlas(state),
-        4 => handle_search_skel(state),
-        5 => show_work_dir_info(state),
-        6 => {
+        3 => handle_verify_pngs(state),
+        4 => handle_search_atlas(state),
+        5 => handle_search_skel(state),
+        6 => handle_fuzzy(state),
+        7 => run_report(state, false, OutputFormat::Text),
+        8 => run_gallery(state, &state.gallery_dir, 256),
+        9 => run_serve(state, 8765),
+        10 => show_work_dir_info(state, false, OutputFormat::Text),
+        11 => {
             println!("{}", green.apply_to("感谢使用！"));
             std::process::exit(0);
         }
@@ -168,7 +596,45 @@ fn show_main_menu(state: &AppState) -> Result<()> {
     Ok(())
 }
 
-fn show_work_dir_info(state: &AppState) -> Result<()> {
+fn show_work_dir_info(state: &AppState, check_alpha: bool, format: OutputFormat) -> Result<()> {
+    let info = WorkspaceInfo {
+        work_dir: collect_dir_info(&state.work_dir),
+        atlas_dir: collect_dir_info(&state.atlas_dir),
+        skels_dir: collect_dir_info(&state.skels_dir),
+        pngs: summarize_pngs(&state.work_dir, check_alpha, &*state.reporter),
+        compatibility: summarize_compatibility(&state.atlas_dir, &state.skels_dir),
+    };
+    print_failures(&info.compatibility.failures, "解析");
+
+    match format {
+        OutputFormat::Json => return print_json(&info),
+        OutputFormat::Csv => {
+            let mut rows = Vec::new();
+            for (label, dir) in [("work_dir", &info.work_dir), ("atlas_dir", &info.atlas_dir), ("skels_dir", &info.skels_dir)] {
+                for (kind, stats) in &dir.types {
+                    rows.push(vec![
+                        label.to_string(),
+                        dir.path.display().to_string(),
+                        kind.clone(),
+                        stats.files.to_string(),
+                        stats.bytes.to_string(),
+                    ]);
+                }
+            }
+            // PNG格式行的bytes列为空
+            let work_dir = info.work_dir.path.display().to_string();
+            for (format, count) in &info.pngs.formats {
+                rows.push(vec!["png_format".to_string(), work_dir.clone(), format.clone(), count.to_string(), String::new()]);
+            }
+            for (kind, count) in [("opaque", info.pngs.opaque), ("alpha", info.pngs.with_alpha), ("unchecked", info.pngs.unchecked), ("interlaced", info.pngs.interlaced), ("trns", info.pngs.with_trns)] {
+                rows.push(vec!["png_alpha".to_string(), work_dir.clone(), kind.to_string(), count.to_string(), String::new()]);
+            }
+            print_csv(&["dir", "path", "type", "files", "bytes"], &rows);
+            return Ok(());
+        }
+        OutputFormat::Text => {}
+    }
+
     let green = Style::new().green();
     let yellow = Style::new().yellow();
 
@@ -178,39 +644,85 @@ fn show_work_dir_info(state: &AppState) -> Result<()> {
     println!("{}: {}", yellow.apply_to("Atlas目录"), state.atlas_dir.display());
     println!("{}: {}", yellow.apply_to("Skels目录"), state.skels_dir.display());
 
-    // 统计工作目录文件数量
-    if state.work_dir.exists() {
-        let file_count = WalkDir::new(&state.work_dir)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
-            .count();
+    print_dir_info("工作目录文件数量", &info.work_dir);
+    print_dir_info("Atlas文件数量", &info.atlas_dir);
+    print_dir_info("Skels文件数量", &info.skels_dir);
+    print_png_summary(&info.pngs);
+    print_compatibility(&info.compatibility);
 
-        println!("{}: {}", yellow.apply_to("工作目录文件数量"), file_count);
-    }
+    println!();
+    Ok(())
+}
 
-    if state.atlas_dir.exists() {
-        let atlas_count = WalkDir::new(&state.atlas_dir)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
-            .count();
+fn print_png_summary(summary: &PngSummary) {
+    let green = Style::new().green();
+    let yellow = Style::new().yellow();
 
-        println!("{}: {}", yellow.apply_to("Atlas文件数量"), atlas_count);
+    if summary.total == 0 {
+        return;
     }
 
-    if state.skels_dir.exists() {
-        let skels_count = WalkDir::new(&state.skels_dir)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
-            .count();
+    println!();
+    println!("{}", green.apply_to("=== PNG格式 ==="));
+    for (format, count) in &summary.formats {
+        println!("  {:<10} {:>8}", format, count);
+    }
+    println!("  有透明像素 {}，完全不透明 {}，含tRNS {}，隔行扫描 {}",
+        summary.with_alpha, summary.opaque, summary.with_trns, summary.interlaced);
+    if summary.unchecked > 0 {
+        println!("  可能透明（未检查像素） {}", summary.unchecked);
+        println!("💡 使用 info --alpha 解码像素，区分真正含透明像素的PNG（较慢）");
+    }
+    if summary.opaque > 0 {
+        println!("💡 完全不透明的PNG多为背景图，Spine的Atlas页面通常带透明像素");
+    }
+    if !summary.unreadable.is_empty() {
+        println!("{}", yellow.apply_to(format!("⚠️  {} 个PNG无法读取，可使用 verify 命令检查", summary.unreadable.len())));
+    }
+}
+
+fn print_compatibility(summary: &CompatibilitySummary) {
+    let green = Style::new().green();
+    let yellow = Style::new().yellow();
 
-        println!("{}: {}", yellow.apply_to("Skels文件数量"), skels_count);
+    if summary.skel_versions.is_empty() && summary.atlas_formats.is_empty() {
+        return;
     }
 
     println!();
-    Ok(())
+    println!("{}", green.apply_to("=== 版本兼容性 ==="));
+    for (version, count) in &summary.skel_versions {
+        println!("  Skel {:<10} {:>8}", version, count);
+    }
+    for (format, count) in &summary.atlas_formats {
+        println!("  Atlas {:<9} {:>8}", format.label(), count);
+    }
+
+    let mismatched: Vec<_> = summary.mismatched().collect();
+    if mismatched.is_empty() {
+        println!("{}", green.apply_to(format!("✅ {} 对Skel与Atlas的主版本一致", summary.pairs.len())));
+        return;
+    }
+    println!("{}", yellow.apply_to(format!("⚠️  {} 对Skel与Atlas中有 {} 对主版本不一致:", summary.pairs.len(), mismatched.len())));
+    for pair in mismatched {
+        println!("  {} ↔ {}", pair.skel.display(), pair.atlas.display());
+        for warning in &pair.compatibility.warnings {
+            println!("    {}", warning);
+        }
+    }
+}
+
+fn print_dir_info(label: &str, dir: &DirInfo) {
+    let yellow = Style::new().yellow();
+
+    if !dir.exists {
+        return;
+    }
+
+    println!("{}: {} ({})", yellow.apply_to(label), dir.files, HumanBytes(dir.bytes));
+    for (kind, stats) in &dir.types {
+        println!("  {:<8} {:>8}  {}", kind, stats.files, HumanBytes(stats.bytes));
+    }
 }
 
 fn handle_restore_extensions(state: &AppState) -> Result<()> {
@@ -232,8 +744,9 @@ fn handle_restore_extensions(state: &AppState) -> Result<()> {
         return Ok(());
     }
 
-    restore_file_extensions(&state.work_dir)?;
-    println!("{}", green.apply_to("✅ 文件扩展名恢复完成！"));
+    let outcome = execute_renames(plan_extension_restore(&state.work_dir, &*state.reporter), &*state.reporter);
+    println!("{}", green.apply_to(format!("✅ 文件扩展名恢复完成！共 {} 个文件", outcome.done.len())));
+    print_failures(&outcome.failures, "恢复扩展名");
 
     Ok(())
 }
@@ -261,33 +774,95 @@ fn handle_organize_files(state: &AppState) -> Result<()> {
     if !state.atlas_dir.exists() {
         fs::create_dir_all(&state.atlas_dir)?;
     }
-    organize_files_by_extension(&state.work_dir, ".atlas", &state.atlas_dir)?;
+    organize_files(&state.work_dir, "atlas", &state.atlas_dir, &*state.reporter)?;
 
     // 移动skel文件
     if !state.skels_dir.exists() {
         fs::create_dir_all(&state.skels_dir)?;
     }
-    organize_files_by_extension(&state.work_dir, ".skel", &state.skels_dir)?;
+    organize_files(&state.work_dir, "skel", &state.skels_dir, &*state.reporter)?;
 
     println!("{}", green.apply_to("✅ 文件归类完成！"));
     println!("📁 Atlas文件已移动到: {}", state.atlas_dir.display());
     println!("📁 Skel文件已移动到: {}", state.skels_dir.display());
 
+    let bucket = Confirm::new()
+        .with_prompt("是否将PNG按尺寸类别（图标、界面、Atlas页面、大图立绘）移入子文件夹？")
+        .default(false)
+        .interact()?;
+    if bucket {
+        let exact_size = Confirm::new()
+            .with_prompt("是否在类别文件夹中再按具体尺寸分文件夹？")
+            .default(false)
+            .interact()?;
+        run_bucket(state, exact_size)?;
+    }
+
     Ok(())
 }
 
+fn organize_files(source_dir: &Path, extension: &str, target_dir: &Path, reporter: &dyn Reporter) -> Result<()> {
+    let plan = plan_organize(source_dir, extension, target_dir);
+    if plan.renames.is_empty() {
+        println!("📁 未找到扩展名为 .{} 的文件", extension);
+        return Ok(());
+    }
+    let outcome = execute_renames(plan, reporter);
+    print_failures(&outcome.failures, "移动");
+    Ok(())
+}
+
+fn run_bucket(state: &AppState, exact_size: bool) -> Result<()> {
+    let plan = plan_bucket(&state.work_dir, &state.atlas_dir, exact_size, &*state.reporter);
+    let outcome = execute_renames(plan.moves, &*state.reporter);
+    print_bucket_summary(&plan.classes, &outcome);
+    Ok(())
+}
+
+fn print_bucket_summary(classes: &BTreeMap<SizeClass, usize>, outcome: &RenameOutcome) {
+    let green = Style::new().green();
+    let yellow = Style::new().yellow();
+
+    println!("{}", green.apply_to(format!("✅ PNG分类完成，移动了 {} 个文件", outcome.done.len())));
+    for (class, count) in classes {
+        println!("  {}/ {} {}", class.folder(), class.label(), count);
+    }
+    if !outcome.failures.is_empty() {
+        println!("{}", yellow.apply_to(format!("⚠️  {} 个PNG无法读取或移动，未分类，可使用 verify 命令检查", outcome.failures.len())));
+    }
+}
+
+/// 汇总列出无法处理的文件；进度方式只影响进度显示，失败总会输出到标准错误
+fn print_failures(failures: &[LoadFailure], action: &str) {
+    if failures.is_empty() {
+        return;
+    }
+    let yellow = Style::new().yellow();
+    eprintln!("{}", yellow.apply_to(format!("⚠️  {} 个文件{}时出错:", failures.len(), action)));
+    for failure in failures {
+        eprintln!("  {}: {}", failure.path.display(), failure.error);
+    }
+}
+
 fn handle_rename_png_files(state: &AppState) -> Result<()> {
     let yellow = Style::new().yellow();
-    let green = Style::new().green();
 
     if !state.work_dir.exists() {
         anyhow::bail!("工作目录不存在: {:?}", state.work_dir);
     }
 
     println!("{}", yellow.apply_to("🖼️  正在重命名PNG文件..."));
+    let placeholders: Vec<String> = available_placeholders(AssetKind::Png).iter().map(|name| format!("{{{}}}", name)).collect();
+    println!("可用占位符: {}", placeholders.join(" "));
+
+    let template: String = Input::new()
+        .with_prompt("文件名模板（不含扩展名）")
+        .default(DEFAULT_PNG_TEMPLATE.to_string())
+        .interact_text()?;
+    let template = parse_template(AssetKind::Png, &template)?;
 
     let proceed = Confirm::new()
-        .with_prompt("确定要按照尺寸重命名工作目录中的所有PNG文件吗？")
+        .with_prompt("确定要按照模板重命名工作目录中的所有PNG文件吗？")
         .default(true)
         .interact()?;
 
@@ -295,21 +870,147 @@ fn handle_rename_png_files(state: &AppState) -> Result<()> {
         return Ok(());
     }
 
-    rename_png_by_size(&state.work_dir)?;
-    println!("{}", green.apply_to("✅ PNG文件重命名完成！"));
+    // 传输中被截断的PNG头部仍然完好，会被当作正常文件按尺寸命名
+    let verify = Confirm::new()
+        .with_prompt("重命名前先完整校验PNG，并将损坏的文件移入quarantine文件夹？")
+        .default(true)
+        .interact()?;
+    if verify {
+        run_verify(state, true, OutputFormat::Text)?;
+    }
+
+    rename_with_template(state, AssetKind::Png, &template)
+}
+
+fn run_rename(state: &AppState, kind: AssetKind, template: Option<&str>) -> Result<()> {
+    let template = template.unwrap_or(default_template(kind));
+    rename_with_template(state, kind, &parse_template(kind, template)?)
+}
 
+fn rename_with_template(state: &AppState, kind: AssetKind, template: &NameTemplate) -> Result<()> {
+    let green = Style::new().green();
+    let dir = match kind {
+        AssetKind::Png => &state.work_dir,
+        AssetKind::Atlas => &state.atlas_dir,
+        AssetKind::Skel => &state.skels_dir,
+    };
+    let outcome = execute_renames(plan_template_renames(dir, kind, template, &state.atlas_dir, &*state.reporter), &*state.reporter);
+    println!("{}", green.apply_to(format!("✅ {}文件重命名完成！共 {} 个文件", kind.label(), outcome.done.len())));
+    print_failures(&outcome.failures, "重命名");
     Ok(())
 }
 
-fn handle_search_atlas(state: &AppState) -> Result<()> {
+fn run_whence(state: &AppState, file: &Path, format: OutputFormat) -> Result<()> {
+    let roots = [state.work_dir.as_path(), &state.atlas_dir, &state.skels_dir, &state.quarantine_dir];
+    let results = whence(file, &roots)?;
+    if results.is_empty() {
+        anyhow::bail!("没有找到 {:?} 的改名记录（names.json）", file);
+    }
+
+    match format {
+        OutputFormat::Text => results.iter().for_each(print_whence),
+        OutputFormat::Json => print_json(&results)?,
+        OutputFormat::Csv => {
+            let rows: Vec<Vec<String>> = results.iter()
+                .map(|result| vec![
+                    result.path.display().to_string(),
+                    result.record.original.clone(),
+                    result.record.hash.clone(),
+                    result.record.source.display().to_string(),
+                    result.record.renamed_at.clone(),
+                    result.modified.map(|modified| modified.to_string()).unwrap_or_default(),
+                    format!("{:?}", result.source_status).to_lowercase(),
+                ])
+                .collect();
+            print_csv(&["path", "original", "hash", "source", "renamed_at", "modified", "source_status"], &rows);
+        }
+    }
+    Ok(())
+}
+
+fn print_whence(result: &Whence) {
     let yellow = Style::new().yellow();
+    let red = Style::new().red();
 
-    if !state.atlas_dir.exists() {
-        anyhow::bail!("Atlas目录不存在: {:?}", state.atlas_dir);
+    println!();
+    println!("📄 {}", result.path.display());
+    println!("  {}: {}", yellow.apply_to("原始文件名"), result.record.original);
+    println!("  {}: {}", yellow.apply_to("来源目录"), result.record.source.display());
+    println!("  {}: {}", yellow.apply_to("内容MD5"), result.record.hash);
+    println!("  {}: {}", yellow.apply_to("改名日期"), result.record.renamed_at);
+    match result.modified {
+        Some(true) => println!("  {}", red.apply_to("⚠️  改名后内容已被修改")),
+        Some(false) => {}
+        None => println!("  {}", red.apply_to("⚠️  文件已不存在")),
+    }
+    println!("  {}: {}", yellow.apply_to("原文件"), result.source_status.label());
+}
+
+fn handle_verify_pngs(state: &AppState) -> Result<()> {
+    let quarantine = Confirm::new()
+        .with_prompt(format!("将损坏的PNG移入 {} ？", state.quarantine_dir.display()))
+        .default(false)
+        .interact()?;
+    run_verify(state, quarantine, OutputFormat::Text)
+}
+
+fn run_verify(state: &AppState, quarantine: bool, format: OutputFormat) -> Result<()> {
+    let mut summary = verify_pngs(&state.work_dir, &*state.reporter);
+    if quarantine && !summary.corrupt.is_empty() {
+        let plan = plan_quarantine(&state.work_dir, &summary.corrupt, &state.quarantine_dir);
+        let outcome = execute_renames(plan, &*state.reporter);
+        print_failures(&outcome.failures, "隔离");
+        for png in &mut summary.corrupt {
+            png.quarantined = outcome.done.iter()
+                .find(|rename| rename.from == png.path)
+                .map(|rename| rename.to.clone());
+        }
     }
 
+    match format {
+        OutputFormat::Json => print_json(&summary)?,
+        OutputFormat::Csv => {
+            let rows: Vec<Vec<String>> = summary.corrupt.iter()
+                .map(|png| vec![
+                    png.path.display().to_string(),
+                    png.reason.clone(),
+                    png.quarantined.as_ref().map(|path| path.display().to_string()).unwrap_or_default(),
+                ])
+                .collect();
+            print_csv(&["path", "reason", "quarantined"], &rows);
+        }
+        OutputFormat::Text => print_verify_summary(&summary, quarantine),
+    }
+    Ok(())
+}
+
+fn print_verify_summary(summary: &VerifySummary, quarantine: bool) {
+    let green = Style::new().green();
+    let red = Style::new().red();
+
+    if summary.corrupt.is_empty() {
+        println!("{}", green.apply_to(format!("✅ 已校验 {} 个PNG，全部完好", summary.checked)));
+        return;
+    }
+    println!("{}", red.apply_to(format!("❌ 已校验 {} 个PNG，{} 个损坏:", summary.checked, summary.corrupt.len())));
+    for png in &summary.corrupt {
+        println!("  {}: {}", png.path.display(), png.reason);
+        if let Some(quarantined) = &png.quarantined {
+            println!("    → 已移至 {}", quarantined.display());
+        }
+    }
+    if !quarantine {
+        println!("💡 使用 --quarantine 将损坏的文件移入隔离目录，重新从模拟器共享文件夹复制后再处理");
+    }
+}
+
+fn handle_search_atlas(state: &AppState) -> Result<()> {
+    let yellow = Style::new().yellow();
+
     println!("{}", yellow.apply_to("🔍 搜索Atlas内容"));
 
+    let scope = prompt_search_scope(&[NameKind::Page, NameKind::Region])?;
+
     let search_text: String = Input::new()
         .with_prompt("请输入搜索内容（例如：2017,1937）")
         .interact()?;
@@ -319,51 +1020,958 @@ fn handle_search_atlas(state: &AppState) -> Result<()> {
         return Ok(());
     }
 
-    let results = search_atlas_content(&state.atlas_dir, &search_text)?;
+    run_search_atlas(state, search_text.trim(), &scope, OutputFormat::Text)
+}
 
-    if results.is_empty() {
-        println!("❌ 未找到匹配的内容");
-    } else {
-        println!("✅ 找到 {} 个匹配的文件:", results.len());
-        for result in results {
-            println!("  📄 {}", result.display());
-        }
+fn run_search_atlas(state: &AppState, search_text: &str, scope: &SearchScope, format: OutputFormat) -> Result<()> {
+    if !state.atlas_dir.exists() {
+        anyhow::bail!("Atlas目录不存在: {:?}", state.atlas_dir);
     }
 
-    Ok(())
+    let mut results = search_atlas_content(&state.atlas_dir, search_text, scope, &*state.reporter)?;
+    if results.searched == 0 {
+        eprintln!("📁 在目录 {:?} 中未找到 .atlas 文件", state.atlas_dir);
+    }
+    print_failures(&results.failures, "搜索");
+    annotate_results(state, &mut results.matches)?;
+    print_search_results(&results.matches, format)
 }
 
 fn handle_search_skel(state: &AppState) -> Result<()> {
     let yellow = Style::new().yellow();
 
-    if !state.skels_dir.exists() {
-        anyhow::bail!("Skels目录不存在: {:?}", state.skels_dir);
-    }
-
     println!("{}", yellow.apply_to("🔍 搜索Skel内容"));
 
+    let scope = prompt_search_scope(&[
+        NameKind::Bone,
+        NameKind::Slot,
+        NameKind::Constraint,
+        NameKind::Skin,
+        NameKind::Attachment,
+        NameKind::Event,
+        NameKind::Animation,
+    ])?;
+
     println!("请输入搜索内容（支持多个，用空格分隔，例如：biaoqing_jiangdongzhizhi biaoqing_yansu）:");
     let search_input: String = Input::new()
         .with_prompt("搜索内容")
         .interact()?;
 
-    let search_texts: Vec<&str> = search_input.trim().split_whitespace().collect();
+    let search_texts: Vec<&str> = search_input.split_whitespace().collect();
 
-    if search_texts.is_empty() || (search_texts.len() == 1 && search_texts[0].is_empty()) {
+    if search_texts.is_empty() {
         println!("⚠️  搜索内容不能为空");
         return Ok(());
     }
 
-    let results = search_skel_content(&state.skels_dir, &search_texts)?;
+    let options = SkelSearchOptions { scope, ..SkelSearchOptions::default() };
+    run_search_skel(state, &search_texts, &options, OutputFormat::Text)
+}
 
-    if results.is_empty() {
-        println!("❌ 未找到匹配的文件");
+/// 交互选择匹配方式；选择正则或通配符时可进一步限定名称类别
+fn prompt_search_scope(kinds: &[NameKind]) -> Result<SearchScope> {
+    let mode = match Select::new()
+        .with_prompt("请选择匹配方式")
+        .items(&["普通文本", "正则表达式（匹配名称）", "通配符（匹配名称）"])
+        .default(0)
+        .interact()?
+    {
+        1 => PatternMode::Regex,
+        2 => PatternMode::Glob,
+        _ => return Ok(SearchScope::default()),
+    };
+
+    let labels: Vec<&str> = kinds.iter().map(|kind| kind.label()).collect();
+    let selected = MultiSelect::new()
+        .with_prompt("请选择要搜索的名称类别（空格选择，回车确认，不选则搜索全部）")
+        .items(&labels)
+        .interact()?;
+
+    Ok(SearchScope {
+        mode,
+        kinds: selected.into_iter().map(|i| kinds[i]).collect(),
+    })
+}
+
+fn run_search_skel(state: &AppState, search_texts: &[&str], options: &SkelSearchOptions, format: OutputFormat) -> Result<()> {
+    if !state.skels_dir.exists() {
+        anyhow::bail!("Skels目录不存在: {:?}", state.skels_dir);
+    }
+    if !(0.0..=1.0).contains(&options.min_score) {
+        anyhow::bail!("最低匹配得分必须在0到1之间: {}", options.min_score);
+    }
+
+    let mut results = search_skel_content(&state.skels_dir, search_texts, options, &*state.reporter)?;
+    if results.searched == 0 {
+        eprintln!("📁 在目录 {:?} 中未找到 .skel 文件", state.skels_dir);
+    }
+    print_failures(&results.failures, "搜索");
+    annotate_results(state, &mut results.matches)?;
+    print_search_results(&results.matches, format)
+}
+
+/// 填入搜索结果的中文显示名称与已还原标记
+fn annotate_results(state: &AppState, results: &mut [SearchMatch]) -> Result<()> {
+    let dictionary = Dictionary::load(&state.dictionary_path)?;
+    let catalog = Catalog::load(&state.catalog_path)?;
+    for result in results {
+        result.display_name = dictionary.lookup(&result.path).map(str::to_string);
+        if !catalog.entries.is_empty() {
+            result.restored = file_hash(&result.path).ok()
+                .and_then(|hash| catalog.find_by_hash(&hash))
+                .map(|entry| format!("#{} {}", entry.id, entry.title()));
+        }
+    }
+    Ok(())
+}
+
+fn display_path(path: &Path, display_name: Option<&str>) -> String {
+    match display_name {
+        Some(name) => format!("{} 〔{}〕", path.display(), name),
+        None => path.display().to_string(),
+    }
+}
+
+fn print_search_results(results: &[SearchMatch], format: OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Json => print_json(&results)?,
+        OutputFormat::Csv => {
+            let rows: Vec<Vec<String>> = results.iter()
+                .flat_map(|result| {
+                    result.details.iter().map(move |detail| vec![
+                        result.path.display().to_string(),
+                        result.display_name.clone().unwrap_or_default(),
+                        result.restored.clone().unwrap_or_default(),
+                        format!("{:.3}", result.score),
+                        result.terms.join(" "),
+                        detail.term.clone(),
+                        detail.kind.map(|k| k.label().to_string()).unwrap_or_default(),
+                        detail.line.map(|l| l.to_string()).unwrap_or_default(),
+                        detail.text.clone(),
+                    ])
+                })
+                .collect();
+            print_csv(&["path", "display_name", "restored", "score", "terms", "term", "kind", "line", "text"], &rows);
+        }
+        OutputFormat::Text => {
+            if results.is_empty() {
+                println!("❌ 未找到匹配的文件");
+            } else {
+                println!("✅ 找到 {} 个匹配的文件:", results.len());
+                let dim = Style::new().dim();
+                let green = Style::new().green();
+                for result in results {
+                    if result.total_terms() > 1 {
+                        println!("  📄 {}  匹配 {}/{} {}",
+                            display_path(&result.path, result.display_name.as_deref()),
+                            result.terms.len(),
+                            result.total_terms(),
+                            dim.apply_to(format!("(得分 {:.2})", result.score)));
+                    } else {
+                        println!("  📄 {}", display_path(&result.path, result.display_name.as_deref()));
+                    }
+                    if let Some(restored) = &result.restored {
+                        println!("     {}", green.apply_to(format!("✔ 已还原: {}", restored)));
+                    }
+                    if !result.missing_terms.is_empty() {
+                        println!("     {}", dim.apply_to(format!("未命中: {}", result.missing_terms.join(" "))));
+                    }
+                    print_match_details(&result.details);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_match_details(details: &[MatchDetail]) {
+    let dim = Style::new().dim();
+
+    for detail in details {
+        let kind = detail.kind.map(|k| k.label()).unwrap_or("字符串");
+        match detail.line {
+            // Atlas：显示命中行所在的整个页面或区域块
+            Some(line) => {
+                println!("     {} {}", dim.apply_to(format!("第{}行", line)), kind);
+                // 命中行的范围相对于去掉缩进后的文本
+                for context in &detail.context {
+                    let indent = context.len() - context.trim_start().len();
+                    let span = detail.span.clone()
+                        .filter(|_| context.trim() == detail.text)
+                        .map(|span| span.start + indent..span.end + indent);
+                    println!("       {} {}", dim.apply_to("│"), highlight(context, span));
+                }
+            }
+            None => {
+                let context = if detail.context.is_empty() {
+                    String::new()
+                } else {
+                    format!("  {}", dim.apply_to(format!("({})", detail.context.join(", "))))
+                };
+                println!("     {} → {}: {}{}", detail.term, kind, highlight(&detail.text, detail.span.clone()), context);
+            }
+        }
+    }
+}
+
+fn handle_fuzzy(state: &AppState) -> Result<()> {
+    let yellow = Style::new().yellow();
+
+    println!("{}", yellow.apply_to("🔍 模糊搜索名称"));
+
+    let query: String = Input::new()
+        .with_prompt("请输入记得的名称（例如：shengzi_jianjia）")
+        .interact()?;
+
+    if query.trim().is_empty() {
+        println!("⚠️  搜索内容不能为空");
+        return Ok(());
+    }
+
+    run_fuzzy(state, query.trim(), &[], 10, OutputFormat::Text)
+}
+
+fn run_fuzzy(state: &AppState, query: &str, kinds: &[NameKind], limit: usize, format: OutputFormat) -> Result<()> {
+    let results = fuzzy_search_names(&state.atlas_dir, &state.skels_dir, query, kinds, limit, &*state.reporter);
+    print_failures(&results.failures, "解析");
+    let matches = results.matches;
+
+    match format {
+        OutputFormat::Json => print_json(&matches)?,
+        OutputFormat::Csv => {
+            let rows: Vec<Vec<String>> = matches.iter()
+                .flat_map(|m| {
+                    m.files.iter().map(move |file| vec![
+                        m.name.clone(),
+                        format!("{:.3}", m.score),
+                        kind_labels(m),
+                        file.display().to_string(),
+                    ])
+                })
+                .collect();
+            print_csv(&["name", "score", "kinds", "path"], &rows);
+        }
+        OutputFormat::Text => print_fuzzy_matches(&matches, &Dictionary::load(&state.dictionary_path)?),
+    }
+
+    Ok(())
+}
+
+fn kind_labels(m: &FuzzyMatch) -> String {
+    m.kinds.iter().map(|k| k.label()).collect::<Vec<_>>().join("、")
+}
+
+fn print_fuzzy_matches(matches: &[FuzzyMatch], dictionary: &Dictionary) {
+    const MAX_FILES_SHOWN: usize = 5;
+    let dim = Style::new().dim();
+    let cyan = Style::new().cyan();
+
+    if matches.is_empty() {
+        println!("❌ 未找到相近的名称");
+        return;
+    }
+
+    println!("✅ 找到 {} 个相近的名称:", matches.len());
+    for m in matches {
+        println!("  {} {}  {}", dim.apply_to(format!("{:.2}", m.score)), cyan.apply_to(&m.name), dim.apply_to(format!("({})", kind_labels(m))));
+        for file in m.files.iter().take(MAX_FILES_SHOWN) {
+            println!("     📄 {}", dictionary.annotate(file, file.display().to_string()));
+        }
+        if m.files.len() > MAX_FILES_SHOWN {
+            println!("     {}", dim.apply_to(format!("… 另有 {} 个文件", m.files.len() - MAX_FILES_SHOWN)));
+        }
+    }
+}
+
+fn run_report(state: &AppState, check_alpha: bool, format: OutputFormat) -> Result<()> {
+    let workspace = Workspace::load(&state.work_dir, &state.atlas_dir, &state.skels_dir, &*state.reporter);
+    let report = build_report(&workspace, check_alpha);
+
+    match format {
+        OutputFormat::Text => {
+            let base_dir = state.work_dir.parent().unwrap_or(&state.work_dir);
+            print_report(&report, base_dir, &Dictionary::load(&state.dictionary_path)?);
+        }
+        OutputFormat::Json => print_json(&report)?,
+        OutputFormat::Csv => print_report_csv(&report),
+    }
+
+    Ok(())
+}
+
+/// 以终端表格形式打印报告，路径显示为相对于`base_dir`的形式，并附上词典中的显示名称
+fn print_report(report: &Report, base_dir: &Path, dictionary: &Dictionary) {
+    let green = Style::new().green();
+    let cyan = Style::new().cyan();
+
+    let short = |path: &Path| -> String {
+        dictionary.annotate(path, path.strip_prefix(base_dir).unwrap_or(path).display().to_string())
+    };
+
+    println!();
+    println!("{}", green.apply_to("=== 还原进度报告 ==="));
+    println!("PNG: {}  Atlas: {}  Skel: {}", report.png_count, report.atlas_count, report.skel_count);
+
+    println!();
+    println!("{}", cyan.apply_to(format!("🖼️  未匹配任何Atlas页面的PNG ({})", report.orphan_pngs.len())));
+    if !report.orphan_pngs.is_empty() {
+        let rows: Vec<Vec<String>> = report.orphan_pngs.iter()
+            .map(|png| vec![
+                short(&png.path),
+                format!("{}x{}", png.width, png.height),
+                png.meta.map(|meta| meta.format_label()).unwrap_or_else(|| "-".to_string()),
+                png.meta.map(|meta| meta.alpha.label()).unwrap_or("-").to_string(),
+            ])
+            .collect();
+        print_table(&["文件", "尺寸", "格式", "透明"], &rows);
+        let opaque = report.orphan_pngs.iter()
+            .filter(|png| png.meta.is_some_and(|meta| meta.alpha == AlphaUsage::Opaque))
+            .count();
+        if opaque > 0 {
+            println!("💡 其中 {} 个完全不透明，多为背景图而非Spine的Atlas页面", opaque);
+        }
+        if report.orphan_pngs.iter().any(|png| png.meta.is_some_and(|meta| meta.alpha == AlphaUsage::Unchecked)) {
+            println!("💡 使用 report --alpha 解码像素，找出完全不透明的背景图（较慢）");
+        }
+    }
+
+    println!();
+    println!("{}", cyan.apply_to(format!("📄 缺少PNG页面的Atlas ({})", report.incomplete_atlases.len())));
+    if !report.incomplete_atlases.is_empty() {
+        let rows: Vec<Vec<String>> = report.incomplete_atlases.iter()
+            .flat_map(|atlas| {
+                atlas.missing_pages.iter().map(move |page| {
+                    vec![short(&atlas.path), page.name.clone(), format!("{}x{}", page.width, page.height)]
+                })
+            })
+            .collect();
+        print_table(&["Atlas", "缺失页面", "尺寸"], &rows);
+    }
+
+    println!();
+    println!("{}", cyan.apply_to(format!("📄 未被任何Skel引用的Atlas ({})", report.unreferenced_atlases.len())));
+    if !report.unreferenced_atlases.is_empty() {
+        let rows: Vec<Vec<String>> = report.unreferenced_atlases.iter()
+            .map(|atlas| vec![short(&atlas.path), atlas.pages.to_string(), atlas.regions.to_string()])
+            .collect();
+        print_table(&["Atlas", "页面数", "区域数"], &rows);
+    }
+
+    println!();
+    println!("{}", cyan.apply_to(format!("🦴 附件无法解析的Skel ({})", report.unresolved_skels.len())));
+    if !report.unresolved_skels.is_empty() {
+        let rows: Vec<Vec<String>> = report.unresolved_skels.iter()
+            .map(|skel| vec![
+                short(&skel.path),
+                skel.version.clone(),
+                skel.attachments.to_string(),
+                skel.best_atlas.as_deref().map(short).unwrap_or_else(|| "-".to_string()),
+                skel.missing.len().to_string(),
+            ])
+            .collect();
+        print_table(&["Skel", "版本", "附件数", "最佳候选Atlas", "缺失附件数"], &rows);
+    }
+
+    if !report.skels_without_attachments.is_empty() {
+        println!();
+        println!("{}", cyan.apply_to(format!("🦴 没有区域附件的Skel ({})", report.skels_without_attachments.len())));
+        let rows: Vec<Vec<String>> = report.skels_without_attachments.iter()
+            .map(|skel| vec![short(&skel.path), skel.version.clone()])
+            .collect();
+        print_table(&["Skel", "版本"], &rows);
+    }
+
+    if !report.failures.is_empty() {
+        println!();
+        println!("{}", cyan.apply_to(format!("⚠️  无法解析的文件 ({})", report.failures.len())));
+        let rows: Vec<Vec<String>> = report.failures.iter()
+            .map(|failure| vec![short(&failure.path), failure.error.clone()])
+            .collect();
+        print_table(&["文件", "错误"], &rows);
+    }
+
+    println!();
+}
+
+/// 以CSV形式打印报告，每行一条记录：类别、路径、详情
+fn print_report_csv(report: &Report) {
+    let mut rows: Vec<Vec<String>> = Vec::new();
+
+    for png in &report.orphan_pngs {
+        let mut detail = format!("{}x{}", png.width, png.height);
+        if let Some(meta) = png.meta {
+            detail.push_str(&format!(" format={} interlaced={} trns={} alpha={}",
+                meta.format_key(), meta.interlaced, meta.has_trns, meta.alpha.key()));
+        }
+        rows.push(vec!["orphan_png".to_string(), png.path.display().to_string(), detail]);
+    }
+    for atlas in &report.incomplete_atlases {
+        for page in &atlas.missing_pages {
+            rows.push(vec![
+                "incomplete_atlas".to_string(),
+                atlas.path.display().to_string(),
+                format!("{} {}x{}", page.name, page.width, page.height),
+            ]);
+        }
+    }
+    for atlas in &report.unreferenced_atlases {
+        rows.push(vec![
+            "unreferenced_atlas".to_string(),
+            atlas.path.display().to_string(),
+            format!("pages={} regions={}", atlas.pages, atlas.regions),
+        ]);
+    }
+    for skel in &report.unresolved_skels {
+        rows.push(vec![
+            "unresolved_skel".to_string(),
+            skel.path.display().to_string(),
+            format!("version={} attachments={} missing={} best_atlas={}",
+                skel.version,
+                skel.attachments,
+                skel.missing.len(),
+                skel.best_atlas.as_deref().map(|p| p.display().to_string()).unwrap_or_default()),
+        ]);
+    }
+    for skel in &report.skels_without_attachments {
+        rows.push(vec![
+            "skel_without_attachments".to_string(),
+            skel.path.display().to_string(),
+            format!("version={}", skel.version),
+        ]);
+    }
+    for failure in &report.failures {
+        rows.push(vec![
+            "failure".to_string(),
+            failure.path.display().to_string(),
+            failure.error.clone(),
+        ]);
+    }
+
+    print_csv(&["category", "path", "detail"], &rows);
+}
+
+fn run_gallery(state: &AppState, output_dir: &Path, thumb_size: u32) -> Result<()> {
+    let yellow = Style::new().yellow();
+    let green = Style::new().green();
+
+    println!("{}", yellow.apply_to("🖼️  正在生成PNG画廊..."));
+
+    let workspace = Workspace::load(&state.work_dir, &state.atlas_dir, &state.skels_dir, &*state.reporter);
+    let dictionary = Dictionary::load(&state.dictionary_path)?;
+    let summary = build_gallery(&workspace, output_dir, thumb_size, &dictionary, &*state.reporter)?;
+
+    println!("{}", green.apply_to(format!("✅ 画廊生成完成：{} 张图片，{} 种尺寸", summary.images, summary.groups)));
+    print_failures(&summary.thumbnail_failures, "生成缩略图");
+    println!("📄 在浏览器中打开: {}", summary.index_path.display());
+
+    Ok(())
+}
+
+fn run_cluster(state: &AppState, output_dir: &Path, layout: ClusterLayout, options: ClusterOptions, thumb_size: u32) -> Result<()> {
+    let yellow = Style::new().yellow();
+    let green = Style::new().green();
+
+    if !(0.0..=1.0).contains(&options.min_color) {
+        anyhow::bail!("--min-color 应在0到1之间: {}", options.min_color);
+    }
+
+    println!("{}", yellow.apply_to("🧩 正在聚类相似图片..."));
+
+    let clustering = cluster_pngs(&state.work_dir, options, &*state.reporter);
+    let grouped: usize = clustering.clusters.iter().map(Vec::len).sum();
+    println!("{}", green.apply_to(format!("✅ 找到 {} 个相似图片聚类，共 {} 张图片；{} 张没有相似图片",
+        clustering.clusters.len(), grouped, clustering.singletons)));
+    if !clustering.failures.is_empty() {
+        println!("{}", yellow.apply_to(format!("⚠️  {} 个PNG无法解码，已跳过，可使用 verify 命令检查", clustering.failures.len())));
+    }
+    if clustering.clusters.is_empty() {
+        return Ok(());
+    }
+
+    match layout {
+        ClusterLayout::Html => {
+            let index_path = write_cluster_html(&clustering, output_dir, thumb_size)?;
+            println!("📄 在浏览器中打开: {}", index_path.display());
+        }
+        ClusterLayout::Folders => {
+            write_cluster_folders(&clustering, output_dir)?;
+            println!("📁 聚类已复制到: {}", output_dir.display());
+        }
+    }
+
+    Ok(())
+}
+
+fn run_convert(state: &AppState, skel: &Path, target: skel::Format, binary: bool, output: Option<PathBuf>, strict: bool, force: bool) -> Result<()> {
+    let source = skel_data::read_skeleton_data(skel)?;
+    println!("🔄 Spine {} → {}（{}）", source.version, target.version(), if binary { "二进制" } else { "JSON" });
+
+    let conversion = convert_skeleton(&source, target, binary);
+    if conversion.issues.is_empty() {
+        println!("✅ 全部内容均可原样转换");
     } else {
-        println!("✅ 找到 {} 个匹配的文件:", results.len());
-        for result in results {
-            println!("  📄 {}", result.display());
+        let yellow = Style::new().yellow();
+        println!("⚠️  {} 处内容无法原样转换:", conversion.issues.len());
+        for issue in &conversion.issues {
+            println!("  [{}] {}: {}", yellow.apply_to(issue.kind.label()), issue.location, issue.message);
+        }
+        if strict {
+            anyhow::bail!("已启用 --strict，未写出文件");
         }
     }
 
+    let output = output.unwrap_or_else(|| {
+        let stem = skel.file_stem().and_then(|s| s.to_str()).unwrap_or("skeleton");
+        let extension = if binary { "skel" } else { "json" };
+        state.output_dir.join(format!("{}_{}.{}", stem, target.label(), extension))
+    });
+    write_converted(&conversion.data, skel, &output, binary, force)?;
+    println!("✅ 已写出: {}", output.display());
     Ok(())
-}
\ No newline at end of file
+}
+
+fn run_validate(dir: &Path, format: OutputFormat) -> Result<()> {
+    let report = validate_folder(dir)?;
+    match format {
+        OutputFormat::Json => print_json(&report)?,
+        OutputFormat::Csv => {
+            let mut rows: Vec<Vec<String>> = Vec::new();
+            for check in &report.checks {
+                if check.passed() {
+                    rows.push(vec![check.name.to_string(), "pass".to_string(), String::new()]);
+                }
+                for problem in &check.problems {
+                    rows.push(vec![check.name.to_string(), "fail".to_string(), problem.clone()]);
+                }
+            }
+            print_csv(&["check", "status", "problem"], &rows);
+        }
+        OutputFormat::Text => print_validation(&report),
+    }
+
+    let failed = report.checks.iter().filter(|check| !check.passed()).count();
+    if failed > 0 {
+        anyhow::bail!("校验未通过：{} 项检查发现问题", failed);
+    }
+    Ok(())
+}
+
+fn print_validation(report: &ValidationReport) {
+    let green = Style::new().green();
+    let red = Style::new().red();
+
+    println!();
+    println!("{}", green.apply_to("=== 角色文件夹校验 ==="));
+    println!("文件夹: {}", report.dir.display());
+    println!("Atlas: {}", report.atlas.display());
+    println!("Skel: {}", report.skel.display());
+    println!();
+    for check in &report.checks {
+        if check.passed() {
+            println!("{} {}（{} 项）", green.apply_to("✅ 通过"), check.name, check.checked);
+            continue;
+        }
+        println!("{} {}（{} 项，{} 个问题）", red.apply_to("❌ 失败"), check.name, check.checked, check.problems.len());
+        for problem in &check.problems {
+            println!("    - {}", problem);
+        }
+    }
+    println!();
+    if report.passed() {
+        println!("{}", green.apply_to("🎉 全部检查通过，查看器应能正常加载"));
+    }
+}
+
+fn run_pma(action: PmaAction) -> Result<()> {
+    let (dir, pages, premultiplied, force) = match action {
+        PmaAction::Check { dir, format } => return run_pma_check(&dir, format),
+        PmaAction::Premultiply { dir, page, force } => (dir, page, true, force),
+        PmaAction::Unpremultiply { dir, page, force } => (dir, page, false, force),
+    };
+
+    let atlas = parse_atlas_file(&single_file_with_extension(&dir, "atlas")?)?;
+    let action = if premultiplied { "预乘" } else { "取消预乘" };
+    for result in convert_pages(&atlas, &pages, premultiplied, force)? {
+        match result.status {
+            PageStatus::AlreadyTarget => {
+                println!("⏭️  页面 {} 的像素已是{}，跳过（--force 强制处理）", result.page, if premultiplied { "预乘" } else { "非预乘" });
+            }
+            PageStatus::Unknown => {
+                println!("⏭️  无法判断页面 {} 的像素是否已预乘，跳过以免重复处理（确认后用 --force 强制处理）", result.page);
+            }
+            PageStatus::Converted => {
+                println!("✅ 页面 {} 已{}", result.page, action);
+                if let Some(format) = &result.original_format {
+                    println!("   原PNG为 {}，已转为 rgba8", format);
+                }
+                if let Some(backup) = &result.backup {
+                    println!("   原文件保存在 {}", backup.display());
+                }
+            }
+        }
+        if result.flag_updated {
+            println!("   已将Atlas中的标记改为 pma:{}", premultiplied);
+        }
+    }
+    if atlas.format == AtlasFormat::Legacy {
+        println!("💡 3.x Atlas没有pma标记，请在查看器中{}预乘Alpha", if premultiplied { "开启" } else { "关闭" });
+    }
+    Ok(())
+}
+
+fn run_pma_check(dir: &Path, format: OutputFormat) -> Result<()> {
+    let atlas = parse_atlas_file(&single_file_with_extension(dir, "atlas")?)?;
+    let pages = check_atlas_pma(&atlas);
+
+    let declared = |value: Option<bool>| match value {
+        Some(pma) => format!("pma:{}", pma),
+        None => "查看器设置".to_string(),
+    };
+    match format {
+        OutputFormat::Json => print_json(&pages)?,
+        OutputFormat::Csv => {
+            let rows: Vec<Vec<String>> = pages.iter()
+                .map(|page| vec![
+                    page.page.clone(),
+                    page.declared.map(|pma| pma.to_string()).unwrap_or_default(),
+                    page.estimate.map(|e| e.mode.label().to_string()).unwrap_or_default(),
+                    page.estimate.map(|e| e.samples.to_string()).unwrap_or_default(),
+                    page.estimate.map(|e| e.exceeding.to_string()).unwrap_or_default(),
+                    page.mismatch().unwrap_or_default(),
+                ])
+                .collect();
+            print_csv(&["page", "declared", "estimate", "samples", "exceeding", "mismatch"], &rows);
+        }
+        OutputFormat::Text => {
+            let green = Style::new().green();
+            println!();
+            println!("{}", green.apply_to(format!("=== 预乘Alpha（{}，{} 格式）===", atlas.path.display(), atlas.format.label())));
+            let rows: Vec<Vec<String>> = pages.iter()
+                .map(|page| vec![
+                    page.page.clone(),
+                    declared(page.declared),
+                    page.estimate.map(|e| e.mode.label().to_string()).unwrap_or_else(|| "PNG无法读取".to_string()),
+                    page.estimate.map(|e| format!("{}/{}", e.exceeding, e.samples)).unwrap_or_default(),
+                ])
+                .collect();
+            print_table(&["页面", "Atlas设置", "像素估计", "颜色超出Alpha/半透明像素"], &rows);
+            println!();
+            for page in &pages {
+                if let Some(mismatch) = page.mismatch() {
+                    println!("⚠️  {}", mismatch);
+                }
+                if let Some(hint) = page.viewer_hint() {
+                    println!("💡 {}", hint);
+                }
+            }
+            if pages.iter().all(|page| page.mismatch().is_none()) {
+                println!("✅ 页面像素与Atlas的预乘设置一致");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 扫描当前工作区：PNG所在的工作目录与归类后的Atlas、Skel目录
+fn scan_workspace(state: &AppState) -> Snapshot {
+    let root = state.work_dir.parent().unwrap_or(&state.work_dir);
+    let snapshot = Snapshot::scan(root, &[&state.work_dir, &state.atlas_dir, &state.skels_dir], &*state.reporter);
+    print_failures(&snapshot.failures, "计算hash");
+    snapshot
+}
+
+/// 读取快照文件，或现场扫描快照目录
+fn load_snapshot(state: &AppState, path: &Path) -> Result<Snapshot> {
+    let snapshot = Snapshot::load(path, &*state.reporter)?;
+    print_failures(&snapshot.failures, "计算hash");
+    Ok(snapshot)
+}
+
+fn run_snapshot(state: &AppState, output: &Path) -> Result<()> {
+    let snapshot = scan_workspace(state);
+    snapshot.save(output)?;
+    println!("✅ 已保存 {} 个文件的快照: {}", snapshot.files.len(), output.display());
+    Ok(())
+}
+
+fn run_diff(state: &AppState, old: &Path, new: Option<&Path>, format: OutputFormat) -> Result<()> {
+    if !old.exists() {
+        anyhow::bail!("快照文件或目录不存在: {:?}", old);
+    }
+    let old = load_snapshot(state, old)?;
+    let new = match new {
+        Some(path) => load_snapshot(state, path)?,
+        None => scan_workspace(state),
+    };
+    let diff = diff_snapshots(&old, &new);
+
+    match format {
+        OutputFormat::Json => print_json(&diff)?,
+        OutputFormat::Csv => {
+            let mut rows: Vec<Vec<String>> = Vec::new();
+            for file in &diff.added {
+                rows.push(vec!["added".to_string(), file.kind.label().to_string(), file.path.display().to_string(), file.hash.clone()]);
+            }
+            for file in &diff.removed {
+                rows.push(vec!["removed".to_string(), file.kind.label().to_string(), file.path.display().to_string(), file.hash.clone()]);
+            }
+            for change in &diff.changed {
+                rows.push(vec!["changed".to_string(), change.new.kind.label().to_string(), change.new.path.display().to_string(), change.new.hash.clone()]);
+            }
+            print_csv(&["status", "kind", "path", "hash"], &rows);
+        }
+        OutputFormat::Text => print_diff(&diff),
+    }
+
+    Ok(())
+}
+
+fn print_diff(diff: &SnapshotDiff) {
+    let green = Style::new().green();
+    let cyan = Style::new().cyan();
+
+    println!();
+    println!("{}", green.apply_to("=== 资源变化 ==="));
+    println!("未变化: {}  新增: {}  删除: {}  修改: {}", diff.unchanged, diff.added.len(), diff.removed.len(), diff.changed.len());
+
+    // 新增的Skel最可能是需要还原的新角色或新皮肤，单独列在最前面
+    let new_skels: Vec<&SnapshotFile> = diff.added.iter().filter(|f| f.kind == AssetKind::Skel).collect();
+    let new_skin_skels: Vec<_> = diff.changed.iter().filter(|c| !c.new_skins.is_empty()).collect();
+    if !new_skels.is_empty() || !new_skin_skels.is_empty() {
+        println!();
+        println!("{}", green.apply_to(format!("🆕 新骨骼：可能是新角色或新皮肤 ({})", new_skels.len() + new_skin_skels.len())));
+        let mut rows: Vec<Vec<String>> = new_skels.iter()
+            .map(|f| vec![
+                f.path.display().to_string(),
+                f.version.clone().unwrap_or_else(|| "?".to_string()),
+                "新增".to_string(),
+                f.skins.join(", "),
+            ])
+            .collect();
+        rows.extend(new_skin_skels.iter().map(|c| vec![
+            c.new.path.display().to_string(),
+            c.new.version.clone().unwrap_or_else(|| "?".to_string()),
+            "新增皮肤".to_string(),
+            c.new_skins.join(", "),
+        ]));
+        print_table(&["Skel", "版本", "变化", "皮肤"], &rows);
+    }
+
+    for kind in [AssetKind::Skel, AssetKind::Atlas, AssetKind::Png] {
+        let added: Vec<&SnapshotFile> = diff.added.iter().filter(|f| f.kind == kind).collect();
+        let removed: Vec<&SnapshotFile> = diff.removed.iter().filter(|f| f.kind == kind).collect();
+        let changed: Vec<_> = diff.changed.iter().filter(|c| c.new.kind == kind).collect();
+        if added.is_empty() && removed.is_empty() && changed.is_empty() {
+            continue;
+        }
+
+        println!();
+        println!("{}", cyan.apply_to(format!("📄 {}：新增 {}，删除 {}，修改 {}", kind.label(), added.len(), removed.len(), changed.len())));
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        rows.extend(added.iter().map(|f| vec!["+".to_string(), f.path.display().to_string(), HumanBytes(f.bytes).to_string()]));
+        rows.extend(removed.iter().map(|f| vec!["-".to_string(), f.path.display().to_string(), HumanBytes(f.bytes).to_string()]));
+        rows.extend(changed.iter().map(|c| vec![
+            "~".to_string(),
+            c.new.path.display().to_string(),
+            format!("{} → {}", HumanBytes(c.old.bytes), HumanBytes(c.new.bytes)),
+        ]));
+        print_table(&["", "文件", "大小"], &rows);
+    }
+
+    println!();
+}
+
+fn run_catalog(state: &AppState, action: CatalogAction) -> Result<()> {
+    let green = Style::new().green();
+    let mut catalog = Catalog::load(&state.catalog_path)?;
+
+    match action {
+        CatalogAction::Add { dir, name, skin } => {
+            if !dir.is_dir() {
+                anyhow::bail!("角色文件夹不存在: {:?}", dir);
+            }
+            let name = match name {
+                Some(name) => name,
+                None => dir.canonicalize()?
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .map(str::to_string)
+                    .ok_or_else(|| anyhow::anyhow!("无法从文件夹名得到角色名，请通过 --name 指定"))?,
+            };
+            let entry = catalog.add(entry_from_dir(&dir, &name, skin.as_deref())?)?;
+            println!("✅ 已记入目录: #{} {}", entry.id, green.apply_to(entry.title()));
+            catalog.save(&state.catalog_path)?;
+        }
+        CatalogAction::List { format } => {
+            let entries: Vec<&CatalogEntry> = catalog.entries.iter().collect();
+            print_catalog_entries(&entries, format)?;
+        }
+        CatalogAction::Search { text, format } => print_catalog_entries(&catalog.search(&text), format)?,
+        CatalogAction::Show { id, format } => {
+            let entry = catalog.get(id).ok_or_else(|| anyhow::anyhow!("目录中没有编号 #{}", id))?;
+            match format {
+                OutputFormat::Text => print_catalog_entry(entry),
+                _ => print_catalog_entries(&[entry], format)?,
+            }
+        }
+        CatalogAction::Remove { id } => {
+            let entry = catalog.remove(id).ok_or_else(|| anyhow::anyhow!("目录中没有编号 #{}", id))?;
+            catalog.save(&state.catalog_path)?;
+            println!("✅ 已从目录中删除: #{} {}（文件保留在 {}）", entry.id, entry.title(), entry.output_dir.display());
+        }
+    }
+
+    Ok(())
+}
+
+fn print_catalog_entries(entries: &[&CatalogEntry], format: OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Json => print_json(&entries)?,
+        OutputFormat::Csv => {
+            let rows: Vec<Vec<String>> = entries.iter()
+                .map(|e| vec![
+                    e.id.to_string(),
+                    e.name.clone(),
+                    e.skin.clone().unwrap_or_default(),
+                    e.spine_version.clone(),
+                    e.restored_at.clone(),
+                    e.atlas.hash.clone(),
+                    e.skel.hash.clone(),
+                    e.output_dir.display().to_string(),
+                ])
+                .collect();
+            print_csv(&["id", "name", "skin", "spine_version", "restored_at", "atlas_hash", "skel_hash", "output_dir"], &rows);
+        }
+        OutputFormat::Text => {
+            if entries.is_empty() {
+                println!("❌ 目录中没有匹配的角色");
+            } else {
+                let rows: Vec<Vec<String>> = entries.iter()
+                    .map(|e| vec![
+                        format!("#{}", e.id),
+                        e.title(),
+                        e.spine_version.clone(),
+                        e.restored_at.clone(),
+                        e.output_dir.display().to_string(),
+                    ])
+                    .collect();
+                print_table(&["编号", "角色", "Spine版本", "还原日期", "文件夹"], &rows);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_catalog_entry(entry: &CatalogEntry) {
+    let cyan = Style::new().cyan();
+
+    println!("{}", cyan.apply_to(format!("#{} {}", entry.id, entry.title())));
+    println!("  Spine版本: {}", entry.spine_version);
+    println!("  还原日期: {}", entry.restored_at);
+    println!("  文件夹: {}", entry.output_dir.display());
+    println!("  Atlas: {}  ({})", entry.atlas.file, entry.atlas.hash);
+    println!("  Skel: {}  ({})", entry.skel.file, entry.skel.hash);
+    for page in &entry.pages {
+        println!("  页面 {}: {}", page.page, page.hash);
+    }
+}
+
+fn run_dict(state: &AppState, action: DictAction) -> Result<()> {
+    let green = Style::new().green();
+    let mut dictionary = Dictionary::load(&state.dictionary_path)?;
+
+    match action {
+        DictAction::Set { target, name, fingerprint } => {
+            let key = resolve_key(&target, fingerprint)?;
+            match dictionary.set(&key, &name) {
+                Some(old) if old != name => println!("✅ {}: {} → {}", key, old, green.apply_to(&name)),
+                _ => println!("✅ {}: {}", key, green.apply_to(&name)),
+            }
+            dictionary.save(&state.dictionary_path)?;
+        }
+        DictAction::Remove { target, fingerprint } => {
+            let key = resolve_key(&target, fingerprint)?;
+            match dictionary.remove(&key) {
+                Some(name) => {
+                    dictionary.save(&state.dictionary_path)?;
+                    println!("✅ 已删除 {}: {}", key, name);
+                }
+                None => println!("⚠️  词典中没有 {}", key),
+            }
+        }
+        DictAction::List { format } => {
+            let entries = dictionary.search("", &[&state.work_dir, &state.atlas_dir, &state.skels_dir]);
+            print_dictionary_entries(&entries, format)?;
+        }
+        DictAction::Search { text, format } => {
+            let entries = dictionary.search(&text, &[&state.work_dir, &state.atlas_dir, &state.skels_dir]);
+            print_dictionary_entries(&entries, format)?;
+        }
+        DictAction::Merge { file, theirs } => {
+            if !file.exists() {
+                anyhow::bail!("词典文件不存在: {:?}", file);
+            }
+            let summary = dictionary.merge(Dictionary::load(&file)?, theirs);
+            dictionary.save(&state.dictionary_path)?;
+
+            println!("✅ 新增 {} 条，相同 {} 条，冲突 {} 条", summary.added, summary.unchanged, summary.conflicts.len());
+            if !summary.conflicts.is_empty() {
+                let kept = if theirs { "已采用对方" } else { "已保留自己" };
+                println!("⚠️  以下名称不一致（{}的名称）:", kept);
+                let rows: Vec<Vec<String>> = summary.conflicts.iter()
+                    .map(|c| vec![c.key.clone(), c.ours.clone(), c.theirs.clone()])
+                    .collect();
+                print_table(&["键", "自己", "对方"], &rows);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_dictionary_entries(entries: &[DictionaryEntry], format: OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Json => print_json(&entries)?,
+        OutputFormat::Csv => {
+            let rows: Vec<Vec<String>> = entries.iter()
+                .map(|entry| vec![
+                    entry.key.clone(),
+                    entry.name.clone(),
+                    entry.files.iter().map(|f| f.display().to_string()).collect::<Vec<_>>().join(";"),
+                ])
+                .collect();
+            print_csv(&["key", "name", "files"], &rows);
+        }
+        OutputFormat::Text => {
+            if entries.is_empty() {
+                println!("❌ 未找到匹配的名称");
+            } else {
+                // 每个文件一行，同一条记录只在第一行显示名称与键
+                let mut rows: Vec<Vec<String>> = Vec::new();
+                for entry in entries {
+                    let files: Vec<String> = if entry.files.is_empty() {
+                        vec!["-".to_string()]
+                    } else {
+                        entry.files.iter().map(|f| f.display().to_string()).collect()
+                    };
+                    for (i, file) in files.into_iter().enumerate() {
+                        let (name, key) = if i == 0 { (entry.name.clone(), entry.key.clone()) } else { (String::new(), String::new()) };
+                        rows.push(vec![name, key, file]);
+                    }
+                }
+                print_table(&["名称", "键", "文件"], &rows);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn run_serve(state: &AppState, port: u16) -> Result<()> {
+    let workspace = Workspace::load(&state.work_dir, &state.atlas_dir, &state.skels_dir, &*state.reporter);
+    let dictionary = Dictionary::load(&state.dictionary_path)?;
+    println!("📁 角色将组装到: {}", state.output_dir.display());
+    server::serve(workspace, dictionary, &state.output_dir, &state.catalog_path, port)
+}
@@ -0,0 +1,77 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::atlas::Atlas;
+use crate::skel::Skeleton;
+
+/// 区域名 -> 包含该区域的Atlas下标，用于快速判断Skel附件能否在Atlas中找到
+pub struct RegionIndex {
+    regions: HashMap<String, Vec<usize>>,
+}
+
+impl RegionIndex {
+    pub fn new(atlases: &[Atlas]) -> Self {
+        let mut regions: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, atlas) in atlases.iter().enumerate() {
+            let names: HashSet<&str> = atlas.regions.iter().map(|r| r.name.as_str()).collect();
+            for name in names {
+                regions.entry(name.to_string()).or_default().push(index);
+            }
+        }
+        Self { regions }
+    }
+
+    /// 统计Skel在各个Atlas中能找到的附件数量，按数量降序返回 (Atlas下标, 命中数)
+    pub fn coverage(&self, skeleton: &Skeleton) -> Vec<(usize, usize)> {
+        let mut counts: HashMap<usize, usize> = HashMap::new();
+        for path in &skeleton.region_paths {
+            if let Some(atlases) = self.regions.get(path) {
+                for &index in atlases {
+                    *counts.entry(index).or_default() += 1;
+                }
+            }
+        }
+
+        let mut coverage: Vec<(usize, usize)> = counts.into_iter().collect();
+        coverage.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        coverage
+    }
+
+    /// 能完整解析Skel全部附件的Atlas下标
+    pub fn resolving_atlases(&self, skeleton: &Skeleton) -> Vec<usize> {
+        let total = skeleton.region_paths.len();
+        if total == 0 {
+            return Vec::new();
+        }
+        self.coverage(skeleton)
+            .into_iter()
+            .filter(|&(_, hits)| hits == total)
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+/// 按附件命中率为某个Atlas排序候选Skel，返回 (Skel下标, 命中数)，不含零命中
+pub fn rank_skeletons(atlas: &Atlas, skeletons: &[Skeleton]) -> Vec<(usize, usize)> {
+    let names: HashSet<&str> = atlas.regions.iter().map(|r| r.name.as_str()).collect();
+
+    let mut ranked: Vec<(usize, usize)> = skeletons.iter()
+        .enumerate()
+        .map(|(i, skeleton)| {
+            let hits = skeleton.region_paths.iter()
+                .filter(|path| names.contains(path.as_str()))
+                .count();
+            (i, hits)
+        })
+        .filter(|&(_, hits)| hits > 0)
+        .collect();
+
+    // 命中率优先，其次命中数
+    ranked.sort_by(|&(a, a_hits), &(b, b_hits)| {
+        let a_total = skeletons[a].region_paths.len();
+        let b_total = skeletons[b].region_paths.len();
+        (b_hits * a_total).cmp(&(a_hits * b_total))
+            .then(b_hits.cmp(&a_hits))
+            .then(a.cmp(&b))
+    });
+    ranked
+}
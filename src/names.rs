@@ -0,0 +1,34 @@
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// Atlas与Skel中可被搜索的名称类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum NameKind {
+    Page,
+    Region,
+    Bone,
+    Slot,
+    Constraint,
+    Skin,
+    Attachment,
+    Event,
+    Animation,
+}
+
+impl NameKind {
+    /// 终端显示用的中文名称
+    pub fn label(self) -> &'static str {
+        match self {
+            NameKind::Page => "页面",
+            NameKind::Region => "区域",
+            NameKind::Bone => "骨骼",
+            NameKind::Slot => "插槽",
+            NameKind::Constraint => "约束",
+            NameKind::Skin => "皮肤",
+            NameKind::Attachment => "附件",
+            NameKind::Event => "事件",
+            NameKind::Animation => "动画",
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use console::{measure_text_width, Style};
+use serde::Serialize;
+use std::ops::Range;
+
+/// 只读命令的输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// 终端表格
+    Text,
+    /// JSON
+    Json,
+    /// CSV（首行为表头）
+    Csv,
+}
+
+pub fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// 打印CSV，按RFC 4180对含逗号、引号或换行的字段加引号
+pub fn print_csv(headers: &[&str], rows: &[Vec<String>]) {
+    let header_line: Vec<String> = headers.iter().map(|h| csv_field(h)).collect();
+    println!("{}", header_line.join(","));
+    for row in rows {
+        let line: Vec<String> = row.iter().map(|cell| csv_field(cell)).collect();
+        println!("{}", line.join(","));
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// 打印对齐的终端表格（按显示宽度对齐，兼容中文）
+pub fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let yellow = Style::new().yellow();
+
+    let mut widths: Vec<usize> = headers.iter().map(|h| measure_text_width(h)).collect();
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            if let Some(width) = widths.get_mut(i) {
+                *width = (*width).max(measure_text_width(cell));
+            }
+        }
+    }
+
+    let header_line: Vec<String> = headers.iter()
+        .zip(&widths)
+        .map(|(h, &w)| pad(h, w))
+        .collect();
+    println!("  {}", yellow.apply_to(header_line.join("  ").trim_end()));
+
+    let total: usize = widths.iter().sum::<usize>() + widths.len().saturating_sub(1) * 2;
+    println!("  {}", "─".repeat(total));
+
+    for row in rows {
+        let line: Vec<String> = row.iter()
+            .zip(&widths)
+            .map(|(cell, &w)| pad(cell, w))
+            .collect();
+        println!("  {}", line.join("  ").trim_end());
+    }
+}
+
+fn pad(text: &str, width: usize) -> String {
+    let padding = width.saturating_sub(measure_text_width(text));
+    format!("{}{}", text, " ".repeat(padding))
+}
+
+/// 高亮文本中`span`范围内的部分，范围无效时原样返回
+pub fn highlight(text: &str, span: Option<Range<usize>>) -> String {
+    match span.and_then(|span| Some((text.get(..span.start)?, text.get(span)?))) {
+        Some((before, matched)) => {
+            let after = &text[before.len() + matched.len()..];
+            format!("{}{}{}", before, Style::new().red().bold().apply_to(matched), after)
+        }
+        None => text.to_string(),
+    }
+}
@@ -0,0 +1,16 @@
+//! 由用户输入或文件内容生成文件名时共用的检查
+
+/// Windows文件名中不允许的字符（另外还有控制字符）
+pub const RESERVED_CHARS: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// 是否包含路径分隔符、Windows不允许的字符或控制字符
+pub fn has_reserved_chars(name: &str) -> bool {
+    name.contains(RESERVED_CHARS) || name.contains(char::is_control)
+}
+
+/// 将路径分隔符、Windows不允许的字符与控制字符替换为下划线，使其能用作单个路径组成部分
+pub fn sanitize_component(value: &str) -> String {
+    value.chars()
+        .map(|c| if RESERVED_CHARS.contains(&c) || c.is_control() { '_' } else { c })
+        .collect()
+}
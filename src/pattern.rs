@@ -0,0 +1,114 @@
+use anyhow::{Result, Context};
+use clap::ValueEnum;
+use globset::{GlobBuilder, GlobMatcher};
+use regex::{Regex, RegexBuilder};
+use std::ops::Range;
+
+use crate::names::NameKind;
+
+/// 搜索词的匹配方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum PatternMode {
+    /// 普通子串
+    #[default]
+    Literal,
+    /// 正则表达式，例如 `^biaoqing_.*_0[1-3]$`
+    Regex,
+    /// 通配符，例如 `jianjia_*_l_0?`
+    Glob,
+}
+
+/// 搜索范围：匹配方式与参与匹配的名称类别
+#[derive(Debug, Clone, Default)]
+pub struct SearchScope {
+    pub mode: PatternMode,
+    /// 为空时不限类别
+    pub kinds: Vec<NameKind>,
+}
+
+impl SearchScope {
+    /// 是否只在解析出的名称中匹配（而非原始内容）
+    pub fn by_name(&self) -> bool {
+        self.mode != PatternMode::Literal || !self.kinds.is_empty()
+    }
+
+    pub fn includes(&self, kind: NameKind) -> bool {
+        self.kinds.is_empty() || self.kinds.contains(&kind)
+    }
+}
+
+/// 编译后的搜索词，均不区分大小写
+#[derive(Debug, Clone)]
+pub enum NamePattern {
+    /// 已转为小写的子串
+    Literal(String),
+    Regex(Regex),
+    Glob(GlobMatcher),
+}
+
+impl NamePattern {
+    pub fn new(mode: PatternMode, pattern: &str) -> Result<Self> {
+        Ok(match mode {
+            PatternMode::Literal => NamePattern::Literal(pattern.to_lowercase()),
+            PatternMode::Regex => NamePattern::Regex(
+                RegexBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .with_context(|| format!("无效的正则表达式: {}", pattern))?,
+            ),
+            PatternMode::Glob => NamePattern::Glob(
+                GlobBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .with_context(|| format!("无效的通配符: {}", pattern))?
+                    .compile_matcher(),
+            ),
+        })
+    }
+
+    /// 名称整体是否匹配（子串与正则只需部分命中，通配符需完整命中）
+    pub fn is_match(&self, name: &str) -> bool {
+        match self {
+            NamePattern::Literal(text) => name.to_lowercase().contains(text.as_str()),
+            NamePattern::Regex(regex) => regex.is_match(name),
+            NamePattern::Glob(glob) => glob.is_match(name),
+        }
+    }
+    /// 名称中命中的字节范围，用于高亮（通配符为整个名称）
+    pub fn find(&self, name: &str) -> Option<Range<usize>> {
+        let range = match self {
+            NamePattern::Literal(text) => {
+                let lower = name.to_lowercase();
+                // 小写转换改变了字节长度时无法对应位置
+                if lower.len() != name.len() {
+                    return None;
+                }
+                lower.find(text.as_str()).map(|start| start..start + text.len())
+            }
+            NamePattern::Regex(regex) => regex.find(name).map(|m| m.range()),
+            NamePattern::Glob(glob) => glob.is_match(name).then_some(0..name.len()),
+        };
+        range.filter(|range| !range.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_matched_range() {
+        let cases = [
+            (PatternMode::Literal, "SHENG", "jianjia_shengzi_l_01", Some(8..13)),
+            (PatternMode::Literal, "", "jianjia", None),
+            (PatternMode::Regex, "shengzi_l_0[1-3]$", "jianjia_ShengZi_L_01", Some(8..20)),
+            (PatternMode::Regex, "x*", "jianjia", None),
+            (PatternMode::Glob, "jianjia_*_l_0?", "jianjia_shengzi_l_01", Some(0..20)),
+            (PatternMode::Glob, "jianjia_*", "biaoqing_01", None),
+        ];
+        for (mode, pattern, name, expected) in cases {
+            let compiled = NamePattern::new(mode, pattern).unwrap();
+            assert_eq!(compiled.find(name), expected, "{:?} {} {}", mode, pattern, name);
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use anyhow::{Result, Context};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+
+use crate::atlas::{set_page_pma, Atlas, AtlasFormat};
+use crate::imaging::{decode_rgba, encode_png_to, RgbaImage};
+use crate::png_meta::read_png_meta;
+
+/// 由像素估计的Alpha类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlphaMode {
+    /// 颜色已乘以Alpha（每个通道都不超过Alpha）
+    Premultiplied,
+    /// 颜色未乘以Alpha
+    Straight,
+    /// 半透明像素太少或证据互相矛盾
+    Unknown,
+}
+
+impl AlphaMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            AlphaMode::Premultiplied => "预乘",
+            AlphaMode::Straight => "非预乘",
+            AlphaMode::Unknown => "无法判断",
+        }
+    }
+}
+
+/// 页面PNG的预乘估计
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PmaEstimate {
+    pub mode: AlphaMode,
+    /// 参与判断的非不透明像素数（不含全透明的黑色像素）
+    pub samples: u64,
+    /// 颜色通道大于Alpha的像素数，预乘图像中不会出现
+    pub exceeding: u64,
+}
+
+/// 参与判断的像素少于此数时无法判断（几乎不透明的页面两种方式显示相同）
+const MIN_SAMPLES: u64 = 64;
+/// 允许的舍入误差
+const TOLERANCE: u8 = 2;
+/// 超出Alpha的像素比例低于此值视为预乘，高于`STRAIGHT_RATIO`视为非预乘
+const PREMULTIPLIED_RATIO: f64 = 0.001;
+const STRAIGHT_RATIO: f64 = 0.01;
+
+/// 根据像素估计图像是否预乘：预乘图像中每个通道都不超过Alpha，全透明像素必为黑色
+pub fn estimate_pma(image: &RgbaImage) -> PmaEstimate {
+    let (mut samples, mut exceeding) = (0u64, 0u64);
+    for pixel in image.pixels.chunks_exact(4) {
+        let (color, alpha) = (pixel[0].max(pixel[1]).max(pixel[2]), pixel[3]);
+        if alpha == 255 || (alpha == 0 && color == 0) {
+            continue;
+        }
+        samples += 1;
+        if color > alpha.saturating_add(TOLERANCE) {
+            exceeding += 1;
+        }
+    }
+
+    let mode = if samples < MIN_SAMPLES {
+        AlphaMode::Unknown
+    } else {
+        let ratio = exceeding as f64 / samples as f64;
+        if ratio < PREMULTIPLIED_RATIO {
+            AlphaMode::Premultiplied
+        } else if ratio > STRAIGHT_RATIO {
+            AlphaMode::Straight
+        } else {
+            AlphaMode::Unknown
+        }
+    };
+    PmaEstimate { mode, samples, exceeding }
+}
+
+pub fn premultiply(image: &mut RgbaImage) {
+    for pixel in image.pixels.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u32;
+        for channel in &mut pixel[..3] {
+            *channel = ((*channel as u32 * alpha + 127) / 255) as u8;
+        }
+    }
+}
+
+/// 取消预乘；Alpha越低精度损失越大，全透明像素的颜色无法恢复
+pub fn unpremultiply(image: &mut RgbaImage) {
+    for pixel in image.pixels.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u32;
+        if alpha == 0 {
+            pixel[..3].fill(0);
+            continue;
+        }
+        for channel in &mut pixel[..3] {
+            *channel = ((*channel as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+        }
+    }
+}
+
+/// Atlas中一个页面的声明与像素估计
+#[derive(Debug, Serialize)]
+pub struct PagePma {
+    pub page: String,
+    pub png: PathBuf,
+    /// Atlas的设置，3.x为None（由查看器设置决定）
+    pub declared: Option<bool>,
+    /// PNG缺失或无法解码时为None
+    pub estimate: Option<PmaEstimate>,
+}
+
+impl PagePma {
+    /// 声明与像素不一致时的说明
+    pub fn mismatch(&self) -> Option<String> {
+        let mode = self.estimate?.mode;
+        match (self.declared, mode) {
+            (Some(true), AlphaMode::Straight) => Some(format!(
+                "页面 {} 标记为 pma:true，但像素未预乘，半透明边缘会发亮", self.page)),
+            (Some(false), AlphaMode::Premultiplied) => Some(format!(
+                "页面 {} 未标记 pma:true，但像素已预乘，半透明边缘会出现黑边", self.page)),
+            _ => None,
+        }
+    }
+
+    /// 3.x Atlas没有预乘标记，像素已预乘时需要在查看器中开启预乘
+    pub fn viewer_hint(&self) -> Option<String> {
+        match (self.declared, self.estimate?.mode) {
+            (None, AlphaMode::Premultiplied) => Some(format!(
+                "页面 {} 的像素已预乘，3.x Atlas没有pma标记，请在查看器中开启预乘Alpha", self.page)),
+            _ => None,
+        }
+    }
+}
+
+/// 估计Atlas各页面PNG（与Atlas同目录、按页面名命名）是否预乘
+pub fn check_atlas_pma(atlas: &Atlas) -> Vec<PagePma> {
+    let dir = atlas.path.parent().unwrap_or(Path::new("."));
+    atlas.pages.iter()
+        .map(|page| {
+            let png = dir.join(&page.name);
+            let estimate = decode_rgba(&png).ok().map(|image| estimate_pma(&image));
+            PagePma {
+                page: page.name.clone(),
+                png,
+                declared: atlas.page_pma(page),
+                estimate,
+            }
+        })
+        .collect()
+}
+
+/// 页面的处理结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageStatus {
+    /// 已转换，原PNG保存为`.orig`
+    Converted,
+    /// 像素已是目标方式而跳过
+    AlreadyTarget,
+    /// 无法判断像素是否预乘而跳过，避免重复预乘或取消预乘
+    Unknown,
+}
+
+/// 转换一个页面后的结果
+pub struct PageConversion {
+    pub page: String,
+    pub status: PageStatus,
+    /// 原PNG的备份（已存在的备份不会覆盖，始终是第一次转换前的文件）
+    pub backup: Option<PathBuf>,
+    /// 原PNG不是RGBA8时的格式（如 `indexed8`），转换后统一为RGBA8
+    pub original_format: Option<String>,
+    /// 是否修改了Atlas中的pma标记
+    pub flag_updated: bool,
+}
+
+/// 将Atlas页面PNG预乘（`premultiplied`为true）或取消预乘，并将4.x Atlas的pma标记改为一致
+///
+/// `pages`为空时处理全部页面；像素已是目标方式或无法判断的页面会跳过，除非`force`。
+/// 转换后的PNG先写入临时文件，成功后才替换原文件，原文件另存为`<页面名>.orig`。
+pub fn convert_pages(atlas: &Atlas, pages: &[String], premultiplied: bool, force: bool) -> Result<Vec<PageConversion>> {
+    for name in pages {
+        if !atlas.pages.iter().any(|page| page.name == *name) {
+            anyhow::bail!("Atlas中没有页面: {}", name);
+        }
+    }
+    let target = if premultiplied { AlphaMode::Premultiplied } else { AlphaMode::Straight };
+    let dir = atlas.path.parent().unwrap_or(Path::new("."));
+    let mut content = fs::read_to_string(&atlas.path).context("无法读取Atlas文件")?;
+    let mut results = Vec::new();
+
+    for page in atlas.pages.iter().filter(|page| pages.is_empty() || pages.contains(&page.name)) {
+        let png = dir.join(&page.name);
+        let mut image = decode_rgba(&png).with_context(|| format!("无法读取页面 {} 的PNG: {:?}", page.name, png))?;
+        let status = match estimate_pma(&image).mode {
+            _ if force => PageStatus::Converted,
+            mode if mode == target => PageStatus::AlreadyTarget,
+            AlphaMode::Unknown => PageStatus::Unknown,
+            _ => PageStatus::Converted,
+        };
+        let mut backup = None;
+        let mut original_format = None;
+        if status == PageStatus::Converted {
+            let meta = read_png_meta(&png, false)?;
+            if meta.format_key() != "rgba8" {
+                original_format = Some(meta.format_key());
+            }
+            if premultiplied {
+                premultiply(&mut image);
+            } else {
+                unpremultiply(&mut image);
+            }
+            backup = Some(replace_png(&image, &png)
+                .with_context(|| format!("无法写入页面 {} 的PNG: {:?}", page.name, png))?);
+        }
+
+        // 跳过无法判断的页面时不改标记，以免与像素不一致
+        let flag_updated = status != PageStatus::Unknown
+            && atlas.format == AtlasFormat::Modern
+            && atlas.page_pma(page) != Some(premultiplied);
+        if flag_updated {
+            content = set_page_pma(&content, &page.name, premultiplied)?;
+        }
+        results.push(PageConversion { page: page.name.clone(), status, backup, original_format, flag_updated });
+    }
+
+    if results.iter().any(|result| result.flag_updated) {
+        fs::write(&atlas.path, content).context("无法写入Atlas文件")?;
+    }
+    Ok(results)
+}
+
+/// 先写入同目录的临时文件，成功后备份原文件并替换，返回备份路径
+fn replace_png(image: &RgbaImage, png: &Path) -> Result<PathBuf> {
+    let with_suffix = |suffix: &str| {
+        let mut name = png.as_os_str().to_owned();
+        name.push(suffix);
+        PathBuf::from(name)
+    };
+    let temp = with_suffix(".tmp");
+    let backup = with_suffix(".orig");
+
+    let written = File::create(&temp).map_err(anyhow::Error::from).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        encode_png_to(image, &mut writer)?;
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        Ok(())
+    });
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+
+    if !backup.exists() {
+        fs::copy(png, &backup).context("无法备份原PNG")?;
+    }
+    fs::rename(&temp, png).context("无法替换原PNG")?;
+    Ok(backup)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 每个像素相同的图像
+    fn image(pixel: [u8; 4], count: usize) -> RgbaImage {
+        RgbaImage { width: count as u32, height: 1, pixels: pixel.repeat(count) }
+    }
+
+    #[test]
+    fn estimate_straight_and_premultiplied() {
+        let straight = image([200, 100, 50, 128], 100);
+        let estimate = estimate_pma(&straight);
+        assert_eq!(estimate.mode, AlphaMode::Straight);
+        assert_eq!((estimate.samples, estimate.exceeding), (100, 100));
+
+        let premultiplied = image([100, 50, 25, 128], 100);
+        assert_eq!(estimate_pma(&premultiplied).mode, AlphaMode::Premultiplied);
+    }
+
+    #[test]
+    fn estimate_unknown_with_few_samples() {
+        // 不透明像素与全透明黑色像素不参与判断
+        let mut pixels = [255, 255, 255, 255].repeat(1000);
+        pixels.extend([0, 0, 0, 0].repeat(1000));
+        pixels.extend([200, 100, 50, 128].repeat((MIN_SAMPLES - 1) as usize));
+        let mixed = RgbaImage { width: pixels.len() as u32 / 4, height: 1, pixels };
+        let estimate = estimate_pma(&mixed);
+        assert_eq!(estimate.mode, AlphaMode::Unknown);
+        assert_eq!(estimate.samples, MIN_SAMPLES - 1);
+    }
+
+    #[test]
+    fn premultiply_and_unpremultiply() {
+        let mut pixels = image([200, 100, 0, 128], 1);
+        premultiply(&mut pixels);
+        assert_eq!(pixels.pixels, [100, 50, 0, 128]);
+
+        unpremultiply(&mut pixels);
+        assert_eq!(pixels.pixels, [199, 100, 0, 128]);
+
+        let mut transparent = image([10, 20, 30, 0], 1);
+        unpremultiply(&mut transparent);
+        assert_eq!(transparent.pixels, [0, 0, 0, 0]);
+
+        let mut opaque = image([10, 20, 30, 255], 1);
+        premultiply(&mut opaque);
+        assert_eq!(opaque.pixels, [10, 20, 30, 255]);
+    }
+}
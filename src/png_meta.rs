@@ -0,0 +1,212 @@
+use anyhow::{Result, Context};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::file_operations::collect_files_with_extension;
+use crate::imaging::decode_rgba;
+use crate::progress::Reporter;
+
+/// IHDR中的颜色类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PngColor {
+    Grayscale,
+    GrayscaleAlpha,
+    Rgb,
+    Rgba,
+    Indexed,
+}
+
+impl PngColor {
+    /// 文件名与CSV中使用的短名称
+    pub fn key(self) -> &'static str {
+        match self {
+            PngColor::Grayscale => "gray",
+            PngColor::GrayscaleAlpha => "graya",
+            PngColor::Rgb => "rgb",
+            PngColor::Rgba => "rgba",
+            PngColor::Indexed => "indexed",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PngColor::Grayscale => "灰度",
+            PngColor::GrayscaleAlpha => "灰度+Alpha",
+            PngColor::Rgb => "RGB",
+            PngColor::Rgba => "RGBA",
+            PngColor::Indexed => "调色板",
+        }
+    }
+}
+
+/// 由像素判断的透明情况
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlphaUsage {
+    /// 所有像素都不透明（多为背景图等类似JPEG的图片）
+    Opaque,
+    /// 存在半透明或透明像素（Spine的Atlas页面通常如此）
+    HasAlpha,
+    /// 可能透明，但未解码像素检查
+    Unchecked,
+}
+
+impl AlphaUsage {
+    pub fn key(self) -> &'static str {
+        match self {
+            AlphaUsage::Opaque => "opaque",
+            AlphaUsage::HasAlpha => "alpha",
+            AlphaUsage::Unchecked => "unchecked",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AlphaUsage::Opaque => "不透明",
+            AlphaUsage::HasAlpha => "有透明",
+            AlphaUsage::Unchecked => "未检查",
+        }
+    }
+}
+
+/// PNG的格式信息与透明情况
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PngMeta {
+    pub width: u32,
+    pub height: u32,
+    pub color: PngColor,
+    pub bit_depth: u8,
+    pub interlaced: bool,
+    /// 是否有tRNS chunk（无Alpha通道的图像通过它声明透明色）
+    pub has_trns: bool,
+    pub alpha: AlphaUsage,
+}
+
+impl PngMeta {
+    /// 格式短名称，如 `rgba8`、`indexed4`
+    pub fn format_key(&self) -> String {
+        format!("{}{}", self.color.key(), self.bit_depth)
+    }
+
+    /// 终端显示用的格式说明，如 `RGBA 8位 隔行`
+    pub fn format_label(&self) -> String {
+        let mut label = format!("{} {}位", self.color.label(), self.bit_depth);
+        if self.has_trns {
+            label.push_str(" tRNS");
+        }
+        if self.interlaced {
+            label.push_str(" 隔行");
+        }
+        label
+    }
+
+    /// 重命名模板中的元数据占位符
+    pub fn placeholder(&self, name: &str) -> Option<String> {
+        let value = match name {
+            "color" => self.color.key().to_string(),
+            "depth" => self.bit_depth.to_string(),
+            "format" => self.format_key(),
+            "interlace" => if self.interlaced { "interlaced" } else { "plain" }.to_string(),
+            "trns" => if self.has_trns { "trns" } else { "notrns" }.to_string(),
+            "alpha" => self.alpha.key().to_string(),
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+/// 重命名模板中可用的元数据占位符
+pub const META_PLACEHOLDERS: [&str; 6] = ["color", "depth", "format", "interlace", "trns", "alpha"];
+
+/// 读取PNG的头部信息；`check_alpha` 为真且图像可能透明时，解码像素判断是否真的有透明像素
+pub fn read_png_meta(file_path: &Path, check_alpha: bool) -> Result<PngMeta> {
+    let file = File::open(file_path).context("无法打开PNG文件")?;
+    let reader = png::Decoder::new(file).read_info().context("无法读取PNG头部")?;
+    let info = reader.info();
+
+    let color = match info.color_type {
+        png::ColorType::Grayscale => PngColor::Grayscale,
+        png::ColorType::GrayscaleAlpha => PngColor::GrayscaleAlpha,
+        png::ColorType::Rgb => PngColor::Rgb,
+        png::ColorType::Rgba => PngColor::Rgba,
+        png::ColorType::Indexed => PngColor::Indexed,
+    };
+    let has_trns = info.trns.is_some();
+    let (width, height, bit_depth, interlaced) = (info.width, info.height, info.bit_depth as u8, info.interlaced);
+    drop(reader);
+
+    // 没有Alpha通道也没有tRNS时不可能透明，无需解码
+    let may_be_transparent = has_trns || matches!(color, PngColor::Rgba | PngColor::GrayscaleAlpha);
+    let alpha = if !may_be_transparent {
+        AlphaUsage::Opaque
+    } else if check_alpha {
+        let image = decode_rgba(file_path)?;
+        if image.pixels.chunks_exact(4).all(|pixel| pixel[3] == 255) {
+            AlphaUsage::Opaque
+        } else {
+            AlphaUsage::HasAlpha
+        }
+    } else {
+        AlphaUsage::Unchecked
+    };
+
+    Ok(PngMeta { width, height, color, bit_depth, interlaced, has_trns, alpha })
+}
+
+/// 目录中PNG格式的统计
+#[derive(Debug, Default, Serialize)]
+pub struct PngSummary {
+    pub total: usize,
+    /// 格式短名称（如 `rgba8`） -> 数量
+    pub formats: BTreeMap<String, usize>,
+    pub interlaced: usize,
+    pub with_trns: usize,
+    pub opaque: usize,
+    pub with_alpha: usize,
+    /// 可能透明但未解码检查的PNG
+    pub unchecked: usize,
+    /// 无法读取的PNG
+    pub unreadable: Vec<PathBuf>,
+}
+
+/// 并行读取目录下全部PNG的元数据并汇总，`check_alpha` 为真时才解码像素检查透明情况
+pub fn summarize_pngs(dir: &Path, check_alpha: bool, reporter: &dyn Reporter) -> PngSummary {
+    let files = collect_files_with_extension(dir, "png");
+
+    reporter.started("读取PNG格式", files.len());
+
+    let metas: Vec<(&PathBuf, Result<PngMeta>)> = files.par_iter()
+        .map(|path| {
+            let meta = read_png_meta(path, check_alpha);
+            match &meta {
+                Ok(_) => reporter.processed(path, None),
+                Err(e) => reporter.error(path, &format!("{:#}", e)),
+            }
+            (path, meta)
+        })
+        .collect();
+
+    reporter.finished("读取PNG格式", "PNG格式读取完成");
+
+    let mut summary = PngSummary { total: files.len(), ..Default::default() };
+    for (path, meta) in metas {
+        let Ok(meta) = meta else {
+            summary.unreadable.push(path.clone());
+            continue;
+        };
+        *summary.formats.entry(meta.format_key()).or_default() += 1;
+        summary.interlaced += meta.interlaced as usize;
+        summary.with_trns += meta.has_trns as usize;
+        match meta.alpha {
+            AlphaUsage::Opaque => summary.opaque += 1,
+            AlphaUsage::HasAlpha => summary.with_alpha += 1,
+            AlphaUsage::Unchecked => summary.unchecked += 1,
+        }
+    }
+    summary.unreadable.sort();
+    summary
+}
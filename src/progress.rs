@@ -0,0 +1,171 @@
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use clap::ValueEnum;
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+
+/// 长时间操作的进度事件
+///
+/// 一次只进行一项任务：`Started`之后每个文件对应一个`Processed`、`Skipped`或`Error`，最后是`Finished`。
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    /// 开始一项任务，`total`为要处理的文件数
+    Started { task: &'a str, total: usize },
+    /// 处理完一个文件，`detail`为需要展示的结果，例如改名的 "a -> b"
+    Processed {
+        path: &'a Path,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        detail: Option<&'a str>,
+    },
+    /// 无需处理而跳过的文件
+    Skipped { path: &'a Path, reason: &'a str },
+    /// 处理文件时出错，出错的文件同时记在结果的`failures`中
+    Error { path: &'a Path, error: &'a str },
+    /// 任务结束
+    Finished { task: &'a str, message: &'a str },
+}
+
+/// 接收进度事件；事件可能来自多个线程
+pub trait Reporter: Sync {
+    fn report(&self, event: Event<'_>);
+
+    fn started(&self, task: &str, total: usize) {
+        self.report(Event::Started { task, total });
+    }
+
+    fn processed(&self, path: &Path, detail: Option<&str>) {
+        self.report(Event::Processed { path, detail });
+    }
+
+    fn skipped(&self, path: &Path, reason: &str) {
+        self.report(Event::Skipped { path, reason });
+    }
+
+    fn error(&self, path: &Path, error: &str) {
+        self.report(Event::Error { path, error });
+    }
+
+    fn finished(&self, task: &str, message: &str) {
+        self.report(Event::Finished { task, message });
+    }
+}
+
+/// 进度的显示方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProgressMode {
+    /// 进度条，改名与错误显示在进度条上方
+    Bar,
+    /// 不显示进度
+    Quiet,
+    /// 逐条记录每个事件
+    Verbose,
+    /// 每行一个JSON事件（NDJSON），供图形界面读取
+    Json,
+}
+
+impl ProgressMode {
+    /// 对应的报告器，输出到标准错误，不影响标准输出中的结果
+    pub fn reporter(self) -> Box<dyn Reporter + Send> {
+        match self {
+            ProgressMode::Bar => Box::new(BarReporter::default()),
+            ProgressMode::Quiet => Box::new(QuietReporter),
+            ProgressMode::Verbose => Box::new(VerboseReporter),
+            ProgressMode::Json => Box::new(JsonReporter::new(std::io::stderr())),
+        }
+    }
+}
+
+/// indicatif进度条，每项任务一个
+#[derive(Default)]
+pub struct BarReporter {
+    bar: Mutex<Option<ProgressBar>>,
+}
+
+impl BarReporter {
+    fn with_bar(&self, action: impl FnOnce(&ProgressBar)) {
+        if let Some(bar) = self.bar.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            action(bar);
+        }
+    }
+}
+
+impl Reporter for BarReporter {
+    fn report(&self, event: Event<'_>) {
+        match event {
+            Event::Started { task, total } => {
+                let bar = ProgressBar::new(total as u64);
+                bar.set_style(
+                    ProgressStyle::default_bar()
+                        .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}")
+                        .unwrap()
+                        .progress_chars("#>-")
+                );
+                bar.set_message(format!("{}中...", task));
+                *self.bar.lock().unwrap_or_else(|e| e.into_inner()) = Some(bar);
+            }
+            Event::Processed { detail, .. } => self.with_bar(|bar| {
+                if let Some(detail) = detail {
+                    bar.println(format!("✓ {}", detail));
+                }
+                bar.inc(1);
+            }),
+            Event::Skipped { .. } => self.with_bar(|bar| bar.inc(1)),
+            Event::Error { path, error } => self.with_bar(|bar| {
+                bar.println(format!("处理文件 {:?} 时出错: {}", path, error));
+                bar.inc(1);
+            }),
+            Event::Finished { message, .. } => {
+                if let Some(bar) = self.bar.lock().unwrap_or_else(|e| e.into_inner()).take() {
+                    bar.finish_with_message(message.to_string());
+                }
+            }
+        }
+    }
+}
+
+/// 不显示任何进度
+pub struct QuietReporter;
+
+impl Reporter for QuietReporter {
+    fn report(&self, _event: Event<'_>) {}
+}
+
+/// 每个事件一行日志，包括跳过的文件
+pub struct VerboseReporter;
+
+impl Reporter for VerboseReporter {
+    fn report(&self, event: Event<'_>) {
+        match event {
+            Event::Started { task, total } => eprintln!("▶ {}：{} 个文件", task, total),
+            Event::Processed { path, detail: Some(detail) } => eprintln!("✓ {} ({})", detail, path.display()),
+            Event::Processed { path, detail: None } => eprintln!("✓ {}", path.display()),
+            Event::Skipped { path, reason } => eprintln!("- {}：{}", path.display(), reason),
+            Event::Error { path, error } => eprintln!("✗ {}：{}", path.display(), error),
+            Event::Finished { message, .. } => eprintln!("■ {}", message),
+        }
+    }
+}
+
+/// 每行一个JSON事件，例如 `{"event":"processed","path":"udp/a.png"}`
+pub struct JsonReporter<W> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write> JsonReporter<W> {
+    pub fn new(writer: W) -> Self {
+        JsonReporter { writer: Mutex::new(writer) }
+    }
+}
+
+impl<W: Write + Send> Reporter for JsonReporter<W> {
+    fn report(&self, event: Event<'_>) {
+        let Ok(line) = serde_json::to_string(&event) else {
+            return;
+        };
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        // 读取事件的一方已退出时不影响操作本身
+        let _ = writeln!(writer, "{}", line).and_then(|_| writer.flush());
+    }
+}
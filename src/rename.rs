@@ -0,0 +1,298 @@
+use anyhow::Result;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use crate::atlas::{parse_atlas_dir, parse_atlas_file, Atlas};
+use crate::file_operations::{collect_files_with_extension, file_hash, get_png_dimensions, NameReservations, RenamePlan};
+use crate::matching::RegionIndex;
+use crate::png_meta::{read_png_meta, META_PLACEHOLDERS};
+use crate::progress::Reporter;
+use crate::sidecar::Rename;
+use crate::skel::{parse_skel_file, Skeleton};
+use crate::snapshot::AssetKind;
+use crate::template::NameTemplate;
+use crate::workspace::LoadFailure;
+
+/// PNG重命名模板的默认值（按尺寸命名）
+pub const DEFAULT_PNG_TEMPLATE: &str = "size_{w}x{h}";
+/// Atlas按内容命名：页面数、主页面尺寸与最常见的区域名前缀，保留内容hash以便追溯
+pub const DEFAULT_ATLAS_TEMPLATE: &str = "atlas_{pages}pages_{main_page}_{prefix}_{hash8}";
+/// Skel按内容命名：主要皮肤、动画与骨骼数
+pub const DEFAULT_SKEL_TEMPLATE: &str = "skel_{skin}_{anims}_{bones}bones_{hash8}";
+
+/// `{anims}`中最多列出的动画数
+const MAX_LISTED_ANIMATIONS: usize = 3;
+
+/// 某类资源的默认重命名模板
+pub fn default_template(kind: AssetKind) -> &'static str {
+    match kind {
+        AssetKind::Png => DEFAULT_PNG_TEMPLATE,
+        AssetKind::Atlas => DEFAULT_ATLAS_TEMPLATE,
+        AssetKind::Skel => DEFAULT_SKEL_TEMPLATE,
+    }
+}
+
+/// 各类型都可用的占位符
+const COMMON_PLACEHOLDERS: [&str; 4] = ["orig", "parent", "type", "hash8"];
+
+/// 某类资源的重命名模板可用的占位符
+pub fn available_placeholders(kind: AssetKind) -> Vec<&'static str> {
+    let mut names = COMMON_PLACEHOLDERS.to_vec();
+    match kind {
+        AssetKind::Png => {
+            names.extend(["w", "h", "atlas_page"]);
+            names.extend(META_PLACEHOLDERS);
+        }
+        AssetKind::Atlas => names.extend(["w", "h", "atlas_page", "pages", "regions", "main_page", "prefix"]),
+        AssetKind::Skel => names.extend(["version", "bones", "slots", "skins", "animations", "atlas_page", "skin", "anims"]),
+    }
+    names
+}
+
+/// 解析某类资源的重命名模板（不含扩展名）
+pub fn parse_template(kind: AssetKind, source: &str) -> Result<NameTemplate> {
+    NameTemplate::parse(source, &available_placeholders(kind))
+}
+
+/// 计划按模板重命名目录下某类资源，已是目标名称的文件不列入计划
+///
+/// `{atlas_page}`需要读取`atlas_dir`中的Atlas：PNG取尺寸相同的页面名，Skel取能完整解析其附件的Atlas的第一个页面名。
+pub fn plan_template_renames(dir_path: &Path, kind: AssetKind, template: &NameTemplate, atlas_dir: &Path, reporter: &dyn Reporter) -> RenamePlan {
+    let mut files = collect_files_with_extension(dir_path, kind.extension());
+    files.sort();
+    let atlases = if kind != AssetKind::Atlas && template.uses_any(&["atlas_page"]) {
+        parse_atlas_dir(atlas_dir)
+    } else {
+        Vec::new()
+    };
+    let pages = PageLookup::new(&atlases);
+
+    reporter.started(&format!("读取{}文件", kind.label()), files.len());
+
+    let mut plan = RenamePlan::default();
+    let mut reservations = NameReservations::default();
+    for file_path in files {
+        match target_name(&file_path, kind, template, &pages) {
+            Ok(stem) => {
+                let dir = file_path.parent().unwrap_or(Path::new("."));
+                let to = reservations.reserve_for(Some(&file_path), dir, &stem, kind.extension());
+                if to != file_path {
+                    reporter.processed(&file_path, None);
+                    plan.renames.push(Rename { from: file_path, to });
+                } else {
+                    reporter.skipped(&file_path, "已是目标名称");
+                }
+            }
+            Err(e) => {
+                let error = format!("{:#}", e);
+                reporter.error(&file_path, &error);
+                plan.failures.push(LoadFailure { path: file_path, error });
+            }
+        }
+    }
+
+    reporter.finished(&format!("读取{}文件", kind.label()), &format!("{}文件读取完成", kind.label()));
+    plan
+}
+
+/// 由尺寸或附件查找Atlas页面名
+struct PageLookup<'a> {
+    atlases: &'a [Atlas],
+    /// 页面尺寸 -> 该尺寸的页面名（不含扩展名）
+    by_size: HashMap<(u32, u32), BTreeSet<String>>,
+    regions: RegionIndex,
+}
+
+impl<'a> PageLookup<'a> {
+    fn new(atlases: &'a [Atlas]) -> Self {
+        let mut by_size: HashMap<(u32, u32), BTreeSet<String>> = HashMap::new();
+        for atlas in atlases {
+            for page in &atlas.pages {
+                by_size.entry((page.width, page.height)).or_default().insert(page_stem(&page.name));
+            }
+        }
+        PageLookup { atlases, by_size, regions: RegionIndex::new(atlases) }
+    }
+
+    /// 尺寸相同的页面只有一个名称时返回该名称
+    fn for_size(&self, width: u32, height: u32) -> Option<String> {
+        match self.by_size.get(&(width, height)) {
+            Some(names) if names.len() == 1 => names.iter().next().cloned(),
+            _ => None,
+        }
+    }
+}
+
+/// 出现最多的名称前缀（第一个`_`、`-`或`/`之前的部分），次数相同时取字母序靠前的
+fn common_prefix<'a>(names: impl Iterator<Item = &'a str>) -> String {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for name in names {
+        let prefix = name.split(['_', '-', '/']).next().unwrap_or(name);
+        if !prefix.is_empty() {
+            *counts.entry(prefix).or_default() += 1;
+        }
+    }
+    counts.into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(a.0)))
+        .map(|(prefix, _)| prefix.to_string())
+        .unwrap_or_else(|| "noregion".to_string())
+}
+
+/// 附件最多的皮肤；default之外还有皮肤时优先取其他皮肤，它们才能区分角色的不同外观
+fn dominant_skin(skeleton: &Skeleton) -> String {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for attachment in &skeleton.attachments {
+        *counts.entry(attachment.skin.as_str()).or_default() += 1;
+    }
+    let named = counts.iter()
+        .filter(|(skin, _)| **skin != "default")
+        .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+        .map(|(skin, _)| skin.to_string());
+    named.unwrap_or_else(|| "default".to_string())
+}
+
+/// 前几个动画名用`-`连接，其余以`+数量`表示，例如 `idle-attack-hit+4`
+fn animation_set(animations: &[String]) -> String {
+    if animations.is_empty() {
+        return "noanim".to_string();
+    }
+    let listed: Vec<String> = animations.iter()
+        .take(MAX_LISTED_ANIMATIONS)
+        .map(|name| name.split_whitespace().collect::<Vec<_>>().join("_"))
+        .collect();
+    let mut set = listed.join("-");
+    if animations.len() > MAX_LISTED_ANIMATIONS {
+        set.push_str(&format!("+{}", animations.len() - MAX_LISTED_ANIMATIONS));
+    }
+    set
+}
+
+fn page_stem(name: &str) -> String {
+    Path::new(name).file_stem().and_then(|s| s.to_str()).unwrap_or(name).to_string()
+}
+
+/// 计算模板用到的占位符的值，需要解码或解析的内容只在用到时读取
+fn placeholder_values(file_path: &Path, kind: AssetKind, template: &NameTemplate, pages: &PageLookup) -> Result<HashMap<&'static str, String>> {
+    let mut values: HashMap<&'static str, String> = HashMap::new();
+    let stem = file_path.file_stem().and_then(|s| s.to_str()).unwrap_or("").to_string();
+    values.insert("orig", stem);
+    let parent = file_path.parent().and_then(|p| p.file_name()).and_then(|n| n.to_str()).unwrap_or("");
+    values.insert("parent", parent.to_string());
+    values.insert("type", kind.extension().to_string());
+    if template.uses_any(&["hash8"]) {
+        values.insert("hash8", file_hash(file_path)?[..8].to_string());
+    }
+
+    match kind {
+        AssetKind::Png => {
+            let (width, height) = get_png_dimensions(file_path)?;
+            values.insert("w", width.to_string());
+            values.insert("h", height.to_string());
+            if let Some(page) = pages.for_size(width, height) {
+                values.insert("atlas_page", page);
+            }
+            // 只有 {alpha} 需要解码像素
+            if template.uses_any(&META_PLACEHOLDERS) {
+                let meta = read_png_meta(file_path, template.uses_any(&["alpha"]))?;
+                for name in META_PLACEHOLDERS {
+                    if let Some(value) = meta.placeholder(name) {
+                        values.insert(name, value);
+                    }
+                }
+            }
+        }
+        AssetKind::Atlas => {
+            let atlas = parse_atlas_file(file_path)?;
+            if let Some(page) = atlas.pages.first() {
+                values.insert("w", page.width.to_string());
+                values.insert("h", page.height.to_string());
+                values.insert("atlas_page", page_stem(&page.name));
+            }
+            values.insert("pages", atlas.pages.len().to_string());
+            values.insert("regions", atlas.regions.len().to_string());
+            if let Some(page) = atlas.pages.iter().max_by_key(|page| page.width as u64 * page.height as u64) {
+                values.insert("main_page", format!("{}x{}", page.width, page.height));
+            }
+            values.insert("prefix", common_prefix(atlas.regions.iter().map(|region| region.name.as_str())));
+        }
+        AssetKind::Skel => {
+            let skeleton = parse_skel_file(file_path)?;
+            values.insert("version", skeleton.version.clone());
+            values.insert("bones", skeleton.bones.len().to_string());
+            values.insert("slots", skeleton.slots.len().to_string());
+            values.insert("skins", skeleton.skins.len().to_string());
+            values.insert("animations", skeleton.animations.len().to_string());
+            values.insert("skin", dominant_skin(&skeleton));
+            values.insert("anims", animation_set(&skeleton.animations));
+            let resolving = pages.regions.resolving_atlases(&skeleton);
+            if let [index] = resolving[..] {
+                if let Some(page) = pages.atlases[index].pages.first() {
+                    values.insert("atlas_page", page_stem(&page.name));
+                }
+            }
+        }
+    }
+    Ok(values)
+}
+
+/// 按模板计算文件的新名称（不含扩展名）
+fn target_name(file_path: &Path, kind: AssetKind, template: &NameTemplate, pages: &PageLookup) -> Result<String> {
+    let values = placeholder_values(file_path, kind, template, pages)?;
+    if template.uses_any(&["atlas_page"]) && !values.contains_key("atlas_page") {
+        anyhow::bail!("找不到唯一对应的Atlas页面，无法取得 {{atlas_page}}");
+    }
+    template.render(|name| values.get(name).cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imaging::{encode_png, RgbaImage};
+    use crate::progress::QuietReporter;
+    use std::path::PathBuf;
+
+    fn write_png(path: &Path, width: u32, height: u32) {
+        let image = RgbaImage { width, height, pixels: vec![0; (width * height * 4) as usize] };
+        encode_png(&image, path).unwrap();
+    }
+
+    #[test]
+    fn same_rendered_name_gets_distinct_suffixes() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.png", "b.png", "c.png"] {
+            write_png(&dir.path().join(name), 2, 3);
+        }
+        write_png(&dir.path().join("size_4x4.png"), 4, 4);
+
+        let template = parse_template(AssetKind::Png, DEFAULT_PNG_TEMPLATE).unwrap();
+        let plan = plan_template_renames(dir.path(), AssetKind::Png, &template, dir.path(), &QuietReporter);
+        assert!(plan.failures.is_empty());
+
+        let renames: Vec<(PathBuf, PathBuf)> = plan.renames.into_iter()
+            .map(|rename| (rename.from, rename.to))
+            .collect();
+        let expected: Vec<(PathBuf, PathBuf)> = [("a", "size_2x3"), ("b", "size_2x3_1"), ("c", "size_2x3_2")].iter()
+            .map(|(from, to)| (dir.path().join(format!("{}.png", from)), dir.path().join(format!("{}.png", to))))
+            .collect();
+        // size_4x4.png 已是目标名称，不列入计划
+        assert_eq!(renames, expected);
+    }
+
+    #[test]
+    fn missing_atlas_page_is_a_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        write_png(&dir.path().join("a.png"), 2, 2);
+
+        let template = parse_template(AssetKind::Png, "{atlas_page}").unwrap();
+        let plan = plan_template_renames(dir.path(), AssetKind::Png, &template, dir.path(), &QuietReporter);
+        assert!(plan.renames.is_empty());
+        assert_eq!(plan.failures.len(), 1);
+        assert!(plan.failures[0].error.contains("{atlas_page}"), "{}", plan.failures[0].error);
+    }
+
+    #[test]
+    fn unknown_placeholder_lists_available() {
+        let error = parse_template(AssetKind::Skel, "{w}").unwrap_err().to_string();
+        assert!(error.contains("未知的占位符 {w}") && error.contains("{bones}"), "{}", error);
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::matching::RegionIndex;
+use crate::png_meta::{read_png_meta, PngMeta};
+use crate::workspace::{LoadFailure, Workspace};
+
+/// 还原进度报告：交叉比对PNG、Atlas页面与Skel附件
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub png_count: usize,
+    pub atlas_count: usize,
+    pub skel_count: usize,
+    /// 尺寸与任何Atlas页面都不匹配的PNG
+    pub orphan_pngs: Vec<OrphanPng>,
+    /// 存在找不到同尺寸PNG页面的Atlas
+    pub incomplete_atlases: Vec<IncompleteAtlas>,
+    /// 没有被任何Skel引用的Atlas：既不能完整解析任何Skel，也不是任何Skel命中附件最多的Atlas
+    pub unreferenced_atlases: Vec<UnreferencedAtlas>,
+    /// 附件无法在任何Atlas中完整找到的Skel
+    pub unresolved_skels: Vec<UnresolvedSkel>,
+    /// 没有任何区域附件的Skel（例如只有骨骼或空皮肤），无法与Atlas匹配
+    pub skels_without_attachments: Vec<SkelWithoutAttachments>,
+    /// 无法读取或解析的文件
+    pub failures: Vec<LoadFailure>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrphanPng {
+    pub path: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    /// 格式与透明情况，无法解码时为None
+    pub meta: Option<PngMeta>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IncompleteAtlas {
+    pub path: PathBuf,
+    pub missing_pages: Vec<MissingPage>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MissingPage {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnreferencedAtlas {
+    pub path: PathBuf,
+    pub pages: usize,
+    pub regions: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnresolvedSkel {
+    pub path: PathBuf,
+    pub version: String,
+    pub attachments: usize,
+    /// 命中附件最多的Atlas
+    pub best_atlas: Option<PathBuf>,
+    /// 在最佳候选Atlas中找不到的附件
+    pub missing: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SkelWithoutAttachments {
+    pub path: PathBuf,
+    pub version: String,
+}
+
+/// 交叉比对工作区；`check_alpha`为真时解码未匹配PNG的像素判断是否含透明像素（较慢）
+pub fn build_report(workspace: &Workspace, check_alpha: bool) -> Report {
+    let png_sizes: HashSet<(u32, u32)> = workspace.pngs.iter()
+        .map(|png| (png.width, png.height))
+        .collect();
+    let page_sizes: HashSet<(u32, u32)> = workspace.atlases.iter()
+        .flat_map(|atlas| atlas.pages.iter().map(|page| (page.width, page.height)))
+        .collect();
+
+    // 只为未匹配的PNG读取格式：不透明的多为背景图，不必再找对应的Atlas
+    let mut orphan_pngs: Vec<OrphanPng> = workspace.pngs.par_iter()
+        .filter(|png| !page_sizes.contains(&(png.width, png.height)))
+        .map(|png| OrphanPng {
+            path: png.path.clone(),
+            width: png.width,
+            height: png.height,
+            meta: read_png_meta(&png.path, check_alpha).ok(),
+        })
+        .collect();
+    orphan_pngs.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut incomplete_atlases: Vec<IncompleteAtlas> = workspace.atlases.iter()
+        .filter_map(|atlas| {
+            let missing_pages: Vec<MissingPage> = atlas.pages.iter()
+                .filter(|page| !png_sizes.contains(&(page.width, page.height)))
+                .map(|page| MissingPage { name: page.name.clone(), width: page.width, height: page.height })
+                .collect();
+            if missing_pages.is_empty() {
+                None
+            } else {
+                Some(IncompleteAtlas { path: atlas.path.clone(), missing_pages })
+            }
+        })
+        .collect();
+    incomplete_atlases.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let index = RegionIndex::new(&workspace.atlases);
+    let mut referenced: HashSet<usize> = HashSet::new();
+    let mut unresolved_skels: Vec<UnresolvedSkel> = Vec::new();
+    let mut skels_without_attachments: Vec<SkelWithoutAttachments> = Vec::new();
+
+    for skeleton in &workspace.skeletons {
+        if skeleton.region_paths.is_empty() {
+            skels_without_attachments.push(SkelWithoutAttachments {
+                path: skeleton.path.clone(),
+                version: skeleton.version.clone(),
+            });
+            continue;
+        }
+
+        let resolving = index.resolving_atlases(skeleton);
+        if !resolving.is_empty() {
+            referenced.extend(resolving);
+            continue;
+        }
+
+        // 缺少个别附件的Skel仍引用命中最多的Atlas（与compat的配对方式相同）
+        let best_index = index.coverage(skeleton).first().map(|&(atlas, _)| atlas);
+        referenced.extend(best_index);
+        let best = best_index.map(|atlas| &workspace.atlases[atlas]);
+        let missing = match best {
+            Some(atlas) => {
+                let names: HashSet<&str> = atlas.regions.iter().map(|r| r.name.as_str()).collect();
+                skeleton.region_paths.iter()
+                    .filter(|path| !names.contains(path.as_str()))
+                    .cloned()
+                    .collect()
+            }
+            None => skeleton.region_paths.clone(),
+        };
+
+        unresolved_skels.push(UnresolvedSkel {
+            path: skeleton.path.clone(),
+            version: skeleton.version.clone(),
+            attachments: skeleton.region_paths.len(),
+            best_atlas: best.map(|atlas| atlas.path.clone()),
+            missing,
+        });
+    }
+    unresolved_skels.sort_by(|a, b| a.path.cmp(&b.path));
+    skels_without_attachments.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut unreferenced_atlases: Vec<UnreferencedAtlas> = workspace.atlases.iter()
+        .enumerate()
+        .filter(|(i, _)| !referenced.contains(i))
+        .map(|(_, atlas)| UnreferencedAtlas {
+            path: atlas.path.clone(),
+            pages: atlas.pages.len(),
+            regions: atlas.regions.len(),
+        })
+        .collect();
+    unreferenced_atlases.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Report {
+        png_count: workspace.pngs.len(),
+        atlas_count: workspace.atlases.len(),
+        skel_count: workspace.skeletons.len(),
+        orphan_pngs,
+        incomplete_atlases,
+        unreferenced_atlases,
+        unresolved_skels,
+        skels_without_attachments,
+        failures: workspace.failures.clone(),
+    }
+}
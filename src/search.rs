@@ -1,12 +1,124 @@
 use anyhow::{Result, Context};
+use std::collections::HashMap;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::fs;
 use walkdir::WalkDir;
 use rayon::prelude::*;
-use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+
+use crate::atlas::atlas_blocks;
+use crate::names::NameKind;
+use crate::pattern::{NamePattern, SearchScope};
+use crate::progress::Reporter;
+use crate::skel::{parse_skel, Skeleton};
+use crate::workspace::LoadFailure;
+
+/// 单个文件的搜索结果
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchMatch {
+    pub path: PathBuf,
+    /// 名称词典中的中文显示名称
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    /// 已还原角色目录中使用了该文件的角色
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restored: Option<String>,
+    /// 命中的搜索词
+    pub terms: Vec<String>,
+    /// 未命中的搜索词
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missing_terms: Vec<String>,
+    /// 匹配得分（0~1），为命中搜索词的权重之和占全部搜索词权重之和的比例
+    pub score: f64,
+    pub details: Vec<MatchDetail>,
+}
+
+impl SearchMatch {
+    /// 全部搜索词的数量
+    pub fn total_terms(&self) -> usize {
+        self.terms.len() + self.missing_terms.len()
+    }
+}
+
+/// 搜索词在文件中的一处命中
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchDetail {
+    pub term: String,
+    /// 命中的名称类别（Atlas为命中行所在的页面或区域）；仅在原始字节中命中时为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<NameKind>,
+    /// 文本文件中的行号（从1开始）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    pub text: String,
+    /// `text`中命中的字节范围，用于高亮
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<Range<usize>>,
+    /// 上下文：Atlas为命中行所在的整个页面或区域块，Skel附件为所属皮肤与插槽
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub context: Vec<String>,
+}
+
+/// 一次搜索的结果
+#[derive(Debug, Default)]
+pub struct SearchResults {
+    pub matches: Vec<SearchMatch>,
+    /// 搜索的文件数，为0表示目录中没有该类文件
+    pub searched: usize,
+    /// 无法读取或解析的文件
+    pub failures: Vec<LoadFailure>,
+}
+
+impl SearchResults {
+    fn collect(searched: usize, found: Vec<(PathBuf, Result<Option<SearchMatch>>)>) -> Self {
+        let mut results = SearchResults { searched, ..Default::default() };
+        for (path, result) in found {
+            match result {
+                Ok(Some(found)) => results.matches.push(found),
+                Ok(None) => {}
+                Err(e) => results.failures.push(LoadFailure { path, error: format!("{:#}", e) }),
+            }
+        }
+        results
+    }
+}
+
+/// Skel搜索选项
+#[derive(Debug, Clone, Default)]
+pub struct SkelSearchOptions {
+    /// 按搜索词在全部Skel中的稀有程度加权（越少见的词权重越高）
+    pub weighted: bool,
+    /// 最低匹配得分（0~1），低于该值的文件不列出
+    pub min_score: f64,
+    pub scope: SearchScope,
+}
+
+/// 每个搜索词最多保留的命中详情数量
+const MAX_DETAILS_PER_TERM: usize = 20;
+
+/// 编译搜索词，重复的搜索词只保留一个
+///
+/// 返回`(显示用搜索词, 编译结果)`，普通子串的显示用搜索词为其小写形式。
+fn compile_terms(search_texts: &[&str], scope: &SearchScope) -> Result<Vec<(String, NamePattern)>> {
+    let mut terms: Vec<(String, NamePattern)> = Vec::new();
+    for text in search_texts {
+        let pattern = NamePattern::new(scope.mode, text)?;
+        let text = match &pattern {
+            NamePattern::Literal(lower) => lower.clone(),
+            _ => text.to_string(),
+        };
+        if !terms.iter().any(|(existing, _)| *existing == text) {
+            terms.push((text, pattern));
+        }
+    }
+    Ok(terms)
+}
 
 /// 搜索Atlas文件内容
-pub fn search_atlas_content(dir_path: &Path, search_text: &str) -> Result<Vec<PathBuf>> {
+///
+/// 默认在全文中查找子串；指定正则、通配符或名称类别时只匹配页面名与区域名。
+pub fn search_atlas_content(dir_path: &Path, search_text: &str, scope: &SearchScope, reporter: &dyn Reporter) -> Result<SearchResults> {
     let files: Vec<PathBuf> = WalkDir::new(dir_path)
         .into_iter()
         .filter_map(|e| e.ok())
@@ -21,46 +133,90 @@ pub fn search_atlas_content(dir_path: &Path, search_text: &str) -> Result<Vec<Pa
         .collect();
 
     if files.is_empty() {
-        println!("📁 在目录 {:?} 中未找到 .atlas 文件", dir_path);
-        return Ok(Vec::new());
+        return Ok(SearchResults::default());
     }
 
-    let pb = ProgressBar::new(files.len() as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}")
-            .unwrap()
-            .progress_chars("#>-")
-    );
-    pb.set_message("搜索Atlas文件中...");
-
-    let results: Vec<PathBuf> = files.par_iter()
-        .filter_map(|file_path| {
-            pb.inc(1);
-            match search_single_atlas_file(file_path, search_text) {
-                Ok(true) => Some(file_path.clone()),
-                Ok(false) => None,
-                Err(e) => {
-                    eprintln!("搜索文件 {:?} 时出错: {}", file_path, e);
-                    None
-                }
-            }
+    let terms = compile_terms(&[search_text], scope)?;
+    let (search_text, pattern) = &terms[0];
+
+    reporter.started("搜索Atlas文件", files.len());
+
+    let found: Vec<(PathBuf, Result<Option<SearchMatch>>)> = files.par_iter()
+        .map(|file_path| {
+            let found = search_single_atlas_file(file_path, search_text, pattern, scope);
+            report_search(reporter, file_path, &found);
+            (file_path.clone(), found)
         })
         .collect();
 
-    pb.finish_with_message("Atlas搜索完成");
-    Ok(results)
+    reporter.finished("搜索Atlas文件", "Atlas搜索完成");
+    Ok(SearchResults::collect(files.len(), found))
+}
+
+/// 按单个文件的搜索结果发送进度事件
+fn report_search(reporter: &dyn Reporter, file_path: &Path, found: &Result<Option<SearchMatch>>) {
+    match found {
+        Ok(Some(_)) => reporter.processed(file_path, None),
+        Ok(None) => reporter.skipped(file_path, "未命中"),
+        Err(e) => reporter.error(file_path, &format!("{:#}", e)),
+    }
 }
 
-fn search_single_atlas_file(file_path: &Path, search_text: &str) -> Result<bool> {
+fn search_single_atlas_file(file_path: &Path, search_text: &str, pattern: &NamePattern, scope: &SearchScope) -> Result<Option<SearchMatch>> {
     let content = fs::read_to_string(file_path)
         .context("无法读取Atlas文件")?;
 
-    Ok(content.to_lowercase().contains(search_text))
+    let lines: Vec<&str> = content.lines().collect();
+    let mut details = Vec::new();
+
+    // 同一页面或区域块只记录第一处命中，并附上整个块作为上下文
+    for block in atlas_blocks(&content) {
+        if details.len() >= MAX_DETAILS_PER_TERM {
+            break;
+        }
+        let block_lines = &lines[block.start..block.end];
+        let offset = if scope.by_name() {
+            if !scope.includes(block.kind) || !pattern.is_match(&block.name) {
+                continue;
+            }
+            0
+        } else {
+            let Some(offset) = block_lines.iter().position(|line| pattern.is_match(line)) else {
+                continue;
+            };
+            offset
+        };
+        let text = block_lines[offset].trim();
+        details.push(MatchDetail {
+            term: search_text.to_string(),
+            kind: Some(block.kind),
+            line: Some(block.start + offset + 1),
+            text: text.to_string(),
+            span: pattern.find(text),
+            context: block_lines.iter().map(|line| line.trim_end().to_string()).collect(),
+        });
+    }
+
+    if details.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(SearchMatch {
+        path: file_path.to_path_buf(),
+        display_name: None,
+        restored: None,
+        terms: vec![search_text.to_string()],
+        missing_terms: Vec::new(),
+        score: 1.0,
+        details,
+    }))
 }
 
 /// 搜索Skel文件内容
-pub fn search_skel_content(dir_path: &Path, search_texts: &[&str]) -> Result<Vec<PathBuf>> {
+///
+/// 只要命中任一搜索词即计入结果，按匹配得分从高到低排列。
+/// 默认在原始内容中查找子串；指定正则、通配符或名称类别时只匹配解析出的名称。
+pub fn search_skel_content(dir_path: &Path, search_texts: &[&str], options: &SkelSearchOptions, reporter: &dyn Reporter) -> Result<SearchResults> {
     let files: Vec<PathBuf> = WalkDir::new(dir_path)
         .into_iter()
         .filter_map(|e| e.ok())
@@ -75,60 +231,163 @@ pub fn search_skel_content(dir_path: &Path, search_texts: &[&str]) -> Result<Vec
         .collect();
 
     if files.is_empty() {
-        println!("📁 在目录 {:?} 中未找到 .skel 文件", dir_path);
-        return Ok(Vec::new());
+        return Ok(SearchResults::default());
     }
 
-    let pb = ProgressBar::new(files.len() as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}")
-            .unwrap()
-            .progress_chars("#>-")
-    );
-    pb.set_message("搜索Skel文件中...");
-
-    let search_texts_lower: Vec<String> = search_texts.iter()
-        .map(|s| s.to_lowercase())
-        .collect();
+    let terms = compile_terms(search_texts, &options.scope)?;
 
-    let results: Vec<PathBuf> = files.par_iter()
-        .filter_map(|file_path| {
-            pb.inc(1);
-            match search_single_skel_file(file_path, &search_texts_lower) {
-                Ok(true) => Some(file_path.clone()),
-                Ok(false) => None,
-                Err(e) => {
-                    eprintln!("搜索文件 {:?} 时出错: {}", file_path, e);
-                    None
-                }
-            }
+    reporter.started("搜索Skel文件", files.len());
+
+    let found: Vec<(PathBuf, Result<Option<SearchMatch>>)> = files.par_iter()
+        .map(|file_path| {
+            let found = search_single_skel_file(file_path, &terms, &options.scope);
+            report_search(reporter, file_path, &found);
+            (file_path.clone(), found)
         })
         .collect();
 
-    pb.finish_with_message("Skel搜索完成");
+    reporter.finished("搜索Skel文件", "Skel搜索完成");
+
+    let mut results = SearchResults::collect(files.len(), found);
+    let weights = term_weights(&terms, &results.matches, files.len(), options.weighted);
+    let total_weight: f64 = weights.values().sum();
+    for result in &mut results.matches {
+        let matched: f64 = result.terms.iter().map(|term| weights[term]).sum();
+        result.score = matched / total_weight;
+    }
+
+    results.matches.retain(|result| result.score >= options.min_score);
+    results.matches.sort_by(|a, b| {
+        b.score.total_cmp(&a.score)
+            .then_with(|| b.terms.len().cmp(&a.terms.len()))
+            .then_with(|| a.path.cmp(&b.path))
+    });
     Ok(results)
 }
 
-fn search_single_skel_file(file_path: &Path, search_texts: &[String]) -> Result<bool> {
-    // Skel文件可能是二进制或文本，先尝试UTF-8解码
-    let content = match fs::read_to_string(file_path) {
-        Ok(content) => content,
-        Err(_) => {
-            // 如果UTF-8解码失败，尝试读取为字节并查找ASCII字符串
-            let bytes = fs::read(file_path).context("无法读取Skel文件")?;
+/// 计算各搜索词的权重
+///
+/// 不加权时每个词权重均为1；加权时使用平滑的逆文档频率，`ln((N+1)/(df+1)) + 1`，
+/// 其中N为搜索的Skel文件数，df为包含该词的文件数。
+fn term_weights(terms: &[(String, NamePattern)], results: &[SearchMatch], file_count: usize, weighted: bool) -> HashMap<String, f64> {
+    terms.iter()
+        .map(|(term, _)| {
+            let weight = if weighted {
+                let df = results.iter().filter(|result| result.terms.contains(term)).count();
+                ((file_count + 1) as f64 / (df + 1) as f64).ln() + 1.0
+            } else {
+                1.0
+            };
+            (term.clone(), weight)
+        })
+        .collect()
+}
+
+fn search_single_skel_file(file_path: &Path, search_terms: &[(String, NamePattern)], scope: &SearchScope) -> Result<Option<SearchMatch>> {
+    let bytes = fs::read(file_path).context("无法读取Skel文件")?;
 
-            // 将字节数据转换为字符串，忽略无效的UTF-8序列
-            String::from_utf8_lossy(&bytes).to_string()
-        }
+    // Skel文件可能是二进制或文本，忽略无效的UTF-8序列
+    let content = String::from_utf8_lossy(&bytes);
+
+    // 按名称匹配时必须能解析；按原始内容匹配时，能解析才按骨骼、插槽、附件、动画等名称给出命中详情
+    let skeleton = if scope.by_name() {
+        Some(parse_skel(&bytes)?)
+    } else {
+        parse_skel(&bytes).ok()
     };
+    let names: Vec<(NameKind, &str)> = skeleton.as_ref()
+        .map(|s| s.names())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|&(kind, _)| scope.includes(kind))
+        .collect();
+    let content_lower = if scope.by_name() { String::new() } else { content.to_lowercase() };
+
+    let mut terms = Vec::new();
+    let mut missing_terms = Vec::new();
+    let mut details = Vec::new();
+    for (search_text, pattern) in search_terms {
+        let name_details = name_details(&names, skeleton.as_ref(), search_text, pattern);
+        let found = if scope.by_name() {
+            !name_details.is_empty()
+        } else {
+            content_lower.contains(search_text.as_str())
+        };
+        if !found {
+            missing_terms.push(search_text.clone());
+            continue;
+        }
+
+        terms.push(search_text.clone());
+        if name_details.is_empty() {
+            details.extend(raw_string_details(&content, search_text, pattern));
+        } else {
+            details.extend(name_details);
+        }
+    }
+
+    // 一个搜索词都未命中时跳过
+    if terms.is_empty() {
+        return Ok(None);
+    }
 
-    // 检查是否包含所有搜索文本
-    for search_text in search_texts {
-        if !content.to_lowercase().contains(search_text) {
-            return Ok(false);
+    // 得分在汇总全部文件后计算
+    Ok(Some(SearchMatch {
+        path: file_path.to_path_buf(),
+        display_name: None,
+        restored: None,
+        terms,
+        missing_terms,
+        score: 0.0,
+        details,
+    }))
+}
+
+/// 在解析出的名称中查找搜索词，附件附上所属皮肤与插槽
+fn name_details(names: &[(NameKind, &str)], skeleton: Option<&Skeleton>, search_text: &str, pattern: &NamePattern) -> Vec<MatchDetail> {
+    names.iter()
+        .filter(|&&(_, name)| pattern.is_match(name))
+        .take(MAX_DETAILS_PER_TERM)
+        .map(|&(kind, name)| {
+            let context = match (kind, skeleton) {
+                (NameKind::Attachment, Some(skeleton)) => skeleton.attachments.iter()
+                    .filter(|a| a.name == name || a.path.as_deref() == Some(name))
+                    .map(|a| format!("{} / {}", a.skin, a.slot))
+                    .collect(),
+                _ => Vec::new(),
+            };
+            MatchDetail {
+                term: search_text.to_string(),
+                kind: Some(kind),
+                line: None,
+                text: name.to_string(),
+                span: pattern.find(name),
+                context,
+            }
+        })
+        .collect()
+}
+
+/// 在无法解析的二进制内容中，以控制字符分隔出包含搜索词的字符串
+fn raw_string_details(content: &str, search_text: &str, pattern: &NamePattern) -> Vec<MatchDetail> {
+    let mut seen: Vec<&str> = Vec::new();
+    for string in content.split(|c: char| c.is_control() || c == char::REPLACEMENT_CHARACTER) {
+        if seen.len() >= MAX_DETAILS_PER_TERM {
+            break;
+        }
+        if string.to_lowercase().contains(search_text) && !seen.contains(&string) {
+            seen.push(string);
         }
     }
 
-    Ok(true)
+    seen.into_iter()
+        .map(|string| MatchDetail {
+            term: search_text.to_string(),
+            kind: None,
+            line: None,
+            text: string.to_string(),
+            span: pattern.find(string),
+            context: Vec::new(),
+        })
+        .collect()
 }
@@ -0,0 +1,487 @@
+use anyhow::{Result, Context};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use unpack_sgzhxdl_cli::bundle::{assemble_bundle, default_page_pngs};
+use unpack_sgzhxdl_cli::catalog::{entry_from_dir, Catalog};
+use unpack_sgzhxdl_cli::dictionary::Dictionary;
+use unpack_sgzhxdl_cli::imaging::{decode_rgba, downscale, encode_png_to};
+use unpack_sgzhxdl_cli::matching::rank_skeletons;
+use unpack_sgzhxdl_cli::workspace::Workspace;
+
+const THUMB_SIZE: u32 = 256;
+/// 请求内容的上限，界面只会发送很短的JSON
+const MAX_BODY_SIZE: u64 = 64 * 1024;
+
+/// 本地浏览界面的应答
+struct Reply {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Reply {
+    fn json(value: Value) -> Self {
+        Self { status: 200, content_type: "application/json; charset=utf-8", body: value.to_string().into_bytes() }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self { status, ..Self::json(json!({ "error": message })) }
+    }
+
+    fn png(body: Vec<u8>) -> Self {
+        Self { status: 200, content_type: "image/png", body }
+    }
+}
+
+#[derive(Deserialize)]
+struct BundleRequest {
+    name: String,
+    atlas: usize,
+    skel: usize,
+    /// 每个Atlas页面选用的PNG下标
+    pngs: Vec<usize>,
+}
+
+struct App {
+    workspace: Workspace,
+    /// 列表中附上的中文显示名称
+    dictionary: Dictionary,
+    /// 允许的来源，例如 "127.0.0.1:8765" 与 "localhost:8765"
+    hosts: [String; 2],
+    output_dir: PathBuf,
+    /// 组装完成的角色记入该目录文件
+    catalog_path: PathBuf,
+    thumbnails: HashMap<usize, Vec<u8>>,
+}
+
+/// 启动仅监听127.0.0.1的本地HTTP服务，直到进程退出
+pub fn serve(workspace: Workspace, dictionary: Dictionary, output_dir: &Path, catalog_path: &Path, port: u16) -> Result<()> {
+    let address = format!("127.0.0.1:{}", port);
+    let server = Server::http(&address)
+        .map_err(|e| anyhow::anyhow!("无法监听 {}: {}", address, e))?;
+
+    println!("🌐 本地界面已启动: http://{}/", address);
+    println!("按 Ctrl+C 退出");
+
+    let mut app = App {
+        workspace,
+        dictionary,
+        hosts: [address.clone(), format!("localhost:{}", port)],
+        output_dir: output_dir.to_path_buf(),
+        catalog_path: catalog_path.to_path_buf(),
+        thumbnails: HashMap::new(),
+    };
+
+    for mut request in server.incoming_requests() {
+        let reply = if let Err(message) = app.check_origin(&request) {
+            Reply::error(403, message)
+        } else {
+            match read_body(&mut request) {
+                Ok(body) => app.handle(request.method(), request.url(), &body),
+                Err(reply) => reply,
+            }
+        };
+        if let Err(e) = respond(request, reply) {
+            eprintln!("发送响应时出错: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// 读取请求内容，超过`MAX_BODY_SIZE`时返回413
+fn read_body(request: &mut Request) -> Result<String, Reply> {
+    let too_large = || Reply::error(413, "请求内容过大");
+    if request.body_length().is_some_and(|length| length as u64 > MAX_BODY_SIZE) {
+        return Err(too_large());
+    }
+    let mut body = String::new();
+    request.as_reader().take(MAX_BODY_SIZE + 1).read_to_string(&mut body)
+        .map_err(|_| Reply::error(400, "无法读取请求内容"))?;
+    if body.len() as u64 > MAX_BODY_SIZE {
+        return Err(too_large());
+    }
+    Ok(body)
+}
+
+fn respond(request: Request, reply: Reply) -> Result<()> {
+    let header = Header::from_bytes(&b"Content-Type"[..], reply.content_type.as_bytes())
+        .map_err(|_| anyhow::anyhow!("无效的响应头"))?;
+    let response = Response::from_data(reply.body)
+        .with_status_code(reply.status)
+        .with_header(header);
+    request.respond(response)?;
+    Ok(())
+}
+
+impl App {
+    /// 只监听127.0.0.1并不能阻止其他网页借用户的浏览器发来请求：
+    /// `Host`必须是本服务的地址（防止DNS重绑定），带`Origin`的写请求必须来自本页面（防止跨站POST）
+    fn check_origin(&self, request: &Request) -> Result<(), &'static str> {
+        let header = |name: &'static str| {
+            request.headers().iter()
+                .find(|header| header.field.equiv(name))
+                .map(|header| header.value.as_str())
+        };
+
+        match header("Host") {
+            Some(host) if self.hosts.iter().any(|allowed| allowed.eq_ignore_ascii_case(host)) => {}
+            _ => return Err("不允许的Host"),
+        }
+        if *request.method() != Method::Get {
+            if let Some(origin) = header("Origin") {
+                let same_origin = self.hosts.iter()
+                    .any(|allowed| origin.eq_ignore_ascii_case(&format!("http://{}", allowed)));
+                if !same_origin {
+                    return Err("不允许跨站请求");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 附上词典中显示名称的文件名
+    fn display_name(&self, path: &Path) -> String {
+        self.dictionary.annotate(path, file_name(path))
+    }
+
+    fn handle(&mut self, method: &Method, url: &str, body: &str) -> Reply {
+        let path = url.split('?').next().unwrap_or("");
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+        let result = match (method, segments.as_slice()) {
+            (Method::Get, []) => Ok(Reply { status: 200, content_type: "text/html; charset=utf-8", body: INDEX_HTML.as_bytes().to_vec() }),
+            (Method::Get, ["api", "pngs"]) => Ok(self.list_pngs()),
+            (Method::Get, ["api", "pngs", id]) => self.png_detail(id),
+            (Method::Get, ["api", "pngs", id, "thumb"]) => self.png_thumbnail(id),
+            (Method::Get, ["api", "pngs", id, "image"]) => self.png_image(id),
+            (Method::Get, ["api", "atlases", id]) => self.atlas_detail(id),
+            (Method::Post, ["api", "bundles"]) => self.create_bundle(body),
+            _ => return Reply::error(404, "未找到"),
+        };
+
+        result.unwrap_or_else(|e| Reply::error(400, &format!("{:#}", e)))
+    }
+
+    fn list_pngs(&self) -> Reply {
+        let mut page_sizes: HashMap<(u32, u32), usize> = HashMap::new();
+        for atlas in &self.workspace.atlases {
+            for page in &atlas.pages {
+                *page_sizes.entry((page.width, page.height)).or_default() += 1;
+            }
+        }
+
+        let pngs: Vec<Value> = self.workspace.pngs.iter()
+            .enumerate()
+            .map(|(id, png)| json!({
+                "id": id,
+                "name": self.display_name(&png.path),
+                "width": png.width,
+                "height": png.height,
+                "pages": page_sizes.get(&(png.width, png.height)).copied().unwrap_or(0),
+            }))
+            .collect();
+        Reply::json(json!(pngs))
+    }
+
+    fn png_detail(&self, id: &str) -> Result<Reply> {
+        let id = parse_index(id, self.workspace.pngs.len())?;
+        let png = &self.workspace.pngs[id];
+
+        let mut pages = Vec::new();
+        for (atlas_id, atlas) in self.workspace.atlases.iter().enumerate() {
+            for (page_id, page) in atlas.pages.iter().enumerate() {
+                if (page.width, page.height) == (png.width, png.height) {
+                    pages.push(json!({
+                        "atlas": atlas_id,
+                        "atlas_name": self.display_name(&atlas.path),
+                        "page": page_id,
+                        "page_name": page.name,
+                    }));
+                }
+            }
+        }
+
+        Ok(Reply::json(json!({
+            "id": id,
+            "name": self.display_name(&png.path),
+            "width": png.width,
+            "height": png.height,
+            "pages": pages,
+        })))
+    }
+
+    fn png_thumbnail(&mut self, id: &str) -> Result<Reply> {
+        let id = parse_index(id, self.workspace.pngs.len())?;
+        if let Some(data) = self.thumbnails.get(&id) {
+            return Ok(Reply::png(data.clone()));
+        }
+
+        let image = decode_rgba(&self.workspace.pngs[id].path)?;
+        let mut data = Vec::new();
+        encode_png_to(&downscale(&image, THUMB_SIZE), &mut data)?;
+        self.thumbnails.insert(id, data.clone());
+        Ok(Reply::png(data))
+    }
+
+    fn png_image(&self, id: &str) -> Result<Reply> {
+        let id = parse_index(id, self.workspace.pngs.len())?;
+        let data = fs::read(&self.workspace.pngs[id].path).context("无法读取PNG文件")?;
+        Ok(Reply::png(data))
+    }
+
+    fn atlas_detail(&self, id: &str) -> Result<Reply> {
+        let id = parse_index(id, self.workspace.atlases.len())?;
+        let atlas = &self.workspace.atlases[id];
+        let defaults = default_page_pngs(atlas, &self.workspace.pngs);
+
+        let pages: Vec<Value> = atlas.pages.iter()
+            .zip(defaults)
+            .map(|(page, default)| {
+                let candidates: Vec<usize> = self.workspace.pngs.iter()
+                    .enumerate()
+                    .filter(|(_, png)| (png.width, png.height) == (page.width, page.height))
+                    .map(|(i, _)| i)
+                    .collect();
+                json!({
+                    "name": page.name,
+                    "width": page.width,
+                    "height": page.height,
+                    "candidates": candidates,
+                    "default": default,
+                })
+            })
+            .collect();
+
+        let skels: Vec<Value> = rank_skeletons(atlas, &self.workspace.skeletons)
+            .into_iter()
+            .map(|(skel_id, hits)| {
+                let skeleton = &self.workspace.skeletons[skel_id];
+                json!({
+                    "id": skel_id,
+                    "name": self.display_name(&skeleton.path),
+                    "version": skeleton.version,
+                    "hits": hits,
+                    "total": skeleton.region_paths.len(),
+                })
+            })
+            .collect();
+
+        Ok(Reply::json(json!({
+            "id": id,
+            "name": self.display_name(&atlas.path),
+            "format": atlas.format,
+            "regions": atlas.regions.len(),
+            "pages": pages,
+            "skels": skels,
+        })))
+    }
+
+    fn create_bundle(&self, body: &str) -> Result<Reply> {
+        let request: BundleRequest = serde_json::from_str(body).context("无效的请求内容")?;
+        let atlas = self.workspace.atlases.get(request.atlas)
+            .ok_or_else(|| anyhow::anyhow!("无效的Atlas编号: {}", request.atlas))?;
+        let skeleton = self.workspace.skeletons.get(request.skel)
+            .ok_or_else(|| anyhow::anyhow!("无效的Skel编号: {}", request.skel))?;
+
+        let page_pngs = request.pngs.iter()
+            .map(|&id| {
+                self.workspace.pngs.get(id)
+                    .map(|png| png.path.clone())
+                    .ok_or_else(|| anyhow::anyhow!("无效的PNG编号: {}", id))
+            })
+            .collect::<Result<Vec<PathBuf>>>()?;
+
+        let bundle = assemble_bundle(
+            &self.output_dir,
+            &request.name,
+            atlas,
+            skeleton,
+            &page_pngs,
+        )?;
+
+        println!("✅ 已组装角色: {}", bundle.dir.display());
+        for warning in &bundle.compatibility.warnings {
+            println!("⚠️  {}", warning);
+        }
+        // 文件已复制完成，记入目录失败时只给出警告
+        if let Err(e) = self.record_in_catalog(&bundle.dir, request.name.trim()) {
+            eprintln!("⚠️  无法记入角色目录: {:#}", e);
+        }
+
+        let files: Vec<String> = bundle.files.iter().map(|f| file_name(f)).collect();
+        Ok(Reply::json(json!({
+            "dir": bundle.dir.display().to_string(),
+            "files": files,
+            "compatibility": bundle.compatibility,
+        })))
+    }
+
+    fn record_in_catalog(&self, dir: &Path, name: &str) -> Result<()> {
+        let mut catalog = Catalog::load(&self.catalog_path)?;
+        catalog.add(entry_from_dir(dir, name, None)?)?;
+        catalog.save(&self.catalog_path)
+    }
+}
+
+fn parse_index(text: &str, len: usize) -> Result<usize> {
+    text.parse::<usize>()
+        .ok()
+        .filter(|&i| i < len)
+        .ok_or_else(|| anyhow::anyhow!("无效的编号: {}", text))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("?")
+        .to_string()
+}
+
+const INDEX_HTML: &str = r#"<!DOCTYPE html>
+<html lang="zh-CN">
+<head>
+<meta charset="utf-8">
+<title>三幻立绘还原</title>
+<style>
+body { font-family: sans-serif; margin: 0; background: #1e1f22; color: #ddd; display: flex; height: 100vh; }
+#list { flex: 3; overflow-y: auto; padding: 16px; }
+#detail { flex: 2; overflow-y: auto; padding: 16px; background: #2b2d31; border-left: 1px solid #444; }
+.toolbar { margin-bottom: 12px; display: flex; gap: 12px; align-items: center; }
+.grid { display: flex; flex-wrap: wrap; gap: 8px; }
+.card { width: 140px; background: #2b2d31; padding: 6px; border-radius: 4px; cursor: pointer; font-size: 12px; word-break: break-all; }
+.card.active { outline: 2px solid #8ab4f8; }
+.card img, .page img { display: block; max-width: 100%; max-height: 128px; margin: 0 auto 4px;
+  background: repeating-conic-gradient(#555 0% 25%, #444 0% 50%) 50% / 16px 16px; }
+.muted { color: #999; }
+.match { color: #8fc98f; }
+.page { background: #1e1f22; padding: 8px; margin: 8px 0; border-radius: 4px; }
+.skel { display: block; padding: 2px 0; }
+button, input, select { font-size: 14px; }
+a { color: #8ab4f8; cursor: pointer; }
+#result { margin-top: 12px; white-space: pre-wrap; }
+</style>
+</head>
+<body>
+<div id="list">
+  <div class="toolbar">
+    <input id="filter" placeholder="按尺寸或文件名筛选，例如 2017x1937">
+    <label><input type="checkbox" id="matched"> 仅显示匹配Atlas页面的图片</label>
+    <span id="count" class="muted"></span>
+  </div>
+  <div id="grid" class="grid"></div>
+</div>
+<div id="detail"><p class="muted">点击左侧图片查看匹配的Atlas页面</p></div>
+<script>
+let pngs = [];
+const el = (tag, props = {}, ...children) => {
+  const node = Object.assign(document.createElement(tag), props);
+  for (const child of children) node.append(child);
+  return node;
+};
+const api = async (url, options) => {
+  const res = await fetch(url, options);
+  const data = await res.json();
+  if (!res.ok) throw new Error(data.error || res.statusText);
+  return data;
+};
+
+function renderList() {
+  const filter = document.getElementById('filter').value.trim().toLowerCase();
+  const matched = document.getElementById('matched').checked;
+  const grid = document.getElementById('grid');
+  grid.replaceChildren();
+  let shown = 0;
+  for (const png of pngs) {
+    const size = `${png.width}x${png.height}`;
+    if (matched && png.pages === 0) continue;
+    if (filter && !size.includes(filter) && !png.name.toLowerCase().includes(filter)) continue;
+    if (++shown > 500) break;
+    const card = el('div', { className: 'card', onclick: () => showPng(png.id, card) },
+      el('img', { src: `/api/pngs/${png.id}/thumb`, loading: 'lazy' }),
+      el('b', { textContent: size }), el('br'), png.name, el('br'),
+      el('span', { className: png.pages ? 'match' : 'muted', textContent: `匹配 ${png.pages} 个Atlas页面` }));
+    grid.append(card);
+  }
+  document.getElementById('count').textContent = `共 ${pngs.length} 张，显示 ${Math.min(shown, 500)} 张`;
+}
+
+async function showPng(id, card) {
+  document.querySelectorAll('.card.active').forEach(c => c.classList.remove('active'));
+  card.classList.add('active');
+  const png = await api(`/api/pngs/${id}`);
+  const detail = document.getElementById('detail');
+  detail.replaceChildren(
+    el('h3', { textContent: `${png.name} (${png.width}x${png.height})` }),
+    el('a', { href: `/api/pngs/${id}/image`, target: '_blank', textContent: '查看原图' }));
+  if (png.pages.length === 0) {
+    detail.append(el('p', { className: 'muted', textContent: '没有尺寸匹配的Atlas页面' }));
+    return;
+  }
+  detail.append(el('h4', { textContent: '匹配的Atlas页面' }));
+  for (const page of png.pages) {
+    detail.append(el('div', {},
+      el('a', { textContent: `${page.atlas_name} / ${page.page_name}`, onclick: () => showAtlas(page.atlas, id) })));
+  }
+}
+
+async function showAtlas(atlasId, pngId) {
+  const atlas = await api(`/api/atlases/${atlasId}`);
+  const detail = document.getElementById('detail');
+  detail.replaceChildren(el('h3', { textContent: atlas.name }),
+    el('p', { className: 'muted', textContent: `${atlas.format} 格式，${atlas.pages.length} 个页面，${atlas.regions} 个区域` }));
+
+  const selects = atlas.pages.map(page => {
+    const select = el('select');
+    for (const id of page.candidates) {
+      select.append(el('option', { value: id, textContent: pngs[id].name, selected: id === pngId || (id === page.default && !page.candidates.includes(pngId)) }));
+    }
+    const preview = el('img');
+    const update = () => { preview.src = select.value ? `/api/pngs/${select.value}/thumb` : ''; };
+    select.onchange = update;
+    update();
+    detail.append(el('div', { className: 'page' },
+      el('b', { textContent: `${page.name} (${page.width}x${page.height})` }), el('br'),
+      page.candidates.length ? select : el('span', { className: 'muted', textContent: '没有同尺寸的PNG' }),
+      preview));
+    return select;
+  });
+
+  detail.append(el('h4', { textContent: '候选Skel（按附件命中率排序）' }));
+  if (atlas.skels.length === 0) detail.append(el('p', { className: 'muted', textContent: '没有Skel引用该Atlas的区域' }));
+  atlas.skels.forEach((skel, i) => {
+    detail.append(el('label', { className: 'skel' },
+      el('input', { type: 'radio', name: 'skel', value: skel.id, checked: i === 0 }),
+      ` ${skel.name} `, el('span', { className: skel.hits === skel.total ? 'match' : 'muted', textContent: `${skel.hits}/${skel.total} · Spine ${skel.version}` })));
+  });
+
+  const name = el('input', { placeholder: '角色名，例如 SP孙策' });
+  const result = el('div', { id: 'result' });
+  const button = el('button', { textContent: '组装到输出目录', onclick: async () => {
+    const skel = document.querySelector('input[name=skel]:checked');
+    if (!skel) { result.textContent = '请先选择Skel'; return; }
+    try {
+      const bundle = await api('/api/bundles', { method: 'POST', body: JSON.stringify({
+        name: name.value, atlas: atlasId, skel: Number(skel.value), pngs: selects.map(s => Number(s.value)) }) });
+      const warnings = bundle.compatibility.warnings.map(w => `⚠️ ${w}\n`).join('');
+      result.textContent = `✅ 已组装到 ${bundle.dir}\n${warnings}${bundle.files.join('\n')}`;
+    } catch (e) {
+      result.textContent = `❌ ${e.message}`;
+    }
+  } });
+  detail.append(el('h4', { textContent: '组装角色' }), name, ' ', button, result);
+}
+
+document.getElementById('filter').oninput = renderList;
+document.getElementById('matched').onchange = renderList;
+api('/api/pngs').then(data => { pngs = data; renderList(); });
+</script>
+</body>
+</html>
+"#;
@@ -0,0 +1,294 @@
+use anyhow::{Result, Context};
+use std::collections::btree_map::{BTreeMap, Entry};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::catalog::today;
+use crate::file_operations::file_hash;
+
+/// 每个目录中记录改名来源的文件
+pub const SIDECAR_FILE: &str = "names.json";
+
+/// 目录中文件的改名记录
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct NameSidecar {
+    pub entries: Vec<NameRecord>,
+}
+
+/// 一个文件的原始名称与来源
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NameRecord {
+    /// 游戏中的原始文件名（通常是hash）
+    pub original: String,
+    /// 当前文件名
+    pub name: String,
+    /// 第一次改名时的内容MD5
+    pub hash: String,
+    /// 原始文件所在的目录
+    pub source: PathBuf,
+    /// 最后一次改名或移动的日期（UTC，YYYY-MM-DD）
+    pub renamed_at: String,
+}
+
+/// 一次改名或移动
+#[derive(Debug, Clone)]
+pub struct Rename {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+impl NameSidecar {
+    /// 读取目录中的记录，文件不存在时返回空记录
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(SIDECAR_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path).context("无法读取改名记录")?;
+        serde_json::from_str(&content).with_context(|| format!("改名记录格式错误: {:?}", path))
+    }
+
+    /// 写入目录；文件都已移走时删除记录文件
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let path = dir.join(SIDECAR_FILE);
+        if self.entries.is_empty() {
+            if path.exists() {
+                fs::remove_file(&path).context("无法删除改名记录")?;
+            }
+            return Ok(());
+        }
+        let mut content = serde_json::to_string_pretty(self)?;
+        content.push('\n');
+        fs::write(path, content).context("无法写入改名记录")
+    }
+
+    /// 当前名为`name`的文件的记录
+    pub fn find(&self, name: &str) -> Option<&NameRecord> {
+        self.entries.iter().find(|record| record.name == name)
+    }
+
+    fn take(&mut self, name: &str) -> Option<NameRecord> {
+        let index = self.entries.iter().position(|record| record.name == name)?;
+        Some(self.entries.remove(index))
+    }
+}
+
+/// 将一批改名写入各目录的names.json
+///
+/// 已有记录的文件沿用最初的原始名、hash与来源目录；移动到其他目录时记录随文件移动。
+pub fn record_renames(renames: &[Rename]) -> Result<()> {
+    if renames.is_empty() {
+        return Ok(());
+    }
+
+    // 计算hash需要读取整个文件，先并行算好
+    let hashes: Vec<Option<String>> = renames.par_iter()
+        .map(|rename| file_hash(&rename.to).ok())
+        .collect();
+
+    let mut sidecars: BTreeMap<PathBuf, NameSidecar> = BTreeMap::new();
+    let date = today();
+    for (rename, hash) in renames.iter().zip(hashes) {
+        let (Some(from_name), Some(to_name)) = (file_name(&rename.from), file_name(&rename.to)) else {
+            continue;
+        };
+        let from_dir = rename.from.parent().unwrap_or(Path::new("."));
+        let to_dir = rename.to.parent().unwrap_or(Path::new("."));
+
+        let record = match sidecar_for(&mut sidecars, from_dir)?.take(&from_name) {
+            Some(previous) => NameRecord { name: to_name.clone(), renamed_at: date.clone(), ..previous },
+            None => NameRecord {
+                original: from_name,
+                name: to_name.clone(),
+                hash: hash.unwrap_or_default(),
+                source: fs::canonicalize(from_dir).unwrap_or_else(|_| from_dir.to_path_buf()),
+                renamed_at: date.clone(),
+            },
+        };
+        let target = sidecar_for(&mut sidecars, to_dir)?;
+        target.entries.retain(|existing| existing.name != to_name);
+        target.entries.push(record);
+    }
+
+    for (dir, sidecar) in &sidecars {
+        sidecar.save(dir)?;
+    }
+    Ok(())
+}
+
+fn sidecar_for<'a>(sidecars: &'a mut BTreeMap<PathBuf, NameSidecar>, dir: &Path) -> Result<&'a mut NameSidecar> {
+    Ok(match sidecars.entry(dir.to_path_buf()) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => entry.insert(NameSidecar::load(dir)?),
+    })
+}
+
+fn file_name(path: &Path) -> Option<String> {
+    path.file_name().and_then(|n| n.to_str()).map(str::to_string)
+}
+
+/// 读取各文件所在目录的names.json，返回 当前路径 -> 游戏中的原始文件名；没有记录的文件（未改过名）不列出
+pub fn original_names<'a>(paths: impl IntoIterator<Item = &'a Path>) -> HashMap<PathBuf, String> {
+    let mut sidecars: BTreeMap<PathBuf, NameSidecar> = BTreeMap::new();
+    let mut originals = HashMap::new();
+    for path in paths {
+        let (Some(dir), Some(name)) = (path.parent(), file_name(path)) else {
+            continue;
+        };
+        let sidecar = sidecars.entry(dir.to_path_buf())
+            .or_insert_with(|| NameSidecar::load(dir).unwrap_or_default());
+        if let Some(record) = sidecar.find(&name) {
+            originals.insert(path.to_path_buf(), record.original.clone());
+        }
+    }
+    originals
+}
+
+/// 在`roots`下所有names.json中查找原始名为`original`的记录，返回 (所在目录, 记录)
+pub fn find_by_original(roots: &[&Path], original: &str) -> Vec<(PathBuf, NameRecord)> {
+    let mut found = Vec::new();
+    for root in roots {
+        for entry in WalkDir::new(root)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file() && e.file_name() == SIDECAR_FILE)
+        {
+            let dir = entry.path().parent().unwrap_or(root);
+            let Ok(sidecar) = NameSidecar::load(dir) else {
+                continue;
+            };
+            found.extend(sidecar.entries.into_iter()
+                .filter(|record| record.original.eq_ignore_ascii_case(original))
+                .map(|record| (dir.to_path_buf(), record)));
+        }
+    }
+    found
+}
+
+/// 来源目录中原始文件的现状
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceStatus {
+    /// 来源目录中已没有该文件（通常是原地改名）
+    Missing,
+    /// 与记录的hash相同
+    Same,
+    /// 内容已变化，游戏更新过该资源
+    Changed,
+}
+
+impl SourceStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            SourceStatus::Missing => "已不在来源目录",
+            SourceStatus::Same => "仍在来源目录，内容相同",
+            SourceStatus::Changed => "仍在来源目录，但内容已变化（游戏可能更新了该资源）",
+        }
+    }
+}
+
+/// 文件的来源查询结果
+#[derive(Debug, Serialize)]
+pub struct Whence {
+    /// 文件现在的路径
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub record: NameRecord,
+    /// 现在的内容与记录的hash是否不同，文件不存在时为None
+    pub modified: Option<bool>,
+    pub source_status: SourceStatus,
+}
+
+/// 查询文件的来源：`file`存在时读取同目录的names.json，否则视为原始文件名在`roots`下查找
+pub fn whence(file: &Path, roots: &[&Path]) -> Result<Vec<Whence>> {
+    let name = file_name(file).ok_or_else(|| anyhow::anyhow!("无效的文件名: {:?}", file))?;
+    let records = if file.is_file() {
+        let dir = file.parent().unwrap_or(Path::new("."));
+        NameSidecar::load(dir)?.find(&name)
+            .map(|record| vec![(dir.to_path_buf(), record.clone())])
+            .unwrap_or_default()
+    } else {
+        find_by_original(roots, &name)
+    };
+
+    Ok(records.into_iter()
+        .map(|(dir, record)| {
+            let path = dir.join(&record.name);
+            let modified = file_hash(&path).ok().map(|hash| hash != record.hash);
+            let source_status = match file_hash(&record.source.join(&record.original)) {
+                Err(_) => SourceStatus::Missing,
+                Ok(hash) if hash == record.hash => SourceStatus::Same,
+                Ok(_) => SourceStatus::Changed,
+            };
+            Whence { path, record, modified, source_status }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_operations::{execute_renames, plan_extension_restore, plan_organize};
+    use crate::progress::QuietReporter;
+
+    const ORIGINAL: &str = "3f2a9c";
+
+    /// 工作目录中的hash文件先补扩展名，再由organize移入atlas目录
+    fn restore_and_organize(root: &Path) -> (PathBuf, PathBuf) {
+        let (work_dir, atlas_dir) = (root.join("udp"), root.join("atlas"));
+        fs::create_dir_all(&work_dir).unwrap();
+        fs::write(work_dir.join(ORIGINAL), "hero.png\nsize: 2,2\n").unwrap();
+
+        let restored = execute_renames(plan_extension_restore(&work_dir, &QuietReporter), &QuietReporter);
+        assert!(restored.failures.is_empty());
+        let organized = execute_renames(plan_organize(&work_dir, "atlas", &atlas_dir), &QuietReporter);
+        assert!(organized.failures.is_empty());
+        (work_dir, atlas_dir)
+    }
+
+    #[test]
+    fn record_follows_organized_file() {
+        let root = tempfile::tempdir().unwrap();
+        let (work_dir, atlas_dir) = restore_and_organize(root.path());
+
+        // 文件都已移走，工作目录的记录文件被删除
+        assert!(!work_dir.join(SIDECAR_FILE).exists());
+        let sidecar = NameSidecar::load(&atlas_dir).unwrap();
+        assert_eq!(sidecar.entries.len(), 1);
+        let record = &sidecar.entries[0];
+        assert_eq!(record.original, ORIGINAL);
+        assert_eq!(record.name, format!("{}.atlas", ORIGINAL));
+        assert_eq!(record.source, fs::canonicalize(&work_dir).unwrap());
+        assert_eq!(record.hash, file_hash(&atlas_dir.join(&record.name)).unwrap());
+
+        let moved = atlas_dir.join(&record.name);
+        let originals = original_names([moved.as_path()]);
+        assert_eq!(originals.get(&moved).map(String::as_str), Some(ORIGINAL));
+    }
+
+    #[test]
+    fn whence_by_new_and_original_name() {
+        let root = tempfile::tempdir().unwrap();
+        let (work_dir, atlas_dir) = restore_and_organize(root.path());
+        let moved = atlas_dir.join(format!("{}.atlas", ORIGINAL));
+        let roots = [work_dir.as_path(), atlas_dir.as_path()];
+
+        for query in [moved.clone(), PathBuf::from(ORIGINAL)] {
+            let found = whence(&query, &roots).unwrap();
+            assert_eq!(found.len(), 1, "{:?}", query);
+            assert_eq!(found[0].path, moved);
+            assert_eq!(found[0].record.original, ORIGINAL);
+            assert_eq!(found[0].modified, Some(false));
+            assert_eq!(found[0].source_status, SourceStatus::Missing);
+        }
+
+        // 游戏更新后来源目录又出现同名文件
+        fs::write(work_dir.join(ORIGINAL), "changed").unwrap();
+        let found = whence(&moved, &roots).unwrap();
+        assert_eq!(found[0].source_status, SourceStatus::Changed);
+    }
+}
@@ -0,0 +1,317 @@
+use anyhow::{Result, Context};
+use std::path::{Path, PathBuf};
+use std::fs;
+
+use crate::names::NameKind;
+use crate::skel_data::{decode_skeleton_data, decode_skeleton_setup, AttachmentKind, SkeletonData};
+
+/// 解析后的Skel文件（保留名称信息，不保留动画数据）
+#[derive(Debug, Clone)]
+pub struct Skeleton {
+    pub path: PathBuf,
+    pub version: String,
+    pub bones: Vec<String>,
+    pub slots: Vec<String>,
+    /// IK、变换与路径约束
+    pub constraints: Vec<String>,
+    pub skins: Vec<String>,
+    pub attachments: Vec<SkelAttachment>,
+    pub events: Vec<String>,
+    pub animations: Vec<String>,
+    /// 需要从Atlas中查找贴图区域的附件路径（已去重）
+    pub region_paths: Vec<String>,
+}
+
+/// 皮肤中的一个附件
+#[derive(Debug, Clone)]
+pub struct SkelAttachment {
+    pub skin: String,
+    pub slot: String,
+    pub name: String,
+    /// 区域/网格附件使用的贴图路径（与名称相同时为None）
+    pub path: Option<String>,
+}
+
+impl Skeleton {
+    /// 从完整解析的数据中提取名称与贴图路径
+    pub fn from_data(data: &SkeletonData) -> Self {
+        let mut attachments = Vec::new();
+        let mut region_paths = Vec::new();
+        for skin in &data.skins {
+            for attachment in &skin.attachments {
+                let name = attachment.name.clone().unwrap_or_else(|| attachment.placeholder.clone());
+                let (path, sequence) = match &attachment.kind {
+                    AttachmentKind::Region { path, sequence, .. }
+                    | AttachmentKind::Mesh { path, sequence, .. }
+                    | AttachmentKind::LinkedMesh { path, sequence, .. } => (path.clone(), Some(sequence)),
+                    _ => (None, None),
+                };
+                if let Some(sequence) = sequence {
+                    let base = path.as_deref().unwrap_or(&name);
+                    match sequence {
+                        Some(frames) => region_paths.extend((0..frames.count).map(|i| frames.frame_path(base, i))),
+                        None => region_paths.push(base.to_string()),
+                    }
+                }
+                attachments.push(SkelAttachment {
+                    skin: skin.name.clone(),
+                    slot: data.slots[attachment.slot].name.clone(),
+                    path: path.filter(|p| *p != name),
+                    name,
+                });
+            }
+        }
+        region_paths.sort();
+        region_paths.dedup();
+
+        let constraints = data.ik_constraints.iter().map(|c| c.name.clone())
+            .chain(data.transform_constraints.iter().map(|c| c.name.clone()))
+            .chain(data.path_constraints.iter().map(|c| c.name.clone()))
+            .collect();
+
+        Skeleton {
+            path: PathBuf::new(),
+            version: data.version.clone(),
+            bones: data.bones.iter().map(|b| b.name.clone()).collect(),
+            slots: data.slots.iter().map(|s| s.name.clone()).collect(),
+            constraints,
+            skins: data.skins.iter().map(|s| s.name.clone()).collect(),
+            attachments,
+            events: data.events.iter().map(|e| e.name.clone()).collect(),
+            animations: data.animations.iter().map(|a| a.name.clone()).collect(),
+            region_paths,
+        }
+    }
+
+    /// 按类别列出全部名称，附件的贴图路径与名称不同时一并列出
+    pub fn names(&self) -> Vec<(NameKind, &str)> {
+        let mut names: Vec<(NameKind, &str)> = Vec::new();
+        names.extend(self.bones.iter().map(|n| (NameKind::Bone, n.as_str())));
+        names.extend(self.slots.iter().map(|n| (NameKind::Slot, n.as_str())));
+        names.extend(self.constraints.iter().map(|n| (NameKind::Constraint, n.as_str())));
+        names.extend(self.skins.iter().map(|n| (NameKind::Skin, n.as_str())));
+        for attachment in &self.attachments {
+            names.push((NameKind::Attachment, attachment.name.as_str()));
+            if let Some(path) = &attachment.path {
+                names.push((NameKind::Attachment, path.as_str()));
+            }
+        }
+        names.extend(self.events.iter().map(|n| (NameKind::Event, n.as_str())));
+        names.extend(self.animations.iter().map(|n| (NameKind::Animation, n.as_str())));
+
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+/// 支持解析的二进制格式版本
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum Format {
+    #[value(name = "3.8")]
+    V38,
+    #[value(name = "4.0")]
+    V40,
+    #[value(name = "4.1")]
+    V41,
+}
+
+impl Format {
+    /// 按版本号（如 `3.8.99`）的主次版本判断格式
+    pub fn from_version(version: &str) -> Option<Self> {
+        match version.get(..3) {
+            Some("3.8") => Some(Format::V38),
+            Some("4.0") => Some(Format::V40),
+            Some("4.1") => Some(Format::V41),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Format::V38 => "3.8",
+            Format::V40 => "4.0",
+            Format::V41 => "4.1",
+        }
+    }
+
+    /// 转换时写入的完整版本号（各版本最后的补丁版本）
+    pub fn version(self) -> &'static str {
+        match self {
+            Format::V38 => "3.8.99",
+            Format::V40 => "4.0.64",
+            Format::V41 => "4.1.24",
+        }
+    }
+}
+
+// 附件类型，与Spine运行时的AttachmentType顺序一致
+pub const ATTACHMENT_REGION: u8 = 0;
+pub const ATTACHMENT_BOUNDING_BOX: u8 = 1;
+pub const ATTACHMENT_MESH: u8 = 2;
+pub const ATTACHMENT_LINKED_MESH: u8 = 3;
+pub const ATTACHMENT_PATH: u8 = 4;
+pub const ATTACHMENT_POINT: u8 = 5;
+pub const ATTACHMENT_CLIPPING: u8 = 6;
+
+pub const CURVE_LINEAR: u8 = 0;
+pub const CURVE_STEPPED: u8 = 1;
+pub const CURVE_BEZIER: u8 = 2;
+
+/// 读取并解析二进制Skel文件
+pub fn parse_skel_file(file_path: &Path) -> Result<Skeleton> {
+    let data = fs::read(file_path).context("无法读取Skel文件")?;
+
+    let mut skeleton = parse_skel(&data)?;
+    skeleton.path = file_path.to_path_buf();
+    Ok(skeleton)
+}
+
+/// 解析二进制Skel数据，支持Spine 3.8 / 4.0 / 4.1
+pub fn parse_skel(data: &[u8]) -> Result<Skeleton> {
+    Ok(Skeleton::from_data(&decode_skeleton_data(data)?))
+}
+
+/// 只解析骨骼、插槽、约束与皮肤，不读取事件与动画（结果中两者为空）
+pub fn parse_skel_setup(data: &[u8]) -> Result<Skeleton> {
+    Ok(Skeleton::from_data(&decode_skeleton_setup(data)?))
+}
+
+/// Skel文件头
+#[derive(Debug, Clone)]
+pub struct SkelHeader {
+    /// 编辑器导出时写入的hash，文件改名后不变，可作为指纹
+    pub hash: String,
+    /// Spine版本，例如 `3.8.99`
+    pub version: String,
+}
+
+/// 只读取文件头，不要求版本受支持
+pub fn read_skel_header(file_path: &Path) -> Result<SkelHeader> {
+    let data = fs::read(file_path).context("无法读取Skel文件")?;
+    let (_, hash, version) = read_header(&data)?;
+    Ok(SkelHeader { hash, version })
+}
+
+/// 读取文件头中的hash与版本号，返回定位在版本号之后的读取器
+///
+/// 3.x以字符串形式存储hash，4.x则是8字节整数（以十六进制表示），因此依次尝试两种布局。
+pub fn read_header(data: &[u8]) -> Result<(Reader<'_>, String, String)> {
+    let mut reader = Reader::new(data);
+    if let Ok(hash) = reader.read_string() {
+        if let Ok(Some(version)) = reader.read_string() {
+            if version.starts_with("3.") {
+                return Ok((reader, hash.unwrap_or_default(), version));
+            }
+        }
+    }
+
+    let mut reader = Reader::new(data);
+    let hash: String = reader.take(8)?.iter().map(|b| format!("{:02x}", b)).collect();
+    if let Ok(Some(version)) = reader.read_string() {
+        if version.starts_with("4.") {
+            return Ok((reader, hash, version));
+        }
+    }
+
+    anyhow::bail!("无法识别的Skel文件头")
+}
+
+/// Spine二进制格式的底层读取器（大端序）
+pub struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| anyhow::anyhow!("Skel文件意外结束"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    pub fn skip(&mut self, len: usize) -> Result<()> {
+        self.take(len).map(|_| ())
+    }
+
+    pub fn read_byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool> {
+        Ok(self.read_byte()? != 0)
+    }
+
+    pub fn read_short(&mut self) -> Result<i16> {
+        let bytes = self.take(2)?;
+        Ok(i16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub fn read_int(&mut self) -> Result<i32> {
+        let bytes = self.take(4)?;
+        Ok(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub fn read_float(&mut self) -> Result<f32> {
+        Ok(f32::from_bits(self.read_int()? as u32))
+    }
+
+    /// 带符号的变长整数（zigzag编码）
+    pub fn read_signed_varint(&mut self) -> Result<i32> {
+        let value = self.read_varint()? as u32;
+        Ok((value >> 1) as i32 ^ -((value & 1) as i32))
+    }
+
+    /// 变长正整数（每字节7位，最多5字节）
+    pub fn read_varint(&mut self) -> Result<usize> {
+        let mut result: u32 = 0;
+        for shift in (0..35).step_by(7) {
+            let byte = self.read_byte()?;
+            result |= ((byte & 0x7F) as u32) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        Ok(result as usize)
+    }
+
+    pub fn read_string(&mut self) -> Result<Option<String>> {
+        let len = self.read_varint()?;
+        match len {
+            0 => Ok(None),
+            1 => Ok(Some(String::new())),
+            _ => {
+                let bytes = self.take(len - 1)?;
+                Ok(Some(String::from_utf8_lossy(bytes).into_owned()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn setup_parse_matches_full_parse_without_animations() {
+        for bytes in [
+            include_bytes!("../tests/fixtures/spine-3.8.skel").as_slice(),
+            include_bytes!("../tests/fixtures/spine-4.1.skel").as_slice(),
+        ] {
+            let full = parse_skel(bytes).unwrap();
+            let setup = parse_skel_setup(bytes).unwrap();
+            assert_eq!(setup.version, full.version);
+            assert_eq!(setup.skins, full.skins);
+            assert_eq!(setup.region_paths, full.region_paths);
+            assert!(!full.animations.is_empty());
+            assert!(setup.animations.is_empty() && setup.events.is_empty());
+        }
+    }
+}
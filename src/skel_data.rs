@@ -0,0 +1,1349 @@
+use anyhow::{Result, Context};
+use std::fs;
+use std::path::Path;
+
+use crate::skel::{
+    read_header, Format, Reader,
+    ATTACHMENT_BOUNDING_BOX, ATTACHMENT_CLIPPING, ATTACHMENT_LINKED_MESH, ATTACHMENT_MESH,
+    ATTACHMENT_PATH, ATTACHMENT_POINT, ATTACHMENT_REGION, CURVE_BEZIER, CURVE_STEPPED,
+};
+
+/// 完整解析的Skel数据（保留动画数据），用于版本转换
+///
+/// 结构按4.x组织：3.8合并的混合值拆为X/Y两个通道，曲线统一为每个通道一条绝对坐标的贝塞尔曲线。
+#[derive(Debug, Clone)]
+pub struct SkeletonData {
+    pub hash: String,
+    pub version: String,
+    pub format: Format,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// 编辑器附加信息，导出时未勾选“非必要数据”则为None
+    pub nonessential: Option<Nonessential>,
+    pub bones: Vec<BoneData>,
+    pub slots: Vec<SlotData>,
+    pub ik_constraints: Vec<IkConstraintData>,
+    pub transform_constraints: Vec<TransformConstraintData>,
+    pub path_constraints: Vec<PathConstraintData>,
+    /// 默认皮肤（如有）在最前，下标与动画中的皮肤索引一致
+    pub skins: Vec<SkinData>,
+    pub events: Vec<EventData>,
+    pub animations: Vec<AnimationData>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Nonessential {
+    pub fps: f32,
+    pub images: Option<String>,
+    pub audio: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BoneData {
+    pub name: String,
+    pub parent: Option<usize>,
+    pub rotation: f32,
+    pub x: f32,
+    pub y: f32,
+    pub scale_x: f32,
+    pub scale_y: f32,
+    pub shear_x: f32,
+    pub shear_y: f32,
+    pub length: f32,
+    pub transform_mode: usize,
+    pub skin_required: bool,
+    pub color: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SlotData {
+    pub name: String,
+    pub bone: usize,
+    /// RGBA8888
+    pub color: u32,
+    /// RGB888
+    pub dark_color: Option<u32>,
+    pub attachment: Option<String>,
+    pub blend_mode: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct IkConstraintData {
+    pub name: String,
+    pub order: usize,
+    pub skin_required: bool,
+    pub bones: Vec<usize>,
+    pub target: usize,
+    pub mix: f32,
+    pub softness: f32,
+    pub bend_direction: i8,
+    pub compress: bool,
+    pub stretch: bool,
+    pub uniform: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct TransformConstraintData {
+    pub name: String,
+    pub order: usize,
+    pub skin_required: bool,
+    pub bones: Vec<usize>,
+    pub target: usize,
+    pub local: bool,
+    pub relative: bool,
+    pub offset_rotation: f32,
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub offset_scale_x: f32,
+    pub offset_scale_y: f32,
+    pub offset_shear_y: f32,
+    pub mix_rotate: f32,
+    pub mix_x: f32,
+    pub mix_y: f32,
+    pub mix_scale_x: f32,
+    pub mix_scale_y: f32,
+    pub mix_shear_y: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct PathConstraintData {
+    pub name: String,
+    pub order: usize,
+    pub skin_required: bool,
+    pub bones: Vec<usize>,
+    /// 路径所在的插槽
+    pub target: usize,
+    pub position_mode: usize,
+    pub spacing_mode: usize,
+    pub rotate_mode: usize,
+    pub offset_rotation: f32,
+    pub position: f32,
+    pub spacing: f32,
+    pub mix_rotate: f32,
+    pub mix_x: f32,
+    pub mix_y: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct SkinData {
+    pub name: String,
+    pub bones: Vec<usize>,
+    pub ik_constraints: Vec<usize>,
+    pub transform_constraints: Vec<usize>,
+    pub path_constraints: Vec<usize>,
+    pub attachments: Vec<SkinAttachment>,
+}
+
+/// 皮肤中插槽下的一个附件
+#[derive(Debug, Clone)]
+pub struct SkinAttachment {
+    pub slot: usize,
+    /// 皮肤中的占位名
+    pub placeholder: String,
+    /// 附件名，与占位名相同时为None
+    pub name: Option<String>,
+    pub kind: AttachmentKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum AttachmentKind {
+    Region {
+        path: Option<String>,
+        rotation: f32,
+        x: f32,
+        y: f32,
+        scale_x: f32,
+        scale_y: f32,
+        width: f32,
+        height: f32,
+        color: u32,
+        sequence: Option<Sequence>,
+    },
+    BoundingBox {
+        vertices: Vertices,
+        color: Option<u32>,
+    },
+    Mesh {
+        path: Option<String>,
+        color: u32,
+        uvs: Vec<f32>,
+        triangles: Vec<i16>,
+        vertices: Vertices,
+        hull: usize,
+        sequence: Option<Sequence>,
+        edges: Option<Vec<i16>>,
+        size: Option<(f32, f32)>,
+    },
+    LinkedMesh {
+        path: Option<String>,
+        color: u32,
+        skin: Option<String>,
+        parent: Option<String>,
+        /// 是否沿用父网格的变形（4.1起包括序列帧）时间轴
+        inherit_timelines: bool,
+        sequence: Option<Sequence>,
+        size: Option<(f32, f32)>,
+    },
+    Path {
+        closed: bool,
+        constant_speed: bool,
+        vertices: Vertices,
+        lengths: Vec<f32>,
+        color: Option<u32>,
+    },
+    Point {
+        rotation: f32,
+        x: f32,
+        y: f32,
+        color: Option<u32>,
+    },
+    Clipping {
+        end_slot: usize,
+        vertices: Vertices,
+        color: Option<u32>,
+    },
+}
+
+/// 4.1的序列帧
+#[derive(Debug, Clone)]
+pub struct Sequence {
+    pub count: usize,
+    pub start: usize,
+    pub digits: usize,
+    pub setup_index: usize,
+}
+
+impl Sequence {
+    /// 第`index`帧的贴图路径
+    pub fn frame_path(&self, base: &str, index: usize) -> String {
+        format!("{}{:0width$}", base, self.start + index, width = self.digits)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Vertices {
+    /// x, y交替排列
+    Plain(Vec<f32>),
+    /// 每个顶点受若干骨骼影响
+    Weighted(Vec<Vec<BoneWeight>>),
+}
+
+#[derive(Debug, Clone)]
+pub struct BoneWeight {
+    pub bone: usize,
+    pub x: f32,
+    pub y: f32,
+    pub weight: f32,
+}
+
+impl Vertices {
+    pub fn count(&self) -> usize {
+        match self {
+            Vertices::Plain(values) => values.len() / 2,
+            Vertices::Weighted(vertices) => vertices.len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EventData {
+    pub name: String,
+    pub int: i32,
+    pub float: f32,
+    pub string: Option<String>,
+    pub audio: Option<String>,
+    pub volume: f32,
+    pub balance: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimationData {
+    pub name: String,
+    pub slots: Vec<SlotTimelines>,
+    pub bones: Vec<BoneTimelines>,
+    pub ik_constraints: Vec<IkTimeline>,
+    pub transform_constraints: Vec<TransformTimeline>,
+    pub path_constraints: Vec<PathTimelines>,
+    /// 变形与序列帧时间轴（按皮肤、插槽排列）
+    pub attachments: Vec<AttachmentTimeline>,
+    pub draw_order: Vec<DrawOrderKey>,
+    pub events: Vec<EventKey>,
+}
+
+/// 关键帧：时间、各通道的值与到下一帧的曲线
+#[derive(Debug, Clone)]
+pub struct Keyframe {
+    pub time: f32,
+    pub values: Vec<f32>,
+    pub curve: Curve,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Curve {
+    Linear,
+    Stepped,
+    /// 每个通道一组控制点 (cx1, cy1, cx2, cy2)，坐标为绝对的时间与值
+    Bezier(Vec<[f32; 4]>),
+}
+
+/// 两帧之间各通道的取值范围；变形时间轴没有通道值，曲线的值域为0~1
+pub fn channel_ranges(from: &Keyframe, to: &Keyframe) -> Vec<(f32, f32)> {
+    if from.values.is_empty() {
+        return vec![(0.0, 1.0)];
+    }
+    from.values.iter().copied().zip(to.values.iter().copied()).collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct SlotTimelines {
+    pub slot: usize,
+    pub timelines: Vec<SlotTimeline>,
+}
+
+#[derive(Debug, Clone)]
+pub enum SlotTimeline {
+    Attachment(Vec<AttachmentKey>),
+    /// 颜色值为0~1
+    Color { kind: ColorKind, frames: Vec<Keyframe> },
+}
+
+#[derive(Debug, Clone)]
+pub struct AttachmentKey {
+    pub time: f32,
+    pub name: Option<String>,
+}
+
+/// 插槽颜色时间轴类型，顺序与4.x二进制格式一致（3.8只有RGBA与RGBA2）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorKind {
+    Rgba,
+    Rgb,
+    Rgba2,
+    Rgb2,
+    Alpha,
+}
+
+impl ColorKind {
+    pub fn channels(self) -> usize {
+        match self {
+            ColorKind::Rgba => 4,
+            ColorKind::Rgb => 3,
+            ColorKind::Rgba2 => 7,
+            ColorKind::Rgb2 => 6,
+            ColorKind::Alpha => 1,
+        }
+    }
+
+    /// 各通道在RGBA2（亮色rgba + 暗色rgb）中的位置
+    pub fn rgba2_channels(self) -> &'static [usize] {
+        match self {
+            ColorKind::Rgba => &[0, 1, 2, 3],
+            ColorKind::Rgb => &[0, 1, 2],
+            ColorKind::Rgba2 => &[0, 1, 2, 3, 4, 5, 6],
+            ColorKind::Rgb2 => &[0, 1, 2, 4, 5, 6],
+            ColorKind::Alpha => &[3],
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ColorKind::Rgba => "rgba",
+            ColorKind::Rgb => "rgb",
+            ColorKind::Rgba2 => "rgba2",
+            ColorKind::Rgb2 => "rgb2",
+            ColorKind::Alpha => "alpha",
+        }
+    }
+
+    fn from_v4(kind: u8) -> Option<Self> {
+        Some(match kind {
+            1 => ColorKind::Rgba,
+            2 => ColorKind::Rgb,
+            3 => ColorKind::Rgba2,
+            4 => ColorKind::Rgb2,
+            5 => ColorKind::Alpha,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BoneTimelines {
+    pub bone: usize,
+    pub timelines: Vec<BoneTimeline>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BoneTimeline {
+    pub kind: BoneTimelineKind,
+    pub frames: Vec<Keyframe>,
+}
+
+/// 骨骼时间轴类型，顺序与4.x二进制格式一致（3.8只有旋转、位移、缩放与斜切）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoneTimelineKind {
+    Rotate,
+    Translate,
+    TranslateX,
+    TranslateY,
+    Scale,
+    ScaleX,
+    ScaleY,
+    Shear,
+    ShearX,
+    ShearY,
+}
+
+impl BoneTimelineKind {
+    pub const ALL: [BoneTimelineKind; 10] = [
+        BoneTimelineKind::Rotate,
+        BoneTimelineKind::Translate,
+        BoneTimelineKind::TranslateX,
+        BoneTimelineKind::TranslateY,
+        BoneTimelineKind::Scale,
+        BoneTimelineKind::ScaleX,
+        BoneTimelineKind::ScaleY,
+        BoneTimelineKind::Shear,
+        BoneTimelineKind::ShearX,
+        BoneTimelineKind::ShearY,
+    ];
+
+    pub fn channels(self) -> usize {
+        match self {
+            BoneTimelineKind::Translate | BoneTimelineKind::Scale | BoneTimelineKind::Shear => 2,
+            _ => 1,
+        }
+    }
+
+    /// 4.x JSON中的时间轴名称
+    pub fn label(self) -> &'static str {
+        match self {
+            BoneTimelineKind::Rotate => "rotate",
+            BoneTimelineKind::Translate => "translate",
+            BoneTimelineKind::TranslateX => "translatex",
+            BoneTimelineKind::TranslateY => "translatey",
+            BoneTimelineKind::Scale => "scale",
+            BoneTimelineKind::ScaleX => "scalex",
+            BoneTimelineKind::ScaleY => "scaley",
+            BoneTimelineKind::Shear => "shear",
+            BoneTimelineKind::ShearX => "shearx",
+            BoneTimelineKind::ShearY => "sheary",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IkTimeline {
+    pub constraint: usize,
+    /// mix, softness
+    pub frames: Vec<Keyframe>,
+    /// 与`frames`一一对应的不参与插值的设置
+    pub flags: Vec<IkFlags>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct IkFlags {
+    pub bend_direction: i8,
+    pub compress: bool,
+    pub stretch: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct TransformTimeline {
+    pub constraint: usize,
+    /// mixRotate, mixX, mixY, mixScaleX, mixScaleY, mixShearY
+    pub frames: Vec<Keyframe>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PathTimelines {
+    pub constraint: usize,
+    pub timelines: Vec<PathTimeline>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PathTimeline {
+    pub kind: PathTimelineKind,
+    /// position、spacing为单值，mix为 mixRotate, mixX, mixY
+    pub frames: Vec<Keyframe>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathTimelineKind {
+    Position,
+    Spacing,
+    Mix,
+}
+
+#[derive(Debug, Clone)]
+pub struct AttachmentTimeline {
+    pub skin: usize,
+    pub slot: usize,
+    pub attachment: String,
+    pub kind: AttachmentTimelineKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum AttachmentTimelineKind {
+    /// 关键帧不带通道值，顶点偏移与`frames`一一对应
+    Deform { frames: Vec<Keyframe>, offsets: Vec<DeformOffsets> },
+    /// 4.1的序列帧切换
+    Sequence(Vec<SequenceKey>),
+}
+
+/// 从第`start`个分量起的顶点偏移，为空表示回到初始形状
+#[derive(Debug, Clone)]
+pub struct DeformOffsets {
+    pub start: usize,
+    pub vertices: Vec<f32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SequenceKey {
+    pub time: f32,
+    pub mode: u8,
+    pub index: i32,
+    pub delay: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct DrawOrderKey {
+    pub time: f32,
+    pub offsets: Vec<DrawOrderOffset>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DrawOrderOffset {
+    pub slot: usize,
+    pub offset: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct EventKey {
+    pub time: f32,
+    pub event: usize,
+    pub int: i32,
+    pub float: f32,
+    /// 与事件默认值不同时才有
+    pub string: Option<String>,
+    /// 仅带音频的事件有音量与平衡
+    pub volume: f32,
+    pub balance: f32,
+}
+
+/// 读取并完整解析二进制Skel文件（支持Spine 3.8 / 4.0 / 4.1）
+pub fn read_skeleton_data(file_path: &Path) -> Result<SkeletonData> {
+    let data = fs::read(file_path).context("无法读取Skel文件")?;
+    decode_skeleton_data(&data)
+}
+
+/// 完整解析二进制Skel数据
+pub fn decode_skeleton_data(data: &[u8]) -> Result<SkeletonData> {
+    decode(data, false)
+}
+
+/// 只解析到皮肤为止（骨骼、插槽、约束与附件），事件与动画留空；只需名称与贴图路径时比完整解析快得多
+pub fn decode_skeleton_setup(data: &[u8]) -> Result<SkeletonData> {
+    decode(data, true)
+}
+
+fn decode(data: &[u8], setup_only: bool) -> Result<SkeletonData> {
+    let (mut reader, hash, version) = read_header(data)?;
+    let format = Format::from_version(&version)
+        .ok_or_else(|| anyhow::anyhow!("不支持的Spine版本: {}", version))?;
+
+    let mut decoder = Decoder {
+        reader: &mut reader,
+        format,
+        setup_only,
+        strings: Vec::new(),
+    };
+    decoder.read_skeleton(hash, version.clone())
+        .with_context(|| format!("解析Spine {} 数据失败", version))
+}
+
+struct Decoder<'a, 'b> {
+    reader: &'a mut Reader<'b>,
+    format: Format,
+    /// 读完皮肤后停止
+    setup_only: bool,
+    strings: Vec<String>,
+}
+
+impl Decoder<'_, '_> {
+    fn read_skeleton(&mut self, hash: String, version: String) -> Result<SkeletonData> {
+        let mut data = SkeletonData {
+            hash,
+            version,
+            format: self.format,
+            x: self.reader.read_float()?,
+            y: self.reader.read_float()?,
+            width: self.reader.read_float()?,
+            height: self.reader.read_float()?,
+            nonessential: None,
+            bones: Vec::new(),
+            slots: Vec::new(),
+            ik_constraints: Vec::new(),
+            transform_constraints: Vec::new(),
+            path_constraints: Vec::new(),
+            skins: Vec::new(),
+            events: Vec::new(),
+            animations: Vec::new(),
+        };
+        if self.reader.read_bool()? {
+            data.nonessential = Some(Nonessential {
+                fps: self.reader.read_float()?,
+                images: self.reader.read_string()?,
+                audio: self.reader.read_string()?,
+            });
+        }
+        let nonessential = data.nonessential.is_some();
+
+        let count = self.reader.read_varint()?;
+        for _ in 0..count {
+            let value = self.reader.read_string()?.unwrap_or_default();
+            self.strings.push(value);
+        }
+
+        let count = self.reader.read_varint()?;
+        for i in 0..count {
+            let name = self.read_name()?;
+            let parent = if i > 0 { Some(self.reader.read_varint()?) } else { None };
+            data.bones.push(BoneData {
+                name,
+                parent,
+                rotation: self.reader.read_float()?,
+                x: self.reader.read_float()?,
+                y: self.reader.read_float()?,
+                scale_x: self.reader.read_float()?,
+                scale_y: self.reader.read_float()?,
+                shear_x: self.reader.read_float()?,
+                shear_y: self.reader.read_float()?,
+                length: self.reader.read_float()?,
+                transform_mode: self.reader.read_varint()?,
+                skin_required: self.reader.read_bool()?,
+                color: self.read_nonessential_color(nonessential)?,
+            });
+        }
+
+        let count = self.reader.read_varint()?;
+        for _ in 0..count {
+            let name = self.read_name()?;
+            let bone = self.reader.read_varint()?;
+            let color = self.reader.read_int()? as u32;
+            let dark_color = self.reader.read_int()?;
+            data.slots.push(SlotData {
+                name,
+                bone,
+                color,
+                dark_color: (dark_color != -1).then_some(dark_color as u32),
+                attachment: self.read_string_ref()?,
+                blend_mode: self.reader.read_varint()?,
+            });
+        }
+
+        let count = self.reader.read_varint()?;
+        for _ in 0..count {
+            let (name, order, skin_required, bones) = self.read_constraint_head()?;
+            data.ik_constraints.push(IkConstraintData {
+                name,
+                order,
+                skin_required,
+                bones,
+                target: self.reader.read_varint()?,
+                mix: self.reader.read_float()?,
+                softness: self.reader.read_float()?,
+                bend_direction: self.reader.read_byte()? as i8,
+                compress: self.reader.read_bool()?,
+                stretch: self.reader.read_bool()?,
+                uniform: self.reader.read_bool()?,
+            });
+        }
+
+        let count = self.reader.read_varint()?;
+        for _ in 0..count {
+            let (name, order, skin_required, bones) = self.read_constraint_head()?;
+            let target = self.reader.read_varint()?;
+            let local = self.reader.read_bool()?;
+            let relative = self.reader.read_bool()?;
+            let mut offsets = [0.0; 6];
+            for value in &mut offsets {
+                *value = self.reader.read_float()?;
+            }
+            // 3.8: rotateMix, translateMix, scaleMix, shearMix
+            let mixes = if self.format == Format::V38 {
+                let [rotate, translate, scale, shear] = self.read_floats::<4>()?;
+                [rotate, translate, translate, scale, scale, shear]
+            } else {
+                self.read_floats::<6>()?
+            };
+            data.transform_constraints.push(TransformConstraintData {
+                name,
+                order,
+                skin_required,
+                bones,
+                target,
+                local,
+                relative,
+                offset_rotation: offsets[0],
+                offset_x: offsets[1],
+                offset_y: offsets[2],
+                offset_scale_x: offsets[3],
+                offset_scale_y: offsets[4],
+                offset_shear_y: offsets[5],
+                mix_rotate: mixes[0],
+                mix_x: mixes[1],
+                mix_y: mixes[2],
+                mix_scale_x: mixes[3],
+                mix_scale_y: mixes[4],
+                mix_shear_y: mixes[5],
+            });
+        }
+
+        let count = self.reader.read_varint()?;
+        for _ in 0..count {
+            let (name, order, skin_required, bones) = self.read_constraint_head()?;
+            let target = self.reader.read_varint()?;
+            let position_mode = self.reader.read_varint()?;
+            let spacing_mode = self.reader.read_varint()?;
+            let rotate_mode = self.reader.read_varint()?;
+            let [offset_rotation, position, spacing] = self.read_floats::<3>()?;
+            // 3.8: rotateMix, translateMix
+            let [mix_rotate, mix_x, mix_y] = if self.format == Format::V38 {
+                let [rotate, translate] = self.read_floats::<2>()?;
+                [rotate, translate, translate]
+            } else {
+                self.read_floats::<3>()?
+            };
+            data.path_constraints.push(PathConstraintData {
+                name,
+                order,
+                skin_required,
+                bones,
+                target,
+                position_mode,
+                spacing_mode,
+                rotate_mode,
+                offset_rotation,
+                position,
+                spacing,
+                mix_rotate,
+                mix_x,
+                mix_y,
+            });
+        }
+
+        if let Some(skin) = self.read_skin(true, &data)? {
+            data.skins.push(skin);
+        }
+        let count = self.reader.read_varint()?;
+        for _ in 0..count {
+            if let Some(skin) = self.read_skin(false, &data)? {
+                data.skins.push(skin);
+            }
+        }
+        if self.setup_only {
+            return Ok(data);
+        }
+
+        let count = self.reader.read_varint()?;
+        for _ in 0..count {
+            let name = self.read_string_ref()?.unwrap_or_default();
+            let int = self.reader.read_signed_varint()?;
+            let float = self.reader.read_float()?;
+            let string = self.reader.read_string()?;
+            let audio = self.reader.read_string()?;
+            let (volume, balance) = if audio.is_some() {
+                (self.reader.read_float()?, self.reader.read_float()?)
+            } else {
+                (1.0, 0.0)
+            };
+            data.events.push(EventData { name, int, float, string, audio, volume, balance });
+        }
+
+        let count = self.reader.read_varint()?;
+        for _ in 0..count {
+            let name = self.read_name()?;
+            let animation = self.read_animation(name.clone(), &data)
+                .with_context(|| format!("解析动画 {} 失败", name))?;
+            data.animations.push(animation);
+        }
+
+        Ok(data)
+    }
+
+    fn read_constraint_head(&mut self) -> Result<(String, usize, bool, Vec<usize>)> {
+        let name = self.read_name()?;
+        let order = self.reader.read_varint()?;
+        let skin_required = self.reader.read_bool()?;
+        let bones = self.read_indices()?;
+        Ok((name, order, skin_required, bones))
+    }
+
+    fn read_skin(&mut self, default_skin: bool, data: &SkeletonData) -> Result<Option<SkinData>> {
+        let mut skin = SkinData {
+            name: "default".to_string(),
+            bones: Vec::new(),
+            ik_constraints: Vec::new(),
+            transform_constraints: Vec::new(),
+            path_constraints: Vec::new(),
+            attachments: Vec::new(),
+        };
+        let slot_count = if default_skin {
+            let count = self.reader.read_varint()?;
+            if count == 0 {
+                return Ok(None);
+            }
+            count
+        } else {
+            skin.name = self.read_string_ref()?.unwrap_or_default();
+            skin.bones = self.read_indices()?;
+            skin.ik_constraints = self.read_indices()?;
+            skin.transform_constraints = self.read_indices()?;
+            skin.path_constraints = self.read_indices()?;
+            self.reader.read_varint()?
+        };
+
+        let nonessential = data.nonessential.is_some();
+        for _ in 0..slot_count {
+            let slot = self.reader.read_varint()?;
+            if slot >= data.slots.len() {
+                anyhow::bail!("插槽索引越界: {}", slot);
+            }
+            let count = self.reader.read_varint()?;
+            for _ in 0..count {
+                let placeholder = self.read_string_ref()?.unwrap_or_default();
+                let name = self.read_string_ref()?;
+                let kind = self.read_attachment(nonessential)
+                    .with_context(|| format!("解析附件 {} 失败", placeholder))?;
+                skin.attachments.push(SkinAttachment { slot, placeholder, name, kind });
+            }
+        }
+        Ok(Some(skin))
+    }
+
+    fn read_attachment(&mut self, nonessential: bool) -> Result<AttachmentKind> {
+        let kind = self.reader.read_byte()?;
+        Ok(match kind {
+            ATTACHMENT_REGION => {
+                let path = self.read_string_ref()?;
+                let [rotation, x, y, scale_x, scale_y, width, height] = self.read_floats::<7>()?;
+                AttachmentKind::Region {
+                    path,
+                    rotation,
+                    x,
+                    y,
+                    scale_x,
+                    scale_y,
+                    width,
+                    height,
+                    color: self.reader.read_int()? as u32,
+                    sequence: self.read_sequence()?,
+                }
+            }
+            ATTACHMENT_BOUNDING_BOX => {
+                let vertex_count = self.reader.read_varint()?;
+                AttachmentKind::BoundingBox {
+                    vertices: self.read_vertices(vertex_count)?,
+                    color: self.read_nonessential_color(nonessential)?,
+                }
+            }
+            ATTACHMENT_MESH => {
+                let path = self.read_string_ref()?;
+                let color = self.reader.read_int()? as u32;
+                let vertex_count = self.reader.read_varint()?;
+                let uvs = self.read_float_array(vertex_count * 2)?;
+                let triangles = self.read_short_array()?;
+                let vertices = self.read_vertices(vertex_count)?;
+                let hull = self.reader.read_varint()?;
+                let sequence = self.read_sequence()?;
+                let (edges, size) = if nonessential {
+                    let edges = self.read_short_array()?;
+                    let [width, height] = self.read_floats::<2>()?;
+                    (Some(edges), Some((width, height)))
+                } else {
+                    (None, None)
+                };
+                AttachmentKind::Mesh { path, color, uvs, triangles, vertices, hull, sequence, edges, size }
+            }
+            ATTACHMENT_LINKED_MESH => {
+                let path = self.read_string_ref()?;
+                let color = self.reader.read_int()? as u32;
+                let skin = self.read_string_ref()?;
+                let parent = self.read_string_ref()?;
+                let inherit_timelines = self.reader.read_bool()?;
+                let sequence = self.read_sequence()?;
+                let size = if nonessential {
+                    let [width, height] = self.read_floats::<2>()?;
+                    Some((width, height))
+                } else {
+                    None
+                };
+                AttachmentKind::LinkedMesh { path, color, skin, parent, inherit_timelines, sequence, size }
+            }
+            ATTACHMENT_PATH => {
+                let closed = self.reader.read_bool()?;
+                let constant_speed = self.reader.read_bool()?;
+                let vertex_count = self.reader.read_varint()?;
+                let vertices = self.read_vertices(vertex_count)?;
+                let lengths = self.read_float_array(vertex_count / 3)?;
+                AttachmentKind::Path {
+                    closed,
+                    constant_speed,
+                    vertices,
+                    lengths,
+                    color: self.read_nonessential_color(nonessential)?,
+                }
+            }
+            ATTACHMENT_POINT => {
+                let [rotation, x, y] = self.read_floats::<3>()?;
+                AttachmentKind::Point { rotation, x, y, color: self.read_nonessential_color(nonessential)? }
+            }
+            ATTACHMENT_CLIPPING => {
+                let end_slot = self.reader.read_varint()?;
+                let vertex_count = self.reader.read_varint()?;
+                AttachmentKind::Clipping {
+                    end_slot,
+                    vertices: self.read_vertices(vertex_count)?,
+                    color: self.read_nonessential_color(nonessential)?,
+                }
+            }
+            _ => anyhow::bail!("未知的附件类型: {}", kind),
+        })
+    }
+
+    fn read_sequence(&mut self) -> Result<Option<Sequence>> {
+        if self.format < Format::V41 || !self.reader.read_bool()? {
+            return Ok(None);
+        }
+        Ok(Some(Sequence {
+            count: self.reader.read_varint()?,
+            start: self.reader.read_varint()?,
+            digits: self.reader.read_varint()?,
+            setup_index: self.reader.read_varint()?,
+        }))
+    }
+
+    fn read_vertices(&mut self, vertex_count: usize) -> Result<Vertices> {
+        if !self.reader.read_bool()? {
+            return Ok(Vertices::Plain(self.read_float_array(vertex_count * 2)?));
+        }
+        let mut vertices = Vec::with_capacity(vertex_count);
+        for _ in 0..vertex_count {
+            let bone_count = self.reader.read_varint()?;
+            let mut weights = Vec::with_capacity(bone_count);
+            for _ in 0..bone_count {
+                let bone = self.reader.read_varint()?;
+                let [x, y, weight] = self.read_floats::<3>()?;
+                weights.push(BoneWeight { bone, x, y, weight });
+            }
+            vertices.push(weights);
+        }
+        Ok(Vertices::Weighted(vertices))
+    }
+
+    fn read_animation(&mut self, name: String, data: &SkeletonData) -> Result<AnimationData> {
+        let mut animation = AnimationData {
+            name,
+            slots: Vec::new(),
+            bones: Vec::new(),
+            ik_constraints: Vec::new(),
+            transform_constraints: Vec::new(),
+            path_constraints: Vec::new(),
+            attachments: Vec::new(),
+            draw_order: Vec::new(),
+            events: Vec::new(),
+        };
+
+        let count = self.reader.read_varint()?;
+        for _ in 0..count {
+            let slot = self.reader.read_varint()?;
+            let mut timelines = Vec::new();
+            for _ in 0..self.reader.read_varint()? {
+                let kind = self.reader.read_byte()?;
+                let frame_count = self.reader.read_varint()?;
+                timelines.push(self.read_slot_timeline(kind, frame_count)?);
+            }
+            animation.slots.push(SlotTimelines { slot, timelines });
+        }
+
+        let count = self.reader.read_varint()?;
+        for _ in 0..count {
+            let bone = self.reader.read_varint()?;
+            let mut timelines = Vec::new();
+            for _ in 0..self.reader.read_varint()? {
+                let kind = self.reader.read_byte()?;
+                let frame_count = self.reader.read_varint()?;
+                let kind = if self.format == Format::V38 {
+                    match kind {
+                        0 => BoneTimelineKind::Rotate,
+                        1 => BoneTimelineKind::Translate,
+                        2 => BoneTimelineKind::Scale,
+                        3 => BoneTimelineKind::Shear,
+                        _ => anyhow::bail!("未知的骨骼时间轴类型: {}", kind),
+                    }
+                } else {
+                    *BoneTimelineKind::ALL.get(kind as usize)
+                        .ok_or_else(|| anyhow::anyhow!("未知的骨骼时间轴类型: {}", kind))?
+                };
+                let channels = kind.channels();
+                let (frames, _) = self.read_timeline(frame_count, channels, |d| d.read_values(channels), |_| Ok(()))?;
+                timelines.push(BoneTimeline { kind, frames });
+            }
+            animation.bones.push(BoneTimelines { bone, timelines });
+        }
+
+        let count = self.reader.read_varint()?;
+        for _ in 0..count {
+            let constraint = self.reader.read_varint()?;
+            let frame_count = self.reader.read_varint()?;
+            let (frames, flags) = self.read_timeline(frame_count, 2, |d| d.read_values(2), |d| {
+                Ok(IkFlags {
+                    bend_direction: d.reader.read_byte()? as i8,
+                    compress: d.reader.read_bool()?,
+                    stretch: d.reader.read_bool()?,
+                })
+            })?;
+            animation.ik_constraints.push(IkTimeline { constraint, frames, flags });
+        }
+
+        let count = self.reader.read_varint()?;
+        for _ in 0..count {
+            let constraint = self.reader.read_varint()?;
+            let frame_count = self.reader.read_varint()?;
+            let (frames, _) = self.read_timeline(frame_count, 6, |d| {
+                if d.format == Format::V38 {
+                    let [rotate, translate, scale, shear] = d.read_floats::<4>()?;
+                    Ok(vec![rotate, translate, translate, scale, scale, shear])
+                } else {
+                    d.read_values(6)
+                }
+            }, |_| Ok(()))?;
+            animation.transform_constraints.push(TransformTimeline { constraint, frames });
+        }
+
+        let count = self.reader.read_varint()?;
+        for _ in 0..count {
+            let constraint = self.reader.read_varint()?;
+            let mut timelines = Vec::new();
+            for _ in 0..self.reader.read_varint()? {
+                let kind = self.reader.read_byte()?;
+                let frame_count = self.reader.read_varint()?;
+                let (kind, channels) = match kind {
+                    0 => (PathTimelineKind::Position, 1),
+                    1 => (PathTimelineKind::Spacing, 1),
+                    2 => (PathTimelineKind::Mix, 3),
+                    _ => anyhow::bail!("未知的路径约束时间轴类型: {}", kind),
+                };
+                let (frames, _) = self.read_timeline(frame_count, channels, |d| {
+                    if kind == PathTimelineKind::Mix && d.format == Format::V38 {
+                        let [rotate, translate] = d.read_floats::<2>()?;
+                        Ok(vec![rotate, translate, translate])
+                    } else {
+                        d.read_values(channels)
+                    }
+                }, |_| Ok(()))?;
+                timelines.push(PathTimeline { kind, frames });
+            }
+            animation.path_constraints.push(PathTimelines { constraint, timelines });
+        }
+
+        let count = self.reader.read_varint()?;
+        for _ in 0..count {
+            let skin = self.reader.read_varint()?;
+            if skin >= data.skins.len() {
+                anyhow::bail!("皮肤索引越界: {}", skin);
+            }
+            for _ in 0..self.reader.read_varint()? {
+                let slot = self.reader.read_varint()?;
+                for _ in 0..self.reader.read_varint()? {
+                    let attachment = self.read_string_ref()?.unwrap_or_default();
+                    let kind = if self.format >= Format::V41 { self.reader.read_byte()? } else { 0 };
+                    let frame_count = self.reader.read_varint()?;
+                    let kind = match kind {
+                        0 => {
+                            let (frames, offsets) = self.read_timeline(frame_count, 1, |_| Ok(Vec::new()), |d| {
+                                let end = d.reader.read_varint()?;
+                                if end == 0 {
+                                    return Ok(DeformOffsets { start: 0, vertices: Vec::new() });
+                                }
+                                let start = d.reader.read_varint()?;
+                                Ok(DeformOffsets { start, vertices: d.read_float_array(end)? })
+                            })?;
+                            AttachmentTimelineKind::Deform { frames, offsets }
+                        }
+                        1 => {
+                            let mut keys = Vec::with_capacity(frame_count);
+                            for _ in 0..frame_count {
+                                let time = self.reader.read_float()?;
+                                let mode_and_index = self.reader.read_int()?;
+                                keys.push(SequenceKey {
+                                    time,
+                                    mode: (mode_and_index & 0xf) as u8,
+                                    index: mode_and_index >> 4,
+                                    delay: self.reader.read_float()?,
+                                });
+                            }
+                            AttachmentTimelineKind::Sequence(keys)
+                        }
+                        _ => anyhow::bail!("未知的附件时间轴类型: {}", kind),
+                    };
+                    animation.attachments.push(AttachmentTimeline { skin, slot, attachment, kind });
+                }
+            }
+        }
+
+        let count = self.reader.read_varint()?;
+        for _ in 0..count {
+            let time = self.reader.read_float()?;
+            let mut offsets = Vec::new();
+            for _ in 0..self.reader.read_varint()? {
+                offsets.push(DrawOrderOffset {
+                    slot: self.reader.read_varint()?,
+                    offset: self.reader.read_varint()? as u32 as i32,
+                });
+            }
+            animation.draw_order.push(DrawOrderKey { time, offsets });
+        }
+
+        let count = self.reader.read_varint()?;
+        for _ in 0..count {
+            let time = self.reader.read_float()?;
+            let event = self.reader.read_varint()?;
+            let event_data = data.events.get(event)
+                .ok_or_else(|| anyhow::anyhow!("事件索引越界: {}", event))?;
+            let int = self.reader.read_signed_varint()?;
+            let float = self.reader.read_float()?;
+            let string = if self.reader.read_bool()? { self.reader.read_string()? } else { None };
+            let (volume, balance) = if event_data.audio.is_some() {
+                (self.reader.read_float()?, self.reader.read_float()?)
+            } else {
+                (1.0, 0.0)
+            };
+            animation.events.push(EventKey { time, event, int, float, string, volume, balance });
+        }
+
+        Ok(animation)
+    }
+
+    fn read_slot_timeline(&mut self, kind: u8, frame_count: usize) -> Result<SlotTimeline> {
+        if kind == 0 {
+            let mut keys = Vec::with_capacity(frame_count);
+            for _ in 0..frame_count {
+                keys.push(AttachmentKey {
+                    time: self.reader.read_float()?,
+                    name: self.read_string_ref()?,
+                });
+            }
+            return Ok(SlotTimeline::Attachment(keys));
+        }
+
+        let color_kind = if self.format == Format::V38 {
+            match kind {
+                1 => ColorKind::Rgba,
+                2 => ColorKind::Rgba2,
+                _ => anyhow::bail!("未知的插槽时间轴类型: {}", kind),
+            }
+        } else {
+            ColorKind::from_v4(kind).ok_or_else(|| anyhow::anyhow!("未知的插槽时间轴类型: {}", kind))?
+        };
+        let channels = color_kind.channels();
+        let (frames, _) = self.read_timeline(frame_count, channels, |d| {
+            if d.format == Format::V38 {
+                // 3.8以整数存储：亮色RGBA8888，暗色RGB888
+                let mut values = rgba_values(d.reader.read_int()? as u32).to_vec();
+                if color_kind == ColorKind::Rgba2 {
+                    values.extend(rgb_values(d.reader.read_int()? as u32));
+                }
+                return Ok(values);
+            }
+            (0..channels).map(|_| Ok(d.reader.read_byte()? as f32 / 255.0)).collect()
+        }, |_| Ok(()))?;
+        Ok(SlotTimeline::Color { kind: color_kind, frames })
+    }
+
+    /// 读取曲线时间轴，`head`读取与时间一起存储的通道值，`tail`读取每帧其余的数据
+    ///
+    /// 3.8每帧依次为时间、通道值、其余数据与到下一帧的曲线（曲线为0~1的相对坐标，读取后换算为绝对坐标）；
+    /// 4.x先是贝塞尔曲线数量，曲线位于下一帧的通道值之后、其余数据之前。
+    fn read_timeline<T>(
+        &mut self,
+        frame_count: usize,
+        channels: usize,
+        mut head: impl FnMut(&mut Self) -> Result<Vec<f32>>,
+        mut tail: impl FnMut(&mut Self) -> Result<T>,
+    ) -> Result<(Vec<Keyframe>, Vec<T>)> {
+        let mut frames: Vec<Keyframe> = Vec::with_capacity(frame_count);
+        let mut extras = Vec::with_capacity(frame_count);
+
+        if self.format == Format::V38 {
+            let mut legacy = Vec::with_capacity(frame_count);
+            for frame in 0..frame_count {
+                let time = self.reader.read_float()?;
+                frames.push(Keyframe { time, values: head(self)?, curve: Curve::Linear });
+                extras.push(tail(self)?);
+                legacy.push(if frame + 1 < frame_count { self.read_legacy_curve()? } else { LegacyCurve::Linear });
+            }
+            for (i, curve) in legacy.into_iter().enumerate() {
+                if i + 1 < frames.len() {
+                    frames[i].curve = curve.expand(&frames[i], &frames[i + 1]);
+                }
+            }
+            return Ok((frames, extras));
+        }
+
+        self.reader.read_varint()?; // bezierCount
+        for frame in 0..frame_count {
+            let time = self.reader.read_float()?;
+            let values = head(self)?;
+            if frame > 0 {
+                let curve = self.read_curve(channels)?;
+                if let Some(previous) = frames.last_mut() {
+                    previous.curve = curve;
+                }
+            }
+            frames.push(Keyframe { time, values, curve: Curve::Linear });
+            extras.push(tail(self)?);
+        }
+        Ok((frames, extras))
+    }
+
+    fn read_legacy_curve(&mut self) -> Result<LegacyCurve> {
+        Ok(match self.reader.read_byte()? {
+            CURVE_STEPPED => LegacyCurve::Stepped,
+            CURVE_BEZIER => LegacyCurve::Bezier(self.read_floats::<4>()?),
+            _ => LegacyCurve::Linear,
+        })
+    }
+
+    fn read_curve(&mut self, channels: usize) -> Result<Curve> {
+        Ok(match self.reader.read_byte()? {
+            CURVE_STEPPED => Curve::Stepped,
+            CURVE_BEZIER => Curve::Bezier(
+                (0..channels).map(|_| self.read_floats::<4>()).collect::<Result<_>>()?,
+            ),
+            _ => Curve::Linear,
+        })
+    }
+
+    fn read_values(&mut self, count: usize) -> Result<Vec<f32>> {
+        self.read_float_array(count)
+    }
+
+    fn read_floats<const N: usize>(&mut self) -> Result<[f32; N]> {
+        let mut values = [0.0; N];
+        for value in &mut values {
+            *value = self.reader.read_float()?;
+        }
+        Ok(values)
+    }
+
+    fn read_float_array(&mut self, count: usize) -> Result<Vec<f32>> {
+        (0..count).map(|_| self.reader.read_float()).collect()
+    }
+
+    fn read_short_array(&mut self) -> Result<Vec<i16>> {
+        let count = self.reader.read_varint()?;
+        (0..count).map(|_| self.reader.read_short()).collect()
+    }
+
+    fn read_indices(&mut self) -> Result<Vec<usize>> {
+        let count = self.reader.read_varint()?;
+        (0..count).map(|_| self.reader.read_varint()).collect()
+    }
+
+    fn read_nonessential_color(&mut self, nonessential: bool) -> Result<Option<u32>> {
+        if !nonessential {
+            return Ok(None);
+        }
+        Ok(Some(self.reader.read_int()? as u32))
+    }
+
+    fn read_name(&mut self) -> Result<String> {
+        Ok(self.reader.read_string()?.unwrap_or_default())
+    }
+
+    fn read_string_ref(&mut self) -> Result<Option<String>> {
+        let index = self.reader.read_varint()?;
+        if index == 0 {
+            return Ok(None);
+        }
+        self.strings.get(index - 1)
+            .cloned()
+            .map(Some)
+            .ok_or_else(|| anyhow::anyhow!("字符串索引越界: {}", index))
+    }
+}
+
+/// 3.8的曲线：每帧一条，控制点为0~1的相对坐标，作用于全部通道
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LegacyCurve {
+    Linear,
+    Stepped,
+    Bezier([f32; 4]),
+}
+
+impl LegacyCurve {
+    /// 换算为各通道的绝对控制点
+    pub fn expand(self, from: &Keyframe, to: &Keyframe) -> Curve {
+        let [cx1, cy1, cx2, cy2] = match self {
+            LegacyCurve::Linear => return Curve::Linear,
+            LegacyCurve::Stepped => return Curve::Stepped,
+            LegacyCurve::Bezier(control) => control,
+        };
+        let duration = to.time - from.time;
+        Curve::Bezier(
+            channel_ranges(from, to).into_iter()
+                .map(|(v1, v2)| {
+                    let delta = v2 - v1;
+                    [from.time + cx1 * duration, v1 + cy1 * delta, from.time + cx2 * duration, v1 + cy2 * delta]
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Curve {
+    /// 换算为3.8的相对坐标曲线，取第一个值有变化的通道（各通道曲线不同时无法原样表达）
+    pub fn to_legacy(&self, from: &Keyframe, to: &Keyframe) -> LegacyCurve {
+        match self {
+            Curve::Linear => LegacyCurve::Linear,
+            Curve::Stepped => LegacyCurve::Stepped,
+            Curve::Bezier(channels) => {
+                let ranges = channel_ranges(from, to);
+                let index = ranges.iter().position(|(v1, v2)| v1 != v2).unwrap_or(0);
+                match (channels.get(index), ranges.get(index)) {
+                    (Some(control), Some(&range)) => LegacyCurve::Bezier(normalize_control(*control, from.time, to.time, range)),
+                    _ => LegacyCurve::Linear,
+                }
+            }
+        }
+    }
+}
+
+/// 绝对控制点 -> 0~1的相对坐标；值没有变化的通道纵坐标取直线的位置
+pub fn normalize_control(control: [f32; 4], t1: f32, t2: f32, (v1, v2): (f32, f32)) -> [f32; 4] {
+    let [cx1, cy1, cx2, cy2] = control;
+    let duration = t2 - t1;
+    // 3.8要求横坐标在0~1之间
+    let x = |cx: f32| if duration > 0.0 { ((cx - t1) / duration).clamp(0.0, 1.0) } else { 0.0 };
+    let delta = v2 - v1;
+    let (x1, x2) = (x(cx1), x(cx2));
+    if delta == 0.0 {
+        return [x1, x1, x2, x2];
+    }
+    [x1, (cy1 - v1) / delta, x2, (cy2 - v1) / delta]
+}
+
+/// RGBA8888 -> 0~1的四个通道
+pub fn rgba_values(color: u32) -> [f32; 4] {
+    color.to_be_bytes().map(|byte| byte as f32 / 255.0)
+}
+
+/// RGB888 -> 0~1的三个通道
+pub fn rgb_values(color: u32) -> [f32; 3] {
+    let [_, r, g, b] = color.to_be_bytes();
+    [r, g, b].map(|byte| byte as f32 / 255.0)
+}
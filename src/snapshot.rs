@@ -0,0 +1,352 @@
+use anyhow::{Result, Context};
+use clap::ValueEnum;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::file_operations::{detect_file_type, file_hash};
+use crate::progress::Reporter;
+use crate::sidecar::{original_names, SIDECAR_FILE};
+use crate::skel::parse_skel_file;
+use crate::workspace::LoadFailure;
+
+/// 资源类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetKind {
+    Png,
+    Atlas,
+    Skel,
+}
+
+impl AssetKind {
+    const ALL: [AssetKind; 3] = [AssetKind::Png, AssetKind::Atlas, AssetKind::Skel];
+
+    fn from_extension(extension: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.extension().eq_ignore_ascii_case(extension))
+    }
+
+    /// 按文件扩展名判断类型，没有资源扩展名的（如游戏目录中的hash文件名）按文件内容判断
+    fn detect(path: &Path) -> Result<Option<Self>> {
+        if let Some(kind) = path.extension().and_then(|e| e.to_str()).and_then(Self::from_extension) {
+            return Ok(Some(kind));
+        }
+        Ok(detect_file_type(path)?.and_then(Self::from_extension))
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            AssetKind::Png => "png",
+            AssetKind::Atlas => "atlas",
+            AssetKind::Skel => "skel",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AssetKind::Png => "PNG",
+            AssetKind::Atlas => "Atlas",
+            AssetKind::Skel => "Skel",
+        }
+    }
+}
+
+/// 资源目录的快照：每个PNG、Atlas与Skel的内容hash
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub files: Vec<SnapshotFile>,
+    /// 扫描时无法读取的文件，不写入快照
+    #[serde(skip)]
+    pub failures: Vec<LoadFailure>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotFile {
+    /// 相对于快照根目录的路径
+    pub path: PathBuf,
+    pub kind: AssetKind,
+    pub hash: String,
+    pub bytes: u64,
+    /// names.json中记录的游戏原始文件名（改过名的文件才有）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original: Option<String>,
+    /// Skel的Spine版本
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Skel中的皮肤
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skins: Vec<String>,
+}
+
+/// 两个快照之间的差异
+#[derive(Debug, Serialize)]
+pub struct SnapshotDiff {
+    /// 新快照中内容在旧快照里已有的文件数（包括移动、改名或复制的）
+    pub unchanged: usize,
+    pub added: Vec<SnapshotFile>,
+    pub removed: Vec<SnapshotFile>,
+    pub changed: Vec<ChangedFile>,
+}
+
+/// 原始文件名与类型相同、内容不同的文件
+#[derive(Debug, Serialize)]
+pub struct ChangedFile {
+    pub old: SnapshotFile,
+    pub new: SnapshotFile,
+    /// 新版Skel中新增的皮肤
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub new_skins: Vec<String>,
+}
+
+impl Snapshot {
+    /// 并行计算`dirs`中全部PNG、Atlas与Skel的hash，路径记为相对于`root`的形式
+    ///
+    /// 没有扩展名的文件按内容判断类型，其他类型的文件不计入快照。
+    pub fn scan(root: &Path, dirs: &[&Path], reporter: &dyn Reporter) -> Self {
+        let mut candidates: Vec<PathBuf> = Vec::new();
+        let mut seen: HashSet<PathBuf> = HashSet::new();
+        for dir in dirs {
+            for entry in WalkDir::new(dir).into_iter().filter_map(|e| e.ok()) {
+                if entry.file_type().is_file() && entry.file_name() != SIDECAR_FILE && seen.insert(entry.path().to_path_buf()) {
+                    candidates.push(entry.into_path());
+                }
+            }
+        }
+
+        let detected: Vec<Result<Option<(PathBuf, AssetKind)>, LoadFailure>> = candidates.into_par_iter()
+            .map(|path| match AssetKind::detect(&path) {
+                Ok(kind) => Ok(kind.map(|kind| (path, kind))),
+                Err(e) => {
+                    let error = format!("{:#}", e);
+                    reporter.error(&path, &error);
+                    Err(LoadFailure { path, error })
+                }
+            })
+            .collect();
+        let mut paths: Vec<(PathBuf, AssetKind)> = Vec::new();
+        let mut failures: Vec<LoadFailure> = Vec::new();
+        for result in detected {
+            match result {
+                Ok(Some(found)) => paths.push(found),
+                Ok(None) => {}
+                Err(failure) => failures.push(failure),
+            }
+        }
+        let originals = original_names(paths.iter().map(|(path, _)| path.as_path()));
+
+        reporter.started("计算文件hash", paths.len());
+
+        let scanned: Vec<Result<SnapshotFile, LoadFailure>> = paths.par_iter()
+            .map(|(path, kind)| {
+                let hash = match file_hash(path) {
+                    Ok(hash) => hash,
+                    Err(e) => {
+                        let error = format!("{:#}", e);
+                        reporter.error(path, &error);
+                        return Err(LoadFailure { path: path.clone(), error });
+                    }
+                };
+                reporter.processed(path, None);
+                // 无法解析的Skel仍按hash比较，只是没有版本与皮肤信息
+                let skeleton = match kind {
+                    AssetKind::Skel => parse_skel_file(path).ok(),
+                    _ => None,
+                };
+                Ok(SnapshotFile {
+                    path: path.strip_prefix(root).unwrap_or(path).to_path_buf(),
+                    kind: *kind,
+                    hash,
+                    bytes: fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+                    original: originals.get(path).cloned(),
+                    version: skeleton.as_ref().map(|s| s.version.clone()),
+                    skins: skeleton.map(|s| s.skins).unwrap_or_default(),
+                })
+            })
+            .collect();
+
+        reporter.finished("计算文件hash", "hash计算完成");
+
+        let mut files = Vec::new();
+        for result in scanned {
+            match result {
+                Ok(file) => files.push(file),
+                Err(failure) => failures.push(failure),
+            }
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        failures.sort_by(|a, b| a.path.cmp(&b.path));
+        Snapshot { files, failures }
+    }
+
+    /// 读取快照：目录则现场扫描，其余视为保存的快照文件
+    pub fn load(path: &Path, reporter: &dyn Reporter) -> Result<Self> {
+        if path.is_dir() {
+            return Ok(Self::scan(path, &[path], reporter));
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("无法读取快照文件: {:?}", path))?;
+        serde_json::from_str(&content).with_context(|| format!("快照文件格式错误: {:?}", path))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(path, content).context("无法写入快照文件")
+    }
+}
+
+/// 比较两个快照
+///
+/// 先按内容hash配对（移动、改名或复制的文件视为未变化），剩余文件中原始文件名与类型相同的视为修改，其余为新增或删除。
+/// 原始文件名取names.json中的记录，没有记录时取去掉资源扩展名的当前文件名，因此改过名的旧目录也能与新拉取的游戏目录比较。
+pub fn diff_snapshots(old: &Snapshot, new: &Snapshot) -> SnapshotDiff {
+    let old_hashes: HashSet<&str> = old.files.iter().map(|f| f.hash.as_str()).collect();
+    let new_hashes: HashSet<&str> = new.files.iter().map(|f| f.hash.as_str()).collect();
+
+    let (same, new_rest): (Vec<&SnapshotFile>, Vec<&SnapshotFile>) = new.files.iter()
+        .partition(|f| old_hashes.contains(f.hash.as_str()));
+    let unchanged = same.len();
+    let old_rest: Vec<&SnapshotFile> = old.files.iter()
+        .filter(|f| !new_hashes.contains(f.hash.as_str()))
+        .collect();
+
+    // (类型, 原始文件名) -> old_rest中的下标，倒序存放以便按原顺序pop
+    let mut by_name: HashMap<(AssetKind, String), Vec<usize>> = HashMap::new();
+    for (index, file) in old_rest.iter().enumerate().rev() {
+        by_name.entry((file.kind, file.match_name())).or_default().push(index);
+    }
+
+    let mut matched = vec![false; old_rest.len()];
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    for file in new_rest {
+        let same_name = by_name.get_mut(&(file.kind, file.match_name())).and_then(Vec::pop);
+        match same_name {
+            Some(index) => {
+                matched[index] = true;
+                let old = old_rest[index];
+                let new_skins = file.skins.iter()
+                    .filter(|skin| !old.skins.contains(skin))
+                    .cloned()
+                    .collect();
+                changed.push(ChangedFile { old: old.clone(), new: file.clone(), new_skins });
+            }
+            None => added.push(file.clone()),
+        }
+    }
+
+    SnapshotDiff {
+        unchanged,
+        added,
+        removed: old_rest.into_iter()
+            .zip(matched)
+            .filter(|(_, matched)| !matched)
+            .map(|(file, _)| file.clone())
+            .collect(),
+        changed,
+    }
+}
+
+impl SnapshotFile {
+    /// 用于配对的原始文件名（小写，不含资源扩展名）
+    fn match_name(&self) -> String {
+        let name = self.original.clone()
+            .or_else(|| self.path.file_name().and_then(|n| n.to_str()).map(str::to_string))
+            .unwrap_or_default()
+            .to_lowercase();
+        let suffix = format!(".{}", self.kind.extension());
+        match name.strip_suffix(&suffix) {
+            Some(stem) => stem.to_string(),
+            None => name,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::QuietReporter;
+
+    fn file(path: &str, kind: AssetKind, hash: &str, original: Option<&str>) -> SnapshotFile {
+        SnapshotFile {
+            path: PathBuf::from(path),
+            kind,
+            hash: hash.to_string(),
+            bytes: 0,
+            original: original.map(str::to_string),
+            version: None,
+            skins: Vec::new(),
+        }
+    }
+
+    fn paths(files: &[SnapshotFile]) -> Vec<&Path> {
+        files.iter().map(|f| f.path.as_path()).collect()
+    }
+
+    #[test]
+    fn diff_added_removed_changed_moved() {
+        let old = Snapshot {
+            files: vec![
+                file("udp/moved.png", AssetKind::Png, "h1", None),
+                file("atlas/gone.atlas", AssetKind::Atlas, "h2", None),
+                file("skels/role.skel", AssetKind::Skel, "h3", Some("9f3c")),
+            ],
+            failures: Vec::new(),
+        };
+        let new = Snapshot {
+            files: vec![
+                file("udp/sub/renamed.png", AssetKind::Png, "h1", None),
+                file("udp/9f3c", AssetKind::Skel, "h4", None),
+                file("udp/fresh", AssetKind::Png, "h5", None),
+            ],
+            failures: Vec::new(),
+        };
+
+        let diff = diff_snapshots(&old, &new);
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(paths(&diff.added), [Path::new("udp/fresh")]);
+        assert_eq!(paths(&diff.removed), [Path::new("atlas/gone.atlas")]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].old.path, Path::new("skels/role.skel"));
+        assert_eq!(diff.changed[0].new.path, Path::new("udp/9f3c"));
+    }
+
+    #[test]
+    fn diff_pairs_same_name_once() {
+        let old = Snapshot {
+            files: vec![
+                file("a/x.atlas", AssetKind::Atlas, "h1", None),
+                file("b/x.atlas", AssetKind::Atlas, "h2", None),
+            ],
+            failures: Vec::new(),
+        };
+        let new = Snapshot { files: vec![file("udp/x", AssetKind::Atlas, "h3", None)], failures: Vec::new() };
+
+        let diff = diff_snapshots(&old, &new);
+        assert_eq!(diff.changed[0].old.path, Path::new("a/x.atlas"));
+        assert_eq!(paths(&diff.removed), [Path::new("b/x.atlas")]);
+        assert!(diff.added.is_empty());
+    }
+
+    #[test]
+    fn scan_detects_extensionless_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("a1b2"), b"\x89PNG\r\n\x1a\n0000").unwrap();
+        fs::write(root.join("c3d4"), "skeleton.png\nsize: 64,64\n").unwrap();
+        fs::write(root.join("e5f6"), [0u8, 0xff, 0xfe, 0x80]).unwrap();
+        fs::write(root.join("config"), "{\"a\": 1}").unwrap();
+        fs::write(root.join(SIDECAR_FILE), "{\"entries\": []}").unwrap();
+
+        let snapshot = Snapshot::scan(root, &[root], &QuietReporter);
+        let kinds: Vec<(&Path, AssetKind)> = snapshot.files.iter().map(|f| (f.path.as_path(), f.kind)).collect();
+        assert_eq!(kinds, [
+            (Path::new("a1b2"), AssetKind::Png),
+            (Path::new("c3d4"), AssetKind::Atlas),
+            (Path::new("e5f6"), AssetKind::Skel),
+        ]);
+        assert!(snapshot.failures.is_empty());
+    }
+}
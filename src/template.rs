@@ -0,0 +1,123 @@
+use anyhow::Result;
+
+use crate::paths::{has_reserved_chars, sanitize_component};
+
+/// 文件名模板，例如 `size_{w}x{h}`，占位符用花括号包围
+#[derive(Debug, Clone)]
+pub struct NameTemplate {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Text(String),
+    Placeholder(String),
+}
+
+impl NameTemplate {
+    /// 解析模板并检查占位符都在`known`中
+    pub fn parse(source: &str, known: &[&str]) -> Result<Self> {
+        if has_reserved_chars(source) {
+            anyhow::bail!("模板不能包含路径分隔符或特殊字符: {}", source);
+        }
+
+        let mut segments = Vec::new();
+        let mut rest = source;
+        while let Some(start) = rest.find(['{', '}']) {
+            if rest[start..].starts_with('}') {
+                anyhow::bail!("模板中有多余的 '}}': {}", source);
+            }
+            let Some(length) = rest[start..].find('}') else {
+                anyhow::bail!("模板中的 '{{' 没有闭合: {}", source);
+            };
+            let name = &rest[start + 1..start + length];
+            if !known.contains(&name) {
+                let available: Vec<String> = known.iter().map(|k| format!("{{{}}}", k)).collect();
+                anyhow::bail!("未知的占位符 {{{}}}，可用: {}", name, available.join(" "));
+            }
+            if start > 0 {
+                segments.push(Segment::Text(rest[..start].to_string()));
+            }
+            segments.push(Segment::Placeholder(name.to_string()));
+            rest = &rest[start + length + 1..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Text(rest.to_string()));
+        }
+
+        Ok(NameTemplate { source: source.to_string(), segments })
+    }
+
+    /// 模板是否用到了`names`中的任意一个占位符
+    pub fn uses_any(&self, names: &[&str]) -> bool {
+        self.segments.iter().any(|segment| match segment {
+            Segment::Placeholder(name) => names.contains(&name.as_str()),
+            Segment::Text(_) => false,
+        })
+    }
+
+    /// 用`lookup`替换全部占位符，取值中的路径分隔符、Windows不允许的字符与控制字符会被替换为下划线
+    pub fn render(&self, lookup: impl Fn(&str) -> Option<String>) -> Result<String> {
+        let mut result = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => result.push_str(text),
+                Segment::Placeholder(name) => {
+                    let value = lookup(name).ok_or_else(|| anyhow::anyhow!("无法取得占位符 {{{}}} 的值", name))?;
+                    result.push_str(&sanitize_component(&value));
+                }
+            }
+        }
+        if result.trim().is_empty() {
+            anyhow::bail!("模板 {} 生成了空文件名", self.source);
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KNOWN: [&str; 3] = ["w", "h", "orig"];
+
+    fn error(source: &str) -> String {
+        NameTemplate::parse(source, &KNOWN).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn parse_rejects_bad_templates() {
+        let cases = [
+            ("size_{x}", "未知的占位符 {x}，可用: {w} {h} {orig}"),
+            ("size_{w", "没有闭合"),
+            ("size_w}", "多余的 '}'"),
+            ("a/{w}", "路径分隔符"),
+        ];
+        for (source, expected) in cases {
+            let message = error(source);
+            assert!(message.contains(expected), "{}: {}", source, message);
+        }
+    }
+
+    #[test]
+    fn render_replaces_placeholders_and_reserved_chars() {
+        let template = NameTemplate::parse("{orig}_{w}x{h}", &KNOWN).unwrap();
+        assert!(template.uses_any(&["h"]));
+        assert!(!template.uses_any(&["atlas_page"]));
+
+        let values = |name: &str| match name {
+            "orig" => Some("a/b:c*\n".to_string()),
+            "w" => Some("2".to_string()),
+            "h" => Some("4".to_string()),
+            _ => None,
+        };
+        assert_eq!(template.render(values).unwrap(), "a_b_c___2x4");
+
+        let missing = template.render(|_| None).unwrap_err();
+        assert!(missing.to_string().contains("{orig}"), "{}", missing);
+
+        let empty = NameTemplate::parse("{orig}", &KNOWN).unwrap();
+        assert!(empty.render(|_| Some(" ".to_string())).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use anyhow::Result;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+
+use crate::atlas::{parse_atlas_file, Atlas};
+use crate::compat::check_compatibility;
+use crate::file_operations::{get_png_dimensions, single_file_with_extension};
+use crate::pma::check_atlas_pma;
+use crate::skel_data::{read_skeleton_data, AttachmentKind, SkeletonData};
+
+/// 角色文件夹的校验结果
+#[derive(Debug, Serialize)]
+pub struct ValidationReport {
+    pub dir: PathBuf,
+    pub atlas: PathBuf,
+    pub skel: PathBuf,
+    pub checks: Vec<Check>,
+}
+
+/// 一项检查及其发现的问题，没有问题即为通过
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    /// 检查的对象数（页面、区域或附件）
+    pub checked: usize,
+    pub problems: Vec<String>,
+}
+
+impl Check {
+    pub fn passed(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl ValidationReport {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(Check::passed)
+    }
+}
+
+/// 校验角色文件夹：页面PNG是否存在且尺寸与Atlas一致、区域是否超出页面、
+/// Skel皮肤中的附件能否在Atlas中找到区域、页面像素与Atlas的预乘标记是否一致、Skel版本与Atlas格式是否兼容
+///
+/// 文件夹中应恰好有一个.atlas与一个.skel（即`serve`组装的结构）。
+pub fn validate_folder(dir: &Path) -> Result<ValidationReport> {
+    if !dir.is_dir() {
+        anyhow::bail!("角色文件夹不存在: {:?}", dir);
+    }
+    let atlas_path = single_file_with_extension(dir, "atlas")?;
+    let skel_path = single_file_with_extension(dir, "skel")?;
+    let atlas = parse_atlas_file(&atlas_path)?;
+    let skeleton = read_skeleton_data(&skel_path)?;
+
+    let (pages, page_sizes) = check_pages(dir, &atlas);
+    let compatibility = check_compatibility(&skeleton.version, atlas.format);
+    let checks = vec![
+        pages,
+        check_regions(&atlas, &page_sizes),
+        check_attachments(&atlas, &skeleton),
+        Check {
+            name: "预乘Alpha",
+            checked: atlas.pages.len(),
+            problems: check_atlas_pma(&atlas).iter().filter_map(|page| page.mismatch()).collect(),
+        },
+        Check { name: "版本兼容", checked: 1, problems: compatibility.warnings },
+    ];
+
+    Ok(ValidationReport {
+        dir: dir.to_path_buf(),
+        atlas: atlas_path,
+        skel: skel_path,
+        checks,
+    })
+}
+
+/// 检查每个页面都有尺寸一致的PNG，返回检查结果与各页面用于区域检查的尺寸
+///
+/// Atlas未声明尺寸的页面以实际PNG尺寸为准。
+fn check_pages(dir: &Path, atlas: &Atlas) -> (Check, Vec<Option<(u32, u32)>>) {
+    let mut problems = Vec::new();
+    let mut sizes = Vec::new();
+    for page in &atlas.pages {
+        let declared = (page.width > 0 && page.height > 0).then_some((page.width, page.height));
+        let png = dir.join(&page.name);
+        if !png.is_file() {
+            problems.push(format!("页面 {} 缺少PNG文件", page.name));
+            sizes.push(declared);
+            continue;
+        }
+        match get_png_dimensions(&png) {
+            Err(e) => {
+                problems.push(format!("页面 {} 的PNG无法读取: {}", page.name, e));
+                sizes.push(declared);
+            }
+            Ok(actual) => {
+                if let Some((width, height)) = declared.filter(|&size| size != actual) {
+                    problems.push(format!("页面 {} 声明为 {}x{}，PNG为 {}x{}", page.name, width, height, actual.0, actual.1));
+                }
+                sizes.push(declared.or(Some(actual)));
+            }
+        }
+    }
+    (Check { name: "页面PNG", checked: atlas.pages.len(), problems }, sizes)
+}
+
+/// 检查每个区域都位于所在页面之内
+fn check_regions(atlas: &Atlas, page_sizes: &[Option<(u32, u32)>]) -> Check {
+    let mut problems = Vec::new();
+    for region in &atlas.regions {
+        let Some(bounds) = region.bounds else {
+            problems.push(format!("区域 {} 缺少位置信息", region.name));
+            continue;
+        };
+        let Some(&Some((page_width, page_height))) = page_sizes.get(region.page) else {
+            continue;
+        };
+        let (width, height) = bounds.packed_size();
+        let (right, bottom) = (bounds.x as u64 + width as u64, bounds.y as u64 + height as u64);
+        if right > page_width as u64 || bottom > page_height as u64 {
+            problems.push(format!(
+                "区域 {} ({}, {}, {}x{}{}) 超出页面 {} 的 {}x{}",
+                region.name, bounds.x, bounds.y, width, height,
+                if bounds.rotated { "，已旋转" } else { "" },
+                atlas.pages[region.page].name, page_width, page_height
+            ));
+        }
+    }
+    Check { name: "区域范围", checked: atlas.regions.len(), problems }
+}
+
+/// 检查Skel各皮肤中的区域、网格附件（含序列帧的每一帧）都能在Atlas中找到区域
+fn check_attachments(atlas: &Atlas, skeleton: &SkeletonData) -> Check {
+    let regions: HashSet<&str> = atlas.regions.iter().map(|region| region.name.as_str()).collect();
+    let mut problems = Vec::new();
+    let mut checked = 0;
+    for skin in &skeleton.skins {
+        for attachment in &skin.attachments {
+            let (path, sequence) = match &attachment.kind {
+                AttachmentKind::Region { path, sequence, .. }
+                | AttachmentKind::Mesh { path, sequence, .. }
+                | AttachmentKind::LinkedMesh { path, sequence, .. } => (path, sequence),
+                _ => continue,
+            };
+            checked += 1;
+            // 路径默认为附件名，附件名默认为占位名
+            let base = path.as_deref()
+                .or(attachment.name.as_deref())
+                .unwrap_or(&attachment.placeholder);
+            let paths: Vec<String> = match sequence {
+                Some(sequence) => (0..sequence.count).map(|i| sequence.frame_path(base, i)).collect(),
+                None => vec![base.to_string()],
+            };
+            let missing: Vec<&str> = paths.iter()
+                .map(String::as_str)
+                .filter(|path| !regions.contains(path))
+                .collect();
+            if !missing.is_empty() {
+                problems.push(format!(
+                    "皮肤 {} / 插槽 {} / 附件 {}: 找不到区域 {}",
+                    skin.name, skeleton.slots[attachment.slot].name, attachment.placeholder, missing.join(", ")
+                ));
+            }
+        }
+    }
+    Check { name: "附件区域", checked, problems }
+}
@@ -0,0 +1,310 @@
+use anyhow::{Result, Context};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use rayon::prelude::*;
+use flate2::read::ZlibDecoder;
+use serde::Serialize;
+
+use crate::file_operations::{collect_files_with_extension, NameReservations, RenamePlan};
+use crate::progress::Reporter;
+use crate::sidecar::Rename;
+
+const PNG_SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+/// 校验失败的PNG
+#[derive(Debug, Serialize)]
+pub struct CorruptPng {
+    pub path: PathBuf,
+    pub reason: String,
+    /// 移入隔离目录后的位置
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quarantined: Option<PathBuf>,
+}
+
+/// 完整性校验结果
+#[derive(Debug, Default, Serialize)]
+pub struct VerifySummary {
+    pub checked: usize,
+    pub corrupt: Vec<CorruptPng>,
+}
+
+/// 并行校验目录下全部PNG
+pub fn verify_pngs(dir: &Path, reporter: &dyn Reporter) -> VerifySummary {
+    let files = collect_files_with_extension(dir, "png");
+
+    reporter.started("校验PNG完整性", files.len());
+
+    let mut corrupt: Vec<CorruptPng> = files.par_iter()
+        .filter_map(|path| {
+            match verify_png(path) {
+                Ok(()) => {
+                    reporter.processed(path, None);
+                    None
+                }
+                Err(e) => {
+                    let reason = format!("{:#}", e);
+                    reporter.error(path, &reason);
+                    Some(CorruptPng { path: path.clone(), reason, quarantined: None })
+                }
+            }
+        })
+        .collect();
+
+    reporter.finished("校验PNG完整性", "PNG校验完成");
+    corrupt.sort_by(|a, b| a.path.cmp(&b.path));
+
+    VerifySummary { checked: files.len(), corrupt }
+}
+
+/// 完整校验一个PNG：逐个检查chunk长度与CRC、解压IDAT确认数据流完整且大小与IHDR一致、以IEND结尾
+pub fn verify_png(file_path: &Path) -> Result<()> {
+    let data = fs::read(file_path).context("无法读取文件")?;
+    if data.len() < PNG_SIGNATURE.len() || data[..8] != PNG_SIGNATURE {
+        anyhow::bail!("PNG签名无效");
+    }
+
+    let mut offset = PNG_SIGNATURE.len();
+    let mut header: Option<ImageHeader> = None;
+    let mut idat: Vec<&[u8]> = Vec::new();
+    let mut ended = false;
+
+    while offset < data.len() {
+        let remaining = data.len() - offset;
+        if remaining < 12 {
+            anyhow::bail!("文件在偏移 {} 处被截断（不足一个chunk头）", offset);
+        }
+        let length = u32::from_be_bytes(data[offset..offset + 4].try_into()?) as usize;
+        let kind = &data[offset + 4..offset + 8];
+        let name = String::from_utf8_lossy(kind).to_string();
+        if length > remaining - 12 {
+            anyhow::bail!("chunk {} 被截断：声明 {} 字节，实际只剩 {} 字节", name, length, remaining - 12);
+        }
+        let body = &data[offset + 8..offset + 8 + length];
+        let stored = u32::from_be_bytes(data[offset + 8 + length..offset + 12 + length].try_into()?);
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(kind);
+        hasher.update(body);
+        if hasher.finalize() != stored {
+            anyhow::bail!("chunk {}（偏移 {}）CRC校验失败", name, offset);
+        }
+
+        match kind {
+            b"IHDR" => header = Some(ImageHeader::parse(body)?),
+            _ if header.is_none() => anyhow::bail!("第一个chunk不是IHDR"),
+            b"IDAT" => idat.push(body),
+            b"IEND" => {
+                ended = true;
+                break;
+            }
+            _ => {}
+        }
+        offset += 12 + length;
+    }
+
+    let header = header.ok_or_else(|| anyhow::anyhow!("缺少IHDR"))?;
+    if idat.is_empty() {
+        anyhow::bail!("缺少IDAT图像数据");
+    }
+    if !ended {
+        anyhow::bail!("缺少IEND（文件可能被截断）");
+    }
+
+    // 只计数不保存，避免大图占用内存；多读一个字节以发现多余的数据
+    let expected = header.raw_size();
+    let mut decoder = ZlibDecoder::new(ChunkReader { chunks: idat, index: 0, position: 0 }).take(expected + 1);
+    let inflated = io::copy(&mut decoder, &mut io::sink()).context("IDAT数据流损坏或不完整")?;
+    if inflated != expected {
+        anyhow::bail!("IDAT解压后为 {} 字节，按IHDR应为 {} 字节", inflated, expected);
+    }
+    Ok(())
+}
+
+/// 计划将损坏的PNG移入隔离目录`quarantine`，保留相对`root`的路径
+///
+/// 执行后原始名称记入names.json，重新复制时可据此找到游戏中的文件。
+pub fn plan_quarantine(root: &Path, corrupt: &[CorruptPng], quarantine: &Path) -> RenamePlan {
+    let mut reservations = NameReservations::default();
+    let renames = corrupt.iter()
+        .map(|png| {
+            let relative = png.path.strip_prefix(root).unwrap_or(&png.path);
+            let target = quarantine.join(relative);
+            let dir = target.parent().unwrap_or(quarantine);
+            let stem = target.file_stem().and_then(|s| s.to_str()).unwrap_or("png");
+            let extension = target.extension().and_then(|s| s.to_str()).unwrap_or("png");
+            Rename { from: png.path.clone(), to: reservations.reserve(dir, stem, extension) }
+        })
+        .collect();
+    RenamePlan { renames, failures: Vec::new() }
+}
+
+struct ImageHeader {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8,
+    interlaced: bool,
+}
+
+impl ImageHeader {
+    fn parse(body: &[u8]) -> Result<Self> {
+        if body.len() != 13 {
+            anyhow::bail!("IHDR长度应为13字节，实际为 {}", body.len());
+        }
+        let header = ImageHeader {
+            width: u32::from_be_bytes(body[0..4].try_into()?),
+            height: u32::from_be_bytes(body[4..8].try_into()?),
+            bit_depth: body[8],
+            color_type: body[9],
+            interlaced: body[12] == 1,
+        };
+        if header.width == 0 || header.height == 0 {
+            anyhow::bail!("IHDR尺寸无效: {}x{}", header.width, header.height);
+        }
+        if header.channels() == 0 || !matches!(header.bit_depth, 1 | 2 | 4 | 8 | 16) {
+            anyhow::bail!("IHDR颜色类型或位深无效: {} / {}", header.color_type, header.bit_depth);
+        }
+        Ok(header)
+    }
+
+    fn channels(&self) -> u64 {
+        match self.color_type {
+            0 | 3 => 1,
+            2 => 3,
+            4 => 2,
+            6 => 4,
+            _ => 0,
+        }
+    }
+
+    /// 解压后的数据大小：每行一个过滤类型字节加像素数据，隔行扫描时为7个子图之和
+    fn raw_size(&self) -> u64 {
+        let bits = self.channels() * self.bit_depth as u64;
+        let size = |width: u64, height: u64| {
+            if width == 0 || height == 0 {
+                0
+            } else {
+                height * (1 + (width * bits).div_ceil(8))
+            }
+        };
+        let (width, height) = (self.width as u64, self.height as u64);
+        if !self.interlaced {
+            return size(width, height);
+        }
+        // Adam7：(起始列, 起始行, 列间隔, 行间隔)
+        [(0, 0, 8, 8), (4, 0, 8, 8), (0, 4, 4, 8), (2, 0, 4, 4), (0, 2, 2, 4), (1, 0, 2, 2), (0, 1, 1, 2)]
+            .iter()
+            .map(|&(x, y, dx, dy)| size((width + dx - 1 - x) / dx, (height + dy - 1 - y) / dy))
+            .sum()
+    }
+}
+
+/// 依次读取多个IDAT的数据
+struct ChunkReader<'a> {
+    chunks: Vec<&'a [u8]>,
+    /// 当前chunk的下标与其中的读取位置
+    index: usize,
+    position: usize,
+}
+
+impl Read for ChunkReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while let Some(chunk) = self.chunks.get(self.index) {
+            if self.position < chunk.len() {
+                let count = buf.len().min(chunk.len() - self.position);
+                buf[..count].copy_from_slice(&chunk[self.position..self.position + count]);
+                self.position += count;
+                return Ok(count);
+            }
+            self.index += 1;
+            self.position = 0;
+        }
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], body: &[u8]) {
+        out.extend((body.len() as u32).to_be_bytes());
+        out.extend(kind);
+        out.extend(body);
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(kind);
+        hasher.update(body);
+        out.extend(hasher.finalize().to_be_bytes());
+    }
+
+    /// RGBA8图像，解压后的数据`raw`按`idat_count`段分别写入IDAT
+    fn png(width: u32, height: u32, interlaced: bool, raw: &[u8], idat_count: usize) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(raw).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut out = PNG_SIGNATURE.to_vec();
+        let mut ihdr = Vec::new();
+        ihdr.extend(width.to_be_bytes());
+        ihdr.extend(height.to_be_bytes());
+        ihdr.extend([8, 6, 0, 0, interlaced as u8]);
+        chunk(&mut out, b"IHDR", &ihdr);
+        for part in compressed.chunks(compressed.len().div_ceil(idat_count)) {
+            chunk(&mut out, b"IDAT", part);
+        }
+        chunk(&mut out, b"IEND", &[]);
+        out
+    }
+
+    fn verify_bytes(bytes: &[u8]) -> Result<()> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.png");
+        fs::write(&path, bytes).unwrap();
+        verify_png(&path)
+    }
+
+    const PIXEL: [u8; 5] = [0, 255, 0, 0, 255];
+
+    #[test]
+    fn valid_png() {
+        verify_bytes(&png(1, 1, false, &PIXEL, 1)).unwrap();
+        verify_bytes(&png(1, 1, false, &PIXEL, 3)).unwrap();
+    }
+
+    #[test]
+    fn truncated_before_iend() {
+        let bytes = png(1, 1, false, &PIXEL, 1);
+        let error = verify_bytes(&bytes[..bytes.len() - 12]).unwrap_err();
+        assert!(error.to_string().contains("IEND"), "{:#}", error);
+    }
+
+    #[test]
+    fn flipped_crc() {
+        let mut bytes = png(1, 1, false, &PIXEL, 1);
+        // IHDR的CRC紧跟在签名、长度、类型与13字节数据之后
+        bytes[8 + 8 + 13] ^= 0x01;
+        let error = verify_bytes(&bytes).unwrap_err();
+        assert!(error.to_string().contains("CRC"), "{:#}", error);
+    }
+
+    #[test]
+    fn wrong_raw_size() {
+        let error = verify_bytes(&png(1, 1, false, &PIXEL[..4], 1)).unwrap_err();
+        assert!(error.to_string().contains("按IHDR应为 5 字节"), "{:#}", error);
+    }
+
+    #[test]
+    fn interlaced_size_uses_adam7() {
+        let header = ImageHeader { width: 3, height: 3, bit_depth: 8, color_type: 6, interlaced: true };
+        // 7个子图：1x1、无、无、1x1、2x1、1x2、3x1，每行加一个过滤类型字节
+        assert_eq!(header.raw_size(), 5 + 5 + 9 + 2 * 5 + 13);
+        let plain = ImageHeader { interlaced: false, ..header };
+        assert_eq!(plain.raw_size(), 3 * 13);
+
+        verify_bytes(&png(3, 3, true, &[0; 42], 2)).unwrap();
+        assert!(verify_bytes(&png(3, 3, true, &[0; 39], 1)).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use std::path::{Path, PathBuf};
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::atlas::{parse_atlas_file, Atlas};
+use crate::file_operations::{collect_files_with_extension, get_png_dimensions};
+use crate::progress::Reporter;
+use crate::skel::{parse_skel_file, Skeleton};
+
+/// 带尺寸信息的PNG文件
+#[derive(Debug, Clone)]
+pub struct PngFile {
+    pub path: PathBuf,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 无法读取或解析的文件
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadFailure {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+/// 工作区中的三类资源：PNG（工作目录）、Atlas与Skel（归类后的目录）
+pub struct Workspace {
+    pub pngs: Vec<PngFile>,
+    pub atlases: Vec<Atlas>,
+    pub skeletons: Vec<Skeleton>,
+    pub failures: Vec<LoadFailure>,
+}
+
+enum Loaded {
+    Png(PngFile),
+    Atlas(Atlas),
+    Skel(Skeleton),
+}
+
+impl Workspace {
+    /// 并行读取全部PNG尺寸并解析Atlas与Skel
+    pub fn load(png_dir: &Path, atlas_dir: &Path, skels_dir: &Path, reporter: &dyn Reporter) -> Self {
+        let mut files: Vec<(PathBuf, &str)> = Vec::new();
+        files.extend(collect_files_with_extension(png_dir, "png").into_iter().map(|p| (p, "png")));
+        files.extend(collect_files_with_extension(atlas_dir, "atlas").into_iter().map(|p| (p, "atlas")));
+        files.extend(collect_files_with_extension(skels_dir, "skel").into_iter().map(|p| (p, "skel")));
+
+        reporter.started("读取资源", files.len());
+
+        let loaded: Vec<Result<Loaded, LoadFailure>> = files.par_iter()
+            .map(|(path, kind)| {
+                let result = match *kind {
+                    "png" => get_png_dimensions(path)
+                        .map(|(width, height)| Loaded::Png(PngFile { path: path.clone(), width, height })),
+                    "atlas" => parse_atlas_file(path).map(Loaded::Atlas),
+                    _ => parse_skel_file(path).map(Loaded::Skel),
+                };
+                match result {
+                    Ok(loaded) => {
+                        reporter.processed(path, None);
+                        Ok(loaded)
+                    }
+                    Err(e) => {
+                        let error = format!("{:#}", e);
+                        reporter.error(path, &error);
+                        Err(LoadFailure { path: path.clone(), error })
+                    }
+                }
+            })
+            .collect();
+
+        reporter.finished("读取资源", "资源读取完成");
+
+        let mut workspace = Workspace {
+            pngs: Vec::new(),
+            atlases: Vec::new(),
+            skeletons: Vec::new(),
+            failures: Vec::new(),
+        };
+        for item in loaded {
+            match item {
+                Ok(Loaded::Png(png)) => workspace.pngs.push(png),
+                Ok(Loaded::Atlas(atlas)) => workspace.atlases.push(atlas),
+                Ok(Loaded::Skel(skeleton)) => workspace.skeletons.push(skeleton),
+                Err(failure) => workspace.failures.push(failure),
+            }
+        }
+
+        workspace
+    }
+}